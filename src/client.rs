@@ -1,8 +1,12 @@
 mod component;
 mod scene;
 
-use crate::engine::{ClientHandler, Managers};
-use std::time::Duration;
+use crate::engine::{
+    graphic::{display, ScalingMode},
+    ClientHandler, Managers,
+};
+use std::{collections::HashSet, time::Duration};
+use winit::keyboard::KeyCode;
 
 /// ゲームを管理するオブジェクト。
 ///
@@ -10,21 +14,208 @@ use std::time::Duration;
 /// ClientHandlerを実装することでエンジンのライフサイクルに組み込める。
 pub struct GameManager {
     scene: scene::Scene,
+    /// フレームの統計情報のオーバーレイ。Noneであれば表示しない。
+    stats_overlay: Option<component::StatsOverlay>,
 }
 
 impl ClientHandler for GameManager {
     fn new(mngrs: &mut Managers) -> Self {
         let scene = scene::load::LoadScene::new(mngrs);
-        Self { scene }
+        Self {
+            scene,
+            stats_overlay: None,
+        }
     }
 
     fn update(&mut self, mngrs: &mut Managers, duration: Duration) -> bool {
-        if let Some(next) = self.scene.update(mngrs, duration) {
-            self.scene = next;
+        let running = self.fixed_update(mngrs, duration);
+        self.render(mngrs, 1.0);
+        running
+    }
+
+    fn fixed_update(&mut self, mngrs: &mut Managers, duration: Duration) -> bool {
+        // F11でフルスクリーンを切り替え
+        if mngrs.in_mngr.just_pressed(&KeyCode::F11) {
+            let is_fullscreen = mngrs.gr_mngr.is_fullscreen();
+            mngrs.gr_mngr.set_fullscreen(!is_fullscreen);
+        }
+        // F3でフレームの統計情報の表示を切り替え
+        if mngrs.in_mngr.just_pressed(&KeyCode::F3) {
+            self.stats_overlay = match self.stats_overlay {
+                Some(_) => None,
+                None => Some(component::StatsOverlay::new("UtsukushiFONT.otf")),
+            };
+        }
+        // F10で整数倍の拡大を切り替え
+        if mngrs.in_mngr.just_pressed(&KeyCode::F10) {
+            let scaling_mode = match mngrs.gr_mngr.get_scaling_mode() {
+                ScalingMode::Fit => ScalingMode::Integer,
+                ScalingMode::Integer => ScalingMode::Fit,
+            };
+            mngrs.gr_mngr.set_scaling_mode(scaling_mode);
         }
 
-        // TODO: どうやって終了を検知しようか。
-        //       どうせグローバルデータ領域を作るので、そこに終了フラグを設けるか。
+        match self.scene.update(mngrs, duration) {
+            Some(scene::SceneCommand::Change(next)) => self.scene = next,
+            Some(scene::SceneCommand::Quit) => return false,
+            None => (),
+        }
         true
     }
+
+    fn render(&mut self, mngrs: &mut Managers, alpha: f32) {
+        // オーバーレイはシーンの描画に加えて描画される
+        if let Some(stats_overlay) = &mut self.stats_overlay {
+            stats_overlay.update(mngrs, self.scene.get_name());
+            let mut chars = HashSet::new();
+            stats_overlay.collect_characters(&mut chars);
+            let should_push_text = mngrs
+                .gr_mngr
+                .load_all_character_images(&mut mngrs.rs_mngr, chars);
+            let mut instances = Vec::new();
+            stats_overlay.push_to(&mut instances, mngrs, should_push_text);
+            mngrs.gr_mngr.push_overlay(instances);
+        }
+
+        self.scene.render(mngrs, alpha);
+    }
+
+    fn shutdown(&mut self, mngrs: &mut Managers) {
+        self.scene.shutdown(mngrs);
+
+        // F11・F10での変更も次回の起動に引き継ぐ
+        if let Err(e) = mngrs
+            .gr_mngr
+            .get_display_settings()
+            .save(display::CONFIG_PATH)
+        {
+            eprintln!("failed to save {}: {e}", display::CONFIG_PATH);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{
+        graphic::backend::GraphicBackend,
+        headless::HeadlessRunner,
+        input::{
+            action::{Action, Binding},
+            gamepad::GamepadButton,
+            replay::InputReplay,
+        },
+        timestep::{TimestepMode, DEFAULT_MAX_FRAME_DURATION},
+        ApplicationInfo,
+    };
+
+    const STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+    fn create_runner() -> HeadlessRunner<GameManager> {
+        HeadlessRunner::new(&ApplicationInfo {
+            title: "test",
+            scene_width: 1280,
+            scene_height: 720,
+            window_width: 1280.0,
+            window_height: 720.0,
+            is_fullscreen: false,
+            scaling_mode: ScalingMode::Fit,
+            replay_mode: None,
+            graphic_backends: GraphicBackend::DEFAULT_ORDER.to_vec(),
+            timestep_mode: TimestepMode::Fixed(STEP),
+            max_frame_duration: DEFAULT_MAX_FRAME_DURATION,
+        })
+        .expect("failed to create a headless runner.")
+    }
+
+    #[test]
+    fn drives_title_to_map() {
+        // NOTE: 終了時に設定ファイルが書き出されるので、close()は呼ばない。
+        let mut runner = create_runner();
+        runner.step(STEP);
+        assert_eq!(runner.client().scene.get_name(), "title");
+
+        // 決定で名前入力へ
+        runner.step_with_keys(STEP, &[KeyCode::KeyZ]);
+        assert_eq!(runner.client().scene.get_name(), "name");
+
+        // 名前を入力し、Enterでマップへ
+        runner.step_with_keys(STEP, &[]);
+        runner.managers().in_mngr.input_text("なまえ");
+        runner.step_with_keys(STEP, &[]);
+        runner.step_with_keys(STEP, &[KeyCode::Enter]);
+        assert_eq!(runner.client().scene.get_name(), "map");
+
+        // マップ上で移動・メッセージ送りをしても続行する
+        let script = [
+            (STEP, &[KeyCode::ArrowRight][..]),
+            (STEP, &[KeyCode::ArrowRight, KeyCode::KeyZ][..]),
+            (STEP, &[][..]),
+            (STEP, &[KeyCode::KeyZ][..]),
+        ];
+        assert_eq!(runner.run_script(script.into_iter().cycle().take(60)), 60);
+        assert!(runner.is_running());
+        assert_eq!(runner.frame(), 65);
+        assert_eq!(runner.client().scene.get_name(), "map");
+        assert!(runner.capture().is_ok());
+    }
+
+    #[test]
+    fn replays_recorded_input() {
+        let path = std::env::temp_dir().join(format!("prototype-{}.replay", std::process::id()));
+
+        // タイトルから名前入力を経てマップに移るまでを記録
+        let mut runner = create_runner();
+        runner.managers().in_mngr.start_recording(&path).unwrap();
+        runner.step(STEP);
+        runner.step_with_keys(STEP, &[KeyCode::KeyZ]);
+        runner.step_with_keys(STEP, &[]);
+        runner.managers().in_mngr.input_text("なまえ");
+        runner.step_with_keys(STEP, &[]);
+        runner.step_with_keys(STEP, &[KeyCode::Enter]);
+        runner.managers().in_mngr.stop_recording();
+        assert_eq!(runner.client().scene.get_name(), "map");
+
+        // 経過時間が端数を残していても、1フレームに1ステップだけ進む
+        let mut runner = create_runner();
+        runner.step(STEP / 2);
+        let replay = InputReplay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(runner.run_replay(replay).unwrap(), 5);
+        assert_eq!(runner.client().scene.get_name(), "map");
+    }
+
+    #[test]
+    fn rebinds_keys_in_options() {
+        // NOTE: キー設定を抜けると設定ファイルが書き出されるので、抜けない。
+        let mut runner = create_runner();
+        runner.step(STEP);
+        runner.step_with_keys(STEP, &[KeyCode::KeyC]);
+        assert_eq!(runner.client().scene.get_name(), "options");
+
+        // 「キー設定」まで下に送って決定
+        for _ in 0..6 {
+            runner.step_with_keys(STEP, &[KeyCode::ArrowDown]);
+            runner.step_with_keys(STEP, &[]);
+        }
+        runner.step_with_keys(STEP, &[KeyCode::KeyZ]);
+        assert_eq!(runner.client().scene.get_name(), "keyconfig");
+
+        // 決定の割り当てをAキーのみにする
+        runner.step_with_keys(STEP, &[]);
+        runner.step_with_keys(STEP, &[KeyCode::KeyZ]);
+        runner.step_with_keys(STEP, &[]);
+        runner.step_with_keys(STEP, &[KeyCode::KeyA]);
+        let action_map = runner.managers().in_mngr.get_action_map();
+        let keys = action_map
+            .get_bindings(Action::Confirm)
+            .iter()
+            .filter(|n| matches!(n, Binding::Key(_)))
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(keys, [Binding::Key(KeyCode::KeyA)]);
+        assert!(action_map
+            .get_bindings(Action::Confirm)
+            .contains(&Binding::Gamepad(GamepadButton::South)));
+    }
 }
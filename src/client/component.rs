@@ -7,7 +7,9 @@ mod components;
 mod maptile;
 mod message;
 mod sprite;
+mod statsoverlay;
 mod text;
+mod textfield;
 
 pub use actor::*;
 pub use camera::*;
@@ -15,11 +17,13 @@ pub use components::*;
 pub use maptile::*;
 pub use message::*;
 pub use sprite::*;
+pub use statsoverlay::*;
 pub use text::*;
+pub use textfield::*;
 
 use crate::{
     engine::{
-        graphic::{pipeline::*, *},
+        graphic::{character::*, pipeline::*, *},
         Managers,
     },
     SCENE_HEIGHT, SCENE_WIDTH,
@@ -52,8 +56,88 @@ pub enum CoordinateSystem {
 }
 
 /// アラインメント(コンポーネントの原点の位置)を指定する列挙型。
-#[derive(Clone, PartialEq, Eq)]
+///
+/// コンポーネントの外接矩形のうち、座標に置かれる点を表す。
+/// 座標系によらず、上下左右は画面上の向きである。
+#[derive(Clone, Copy, PartialEq)]
 pub enum Alignment {
-    Center,
     TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+    /// 任意の点。左上を(0, 0)、右下を(1, 1)とする比で指定する。
+    Pivot(Vec2),
+}
+
+impl Alignment {
+    /// 原点の位置を左上(0, 0)から右下(1, 1)までの比で取得するメソッド。
+    pub fn get_pivot(&self) -> Vec2 {
+        match self {
+            Alignment::TopLeft => Vec2::new(0.0, 0.0),
+            Alignment::Top => Vec2::new(0.5, 0.0),
+            Alignment::TopRight => Vec2::new(1.0, 0.0),
+            Alignment::Left => Vec2::new(0.0, 0.5),
+            Alignment::Center => Vec2::new(0.5, 0.5),
+            Alignment::Right => Vec2::new(1.0, 0.5),
+            Alignment::BottomLeft => Vec2::new(0.0, 1.0),
+            Alignment::Bottom => Vec2::new(0.5, 1.0),
+            Alignment::BottomRight => Vec2::new(1.0, 1.0),
+            Alignment::Pivot(n) => *n,
+        }
+    }
+
+    /// 大きさsizeの矩形について、原点から中心への変位をワールド座標系の向き(上が正)で求めるメソッド。
+    pub fn get_center_offset(&self, size: Vec2) -> Vec2 {
+        let pivot = self.get_pivot();
+        Vec2::new((0.5 - pivot.x) * size.x, (pivot.y - 0.5) * size.y)
+    }
+}
+
+/// 矩形。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    /// 左上の位置。コンポーネントと同じ座標系で表す。
+    pub pos: Vec2,
+    /// 幅・高さ。
+    pub size: Vec2,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn places_pivots_and_centers() {
+        // 幅40・高さ20の矩形について、(アラインメント, 原点の位置の比, 原点から中心への変位)
+        let size = Vec2::new(40.0, 20.0);
+        for (align, pivot, offset) in [
+            (Alignment::TopLeft, (0.0, 0.0), (20.0, -10.0)),
+            (Alignment::Top, (0.5, 0.0), (0.0, -10.0)),
+            (Alignment::TopRight, (1.0, 0.0), (-20.0, -10.0)),
+            (Alignment::Left, (0.0, 0.5), (20.0, 0.0)),
+            (Alignment::Center, (0.5, 0.5), (0.0, 0.0)),
+            (Alignment::Right, (1.0, 0.5), (-20.0, 0.0)),
+            (Alignment::BottomLeft, (0.0, 1.0), (20.0, 10.0)),
+            (Alignment::Bottom, (0.5, 1.0), (0.0, 10.0)),
+            (Alignment::BottomRight, (1.0, 1.0), (-20.0, 10.0)),
+            (
+                Alignment::Pivot(Vec2::new(0.25, 0.75)),
+                (0.25, 0.75),
+                (10.0, 5.0),
+            ),
+            (
+                Alignment::Pivot(Vec2::new(-0.5, 1.5)),
+                (-0.5, 1.5),
+                (40.0, 20.0),
+            ),
+        ] {
+            assert_eq!(align.get_pivot(), Vec2::from(pivot));
+            assert_eq!(align.get_center_offset(size), Vec2::from(offset));
+        }
+    }
 }
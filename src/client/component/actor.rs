@@ -29,6 +29,10 @@ pub struct Actor {
     i: usize,
     j: usize,
     z: f32,
+    /// 座標。
+    pos: Vec3,
+    /// 直前のステップにおける座標。
+    prev_pos: Vec3,
     speed: f32,
     idle_uv: UVFunc,
     moving_uv: UVFunc,
@@ -54,19 +58,18 @@ impl Actor {
         idle_uv: UVFunc,
         moving_uv: UVFunc,
     ) -> Self {
+        let pos = Vec3::new(MAPTILE_SIZE * j as f32, -MAPTILE_SIZE * i as f32, z);
         let sprite = Sprite::new(image_id)
             .with_scl(Vec2::new(MAPTILE_SIZE, MAPTILE_SIZE))
-            .with_pos(Vec3::new(
-                MAPTILE_SIZE * j as f32,
-                -MAPTILE_SIZE * i as f32,
-                z,
-            ))
+            .with_pos(pos)
             .with_uv((idle_uv)(ActorDirection::Down, 0.0));
         Self {
             sprite,
             i,
             j,
             z,
+            pos,
+            prev_pos: pos,
             speed,
             idle_uv,
             moving_uv,
@@ -89,7 +92,14 @@ impl Actor {
 
     /// アクターの座標(Vec3)を取得するメソッド。
     pub fn get_position(&self) -> Vec3 {
-        self.sprite.get_pos()
+        self.pos
+    }
+
+    /// 直前のステップの座標として現在の座標を保存するメソッド。
+    ///
+    /// 描画時の補間に用いるため、ステップの最初に呼ぶ。
+    pub fn save_position(&mut self) {
+        self.prev_pos = self.pos;
     }
 
     /// アイドル状態の向きを変更するメソッド。
@@ -113,14 +123,13 @@ impl Actor {
                 let offset = offset + diff;
                 // まだ移動中？
                 if offset < MAPTILE_SIZE {
-                    let pos = self.sprite.get_pos();
-                    let pos = match direction {
+                    let pos = self.pos;
+                    self.pos = match direction {
                         ActorDirection::Left => pos.with_x(pos.x - diff),
                         ActorDirection::Right => pos.with_x(pos.x + diff),
                         ActorDirection::Up => pos.with_y(pos.y + diff),
                         ActorDirection::Down => pos.with_y(pos.y - diff),
                     };
-                    self.sprite.set_pos(pos);
                     self.sprite
                         .set_uv((self.moving_uv)(direction.clone(), offset / MAPTILE_SIZE));
                     self.state = State::Moving((direction.clone(), offset));
@@ -133,11 +142,11 @@ impl Actor {
                         ActorDirection::Up => self.i -= 1,
                         ActorDirection::Down => self.i += 1,
                     }
-                    self.sprite.set_pos(Vec3::new(
+                    self.pos = Vec3::new(
                         MAPTILE_SIZE * self.j as f32,
                         -MAPTILE_SIZE * self.i as f32,
-                        self.get_position().z,
-                    ));
+                        self.pos.z,
+                    );
                     self.change_direction(direction.clone());
                 }
             }
@@ -145,7 +154,12 @@ impl Actor {
         }
     }
 
-    pub fn push_to(&mut self, instances: &mut Vec<InstanceMeta>) {
+    /// 座標を直前のステップと現在のステップの間でalpha [0, 1) により補間して描画するメソッド。
+    pub fn push_to(&mut self, instances: &mut Vec<InstanceMeta>, alpha: f32) {
+        let pos = self.prev_pos.lerp(self.pos, alpha);
+        if self.sprite.get_pos() != pos {
+            self.sprite.set_pos(pos);
+        }
         self.sprite.push_to(instances);
     }
 }
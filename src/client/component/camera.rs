@@ -9,6 +9,10 @@ use super::*;
 /// 非描画コンポーネント。
 pub struct Camera {
     buffer: BaseCamera,
+    /// 注視している位置。
+    pos: Vec3,
+    /// 直前のステップにおけるpos。
+    prev_pos: Vec3,
 }
 
 impl Camera {
@@ -25,20 +29,30 @@ impl Camera {
                 ),
                 _view: Mat4::IDENTITY,
             },
+            pos: Vec3::ZERO,
+            prev_pos: Vec3::ZERO,
         }
     }
 
-    pub fn get(&self) -> BaseCamera {
-        self.buffer.clone()
+    /// カメラバッファを取得するメソッド。
+    ///
+    /// 位置は直前のステップと現在のステップの間をalpha [0, 1) で補間したものとなる。
+    pub fn get(&self, alpha: f32) -> BaseCamera {
+        let pos = self.prev_pos.lerp(self.pos, alpha);
+        let mut buffer = self.buffer.clone();
+        buffer._view = Mat4::from_translation(Vec3::new(-pos.x, -pos.y, 0.0));
+        buffer
+    }
+
+    /// 直前のステップの位置として現在の位置を保存するメソッド。
+    pub fn save_position(&mut self) {
+        self.prev_pos = self.pos;
     }
 
     /// 座標posにある物体の真上に移動するメソッド。
     ///
     /// WARN: posのz座標は無視される。
-    pub fn chase(&mut self, mut pos: Vec3) {
-        pos.x *= -1.0;
-        pos.y *= -1.0;
-        pos.z = 0.0;
-        self.buffer._view = Mat4::from_translation(pos);
+    pub fn chase(&mut self, pos: Vec3) {
+        self.pos = pos.with_z(0.0);
     }
 }
@@ -12,22 +12,35 @@ pub struct Components {
 }
 
 impl Components {
-    pub fn collect_characters(&self, chars: &mut HashSet<(&'static str, char)>) {
+    pub fn collect_characters(&self, chars: &mut HashSet<CharacterKey>) {
         if let Some(n) = &self.message_box {
             n.collect_characters(chars);
         }
     }
 
+    /// 直前のステップの位置として現在の位置を保存するメソッド。
+    ///
+    /// ステップの最初に呼ぶ。
+    pub fn save_positions(&mut self) {
+        self.camera.save_position();
+        self.player.save_position();
+        for n in &mut self.actors {
+            n.save_position();
+        }
+    }
+
+    /// alphaは直前のステップから次のステップまでの進行度 [0, 1) である。
     pub fn push_to(
         &mut self,
         instances: &mut Vec<InstanceMeta>,
         mngrs: &Managers,
         should_push_text: bool,
+        alpha: f32,
     ) {
         self.map_tiles.push_to(instances);
-        self.player.push_to(instances);
+        self.player.push_to(instances, alpha);
         for n in &mut self.actors {
-            n.push_to(instances);
+            n.push_to(instances, alpha);
         }
         if let Some(n) = &mut self.message_box {
             n.push_to(instances, mngrs, should_push_text);
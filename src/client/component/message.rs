@@ -1,14 +1,82 @@
 use super::*;
 
+use crate::engine::input::action::Action;
 use std::collections::HashSet;
 
+/// メッセージボックスの枠から文字列までの余白 [px]。
+const MARGIN: f32 = 48.0;
+/// 既定の文字送りの速さ [文字/s]。
+const DEFAULT_SPEED: f32 = 30.0;
+/// `{w}`で停止する時間 [s]。
+const DEFAULT_PAUSE: f32 = 0.5;
+/// オートモードでページを表示し終えてから次のページへ送るまでの時間 [s]。
+const AUTO_WAIT: f32 = 1.5;
+/// スキップモードでページを送る間隔 [s]。
+const SKIP_INTERVAL: f32 = 0.1;
+/// 送り記号の点滅の周期 [s]。
+const INDICATOR_BLINK_PERIOD: f32 = 1.0;
+/// 送り記号の1辺の大きさ [px]。
+const INDICATOR_SIZE: f32 = 12.0;
+
+/// メッセージの送り方。
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MessageMode {
+    /// 決定で次のページへ送る。
+    Normal,
+    /// ページを表示し終えてから一定時間で自動的に次のページへ送る。
+    Auto,
+    /// 文字送りを省略し、短い間隔で次々にページを送る。
+    Skip,
+}
+
+/// メッセージの1ページ。
+struct Page {
+    text: String,
+    /// 文字数。
+    length: usize,
+    /// 停止する位置 [文字] と時間 [s]。位置の昇順に並ぶ。
+    pauses: Vec<(usize, f32)>,
+}
+
 /// メッセージボックス。
+///
+/// メッセージを1文字ずつ表示する。メッセージ中には次の記号を書ける：
+/// - `{w}`：DEFAULT_PAUSE秒だけ文字送りを止める
+/// - `{w:秒数}`：指定した秒数だけ文字送りを止める
+/// - `{p}`：ページを区切る
+/// - `{{`：`{`そのもの
 pub struct MessageBox {
     sprite: Sprite,
+    /// 次のページへ送れることを示す記号。
+    indicator: Sprite,
+    /// オート・スキップ中であることを示す文字列。
+    mode_label: Text,
+    font_name: &'static str,
+    height: f32,
+    /// 文字送りの速さ [文字/s]。0以下の場合は即座に全文を表示する。
+    speed: f32,
+    mode: MessageMode,
+    pages: Vec<Page>,
+    /// 表示中のページの番号。
+    page: usize,
     message: Option<Text>,
+    /// 表示した文字数。小数部は次の文字までの進み具合である。
+    revealed: f32,
+    /// 次に停止する位置のpauses中の番号。
+    pause_index: usize,
+    /// 停止の残り時間 [s]。
+    pause: f32,
+    /// ページを表示し終えてから(スキップモードではページを開いてから)の経過時間 [s]。
+    wait: f32,
+    total_time: f32,
 }
 
 impl MessageBox {
+    /// コンストラクタ。
+    ///
+    /// 初期設定として次が設定される：
+    /// - 文字送りの速さ：30文字/s
+    /// - 送り方：通常
     pub fn new(image_id: &'static str, uv: Vec4) -> Self {
         let sprite = Sprite::new(image_id)
             .with_scl(Vec2::new(SCENE_WIDTH, SCENE_HEIGHT * 0.30))
@@ -18,24 +86,133 @@ impl MessageBox {
             .with_is_ui(true)
             .with_coods(CoordinateSystem::Canvas)
             .with_align(Alignment::TopLeft);
+        let indicator = Sprite::new(WHITE_IMAGE_ID)
+            .with_scl(Vec2::new(INDICATOR_SIZE, INDICATOR_SIZE))
+            .with_pos(Vec3::new(
+                SCENE_WIDTH - MARGIN / 2.0,
+                SCENE_HEIGHT - MARGIN / 2.0,
+                40.0,
+            ))
+            .with_is_ui(true)
+            .with_coods(CoordinateSystem::Canvas)
+            .with_align(Alignment::BottomRight);
         Self {
             sprite,
+            indicator,
+            mode_label: Text::new("", String::new(), 0.0),
+            font_name: "",
+            height: 0.0,
+            speed: DEFAULT_SPEED,
+            mode: MessageMode::Normal,
+            pages: Vec::new(),
+            page: 0,
             message: None,
+            revealed: 0.0,
+            pause_index: 0,
+            pause: 0.0,
+            wait: 0.0,
+            total_time: 0.0,
         }
     }
 
+    /// メッセージを設定し、最初のページから表示し直すメソッド。
     pub fn set_message(&mut self, font_name: &'static str, message: String, height: f32) {
-        let message = Text::new(font_name, message, height).with_pos(Vec3::new(
-            48.0,
-            SCENE_HEIGHT * 0.70 + 48.0,
-            40.0,
-        ));
-        self.message = Some(message);
+        self.font_name = font_name;
+        self.height = height;
+        self.mode_label = Text::new(font_name, String::new(), height * 0.75)
+            .with_pos(Vec3::new(
+                SCENE_WIDTH - MARGIN / 2.0,
+                SCENE_HEIGHT * 0.70 + MARGIN / 2.0,
+                40.0,
+            ))
+            .with_align(Alignment::TopRight);
+        self.pages = parse_message(&message);
+        self.open_page(0);
+        self.update_mode_label();
+    }
+    pub fn get_mode(&self) -> MessageMode {
+        self.mode
+    }
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+    pub fn set_mode(&mut self, mode: MessageMode) {
+        self.mode = mode;
+        self.wait = 0.0;
+        self.update_mode_label();
+    }
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.set_speed(speed);
+        self
+    }
+    pub fn with_mode(mut self, mode: MessageMode) -> Self {
+        self.set_mode(mode);
+        self
+    }
+
+    /// すべてのページを送り終えたか判定するメソッド。
+    pub fn is_finished(&self) -> bool {
+        self.message.is_none()
+    }
+
+    /// 入力と経過時間に応じて文字送り・ページ送りを行うメソッド。
+    ///
+    /// - 決定：表示途中なら全文を表示し、表示し終えていれば次のページへ送る
+    /// - オート：オートモードを切り替える
+    /// - スキップ：スキップモードを切り替える
+    ///
+    /// オート・スキップ中に決定すると、通常の送り方に戻る。
+    pub fn update(&mut self, mngrs: &Managers, duration: Duration) {
+        if self.is_finished() {
+            return;
+        }
+        let duration = duration.as_secs_f32();
+        self.total_time += duration;
+
+        let in_mngr = &mngrs.in_mngr;
+        if in_mngr.action_just_pressed(Action::Auto) {
+            self.toggle_mode(MessageMode::Auto);
+        }
+        if in_mngr.action_just_pressed(Action::Skip) {
+            self.toggle_mode(MessageMode::Skip);
+        }
+        if in_mngr.action_just_pressed(Action::Confirm) {
+            if self.mode != MessageMode::Normal {
+                self.set_mode(MessageMode::Normal);
+            } else if self.is_page_revealed() {
+                self.open_page(self.page + 1);
+            } else {
+                self.reveal_all();
+            }
+            return;
+        }
+
+        match self.mode {
+            MessageMode::Normal => self.reveal(duration),
+            MessageMode::Auto => {
+                if self.is_page_revealed() {
+                    self.wait += duration;
+                    if self.wait >= AUTO_WAIT {
+                        self.open_page(self.page + 1);
+                    }
+                } else {
+                    self.reveal(duration);
+                }
+            }
+            MessageMode::Skip => {
+                self.reveal_all();
+                self.wait += duration;
+                if self.wait >= SKIP_INTERVAL {
+                    self.open_page(self.page + 1);
+                }
+            }
+        }
     }
 
-    pub fn collect_characters(&self, chars: &mut HashSet<(&'static str, char)>) {
+    pub fn collect_characters(&self, chars: &mut HashSet<CharacterKey>) {
         if let Some(n) = &self.message {
             n.collect_characters(chars);
+            self.mode_label.collect_characters(chars);
         }
     }
 
@@ -46,8 +223,311 @@ impl MessageBox {
         should_push_text: bool,
     ) {
         self.sprite.push_to(instances);
+        let Some(message) = &mut self.message else {
+            return;
+        };
+        message.push_to(instances, mngrs, should_push_text);
+        self.mode_label.push_to(instances, mngrs, should_push_text);
+
+        // NOTE: スキップ中は次々に送られるので、送り記号を出さない。
+        let is_indicator_visible = self.total_time % INDICATOR_BLINK_PERIOD
+            < INDICATOR_BLINK_PERIOD / 2.0
+            && self.mode != MessageMode::Skip;
+        if is_indicator_visible && self.is_page_revealed() {
+            self.indicator.push_to(instances);
+        }
+    }
+
+    /// n番目のページを開くメソッド。ページが無い場合、メッセージを閉じる。
+    fn open_page(&mut self, n: usize) {
+        self.page = n;
+        self.revealed = 0.0;
+        self.pause_index = 0;
+        self.pause = 0.0;
+        self.wait = 0.0;
+        let Some(page) = self.pages.get(n) else {
+            self.message = None;
+            return;
+        };
+        let mut message = Text::new(self.font_name, page.text.clone(), self.height)
+            .with_pos(Vec3::new(MARGIN, SCENE_HEIGHT * 0.70 + MARGIN, 40.0))
+            .with_max_width(Some(SCENE_WIDTH - MARGIN * 2.0));
+        message.set_visible_length(0);
+        self.message = Some(message);
+        if self.speed <= 0.0 {
+            self.reveal_all();
+        }
+    }
+
+    /// 表示中のページを表示し終えたか判定するメソッド。
+    fn is_page_revealed(&self) -> bool {
+        match self.pages.get(self.page) {
+            Some(n) => {
+                self.revealed >= n.length as f32
+                    && self.pause_index >= n.pauses.len()
+                    && self.pause <= 0.0
+            }
+            None => true,
+        }
+    }
+
+    /// 文字送りをtime [s] だけ進めるメソッド。
+    fn reveal(&mut self, mut time: f32) {
+        if self.speed <= 0.0 {
+            self.reveal_all();
+            return;
+        }
+        let Some(page) = self.pages.get(self.page) else {
+            return;
+        };
+        while time > 0.0 {
+            // 停止中
+            if self.pause > 0.0 {
+                let t = self.pause.min(time);
+                self.pause -= t;
+                time -= t;
+                continue;
+            }
+            // 停止位置に達したら停止を始める
+            if let Some((i, secs)) = page.pauses.get(self.pause_index) {
+                if self.revealed >= *i as f32 {
+                    self.pause = *secs;
+                    self.pause_index += 1;
+                    continue;
+                }
+            }
+            // 次の停止位置(無ければページの末尾)まで文字を送る
+            let limit = page
+                .pauses
+                .get(self.pause_index)
+                .map_or(page.length, |n| n.0) as f32;
+            if self.revealed >= limit {
+                break;
+            }
+            let rest = (limit - self.revealed) / self.speed;
+            if rest <= time {
+                self.revealed = limit;
+                time -= rest;
+            } else {
+                self.revealed += time * self.speed;
+                time = 0.0;
+            }
+        }
+        self.update_visible_length();
+    }
+
+    /// 表示中のページの全文を即座に表示するメソッド。
+    fn reveal_all(&mut self) {
+        if let Some(page) = self.pages.get(self.page) {
+            self.revealed = page.length as f32;
+            self.pause_index = page.pauses.len();
+            self.pause = 0.0;
+        }
+        self.update_visible_length();
+    }
+
+    fn update_visible_length(&mut self) {
         if let Some(message) = &mut self.message {
-            message.push_to(instances, mngrs, should_push_text);
+            message.set_visible_length(self.revealed as usize);
+        }
+    }
+
+    /// 送り方をmodeと通常とで切り替えるメソッド。
+    fn toggle_mode(&mut self, mode: MessageMode) {
+        if self.mode == mode {
+            self.set_mode(MessageMode::Normal);
+        } else {
+            self.set_mode(mode);
+        }
+    }
+
+    fn update_mode_label(&mut self) {
+        self.mode_label.set_text(
+            match self.mode {
+                MessageMode::Normal => "",
+                MessageMode::Auto => "AUTO",
+                MessageMode::Skip => "SKIP",
+            }
+            .to_string(),
+        );
+    }
+}
+
+/// メッセージを記号に従ってページに分ける関数。
+///
+/// 解釈できない記号はそのまま文字列として扱う。
+fn parse_message(message: &str) -> Vec<Page> {
+    let mut pages = Vec::new();
+    let mut text = String::new();
+    let mut pauses = Vec::new();
+    let mut rest = message;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        if c != '{' {
+            text.push(c);
+            continue;
+        }
+        if let Some(n) = rest.strip_prefix('{') {
+            text.push('{');
+            rest = n;
+            continue;
+        }
+        let tag = rest.find('}').map(|i| (&rest[..i], &rest[i + 1..]));
+        match tag {
+            Some(("p", n)) => {
+                pages.push(Page {
+                    length: text.chars().count(),
+                    text: std::mem::take(&mut text),
+                    pauses: std::mem::take(&mut pauses),
+                });
+                rest = n;
+            }
+            Some(("w", n)) => {
+                pauses.push((text.chars().count(), DEFAULT_PAUSE));
+                rest = n;
+            }
+            Some((tag, n)) if tag.starts_with("w:") => match tag[2..].parse::<f32>() {
+                Ok(secs) => {
+                    pauses.push((text.chars().count(), secs.max(0.0)));
+                    rest = n;
+                }
+                Err(_) => text.push('{'),
+            },
+            _ => text.push('{'),
+        }
+    }
+    pages.push(Page {
+        length: text.chars().count(),
+        text,
+        pauses,
+    });
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 各ページの(文字列, 停止)を取得する関数。
+    fn parse(message: &str) -> Vec<(String, Vec<(usize, f32)>)> {
+        parse_message(message)
+            .into_iter()
+            .map(|n| {
+                assert_eq!(n.length, n.text.chars().count());
+                (n.text, n.pauses)
+            })
+            .collect()
+    }
+
+    fn create_message_box(message: &str, speed: f32) -> MessageBox {
+        let mut message_box = MessageBox::new("uis", Vec4::ONE).with_speed(speed);
+        message_box.set_message("UtsukushiFONT.otf", message.to_string(), 24.0);
+        message_box
+    }
+
+    #[test]
+    fn parses_tags() {
+        assert_eq!(parse("あいう"), [("あいう".to_string(), vec![])]);
+        assert_eq!(
+            parse("あ{w}い{w:1.5}う{w:-1}"),
+            [(
+                "あいう".to_string(),
+                vec![(1, DEFAULT_PAUSE), (2, 1.5), (3, 0.0)]
+            )]
+        );
+        assert_eq!(
+            parse("あ{p}{w}い{p}"),
+            [
+                ("あ".to_string(), vec![]),
+                ("い".to_string(), vec![(0, DEFAULT_PAUSE)]),
+                (String::new(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_escapes() {
+        assert_eq!(parse("{{"), [("{".to_string(), vec![])]);
+        assert_eq!(parse("{{w}"), [("{w}".to_string(), vec![])]);
+        assert_eq!(
+            parse("あ{{{w}"),
+            [("あ{".to_string(), vec![(2, DEFAULT_PAUSE)])]
+        );
+        assert_eq!(parse("}"), [("}".to_string(), vec![])]);
+    }
+
+    #[test]
+    fn keeps_malformed_tags_as_text() {
+        for message in ["{x}", "{w:abc}", "{w:}", "{P}", "{w", "あ{", "{}"] {
+            assert_eq!(parse(message), [(message.to_string(), vec![])]);
         }
+        // 不正な記号の後の記号は解釈される
+        assert_eq!(
+            parse("{x}{w}"),
+            [("{x}".to_string(), vec![(3, DEFAULT_PAUSE)])]
+        );
+    }
+
+    #[test]
+    fn reveals_with_pauses() {
+        let mut message_box = create_message_box("あいう{w:1}えお{p}か", 10.0);
+        assert!(!message_box.is_page_revealed());
+
+        // 3文字を0.3秒で送り、残りの0.2秒は停止する
+        message_box.reveal(0.5);
+        assert!((message_box.revealed - 3.0).abs() < 1e-4);
+        assert!((message_box.pause - 0.8).abs() < 1e-4);
+
+        // 停止中は送らない
+        message_box.reveal(0.7);
+        assert!((message_box.revealed - 3.0).abs() < 1e-4);
+
+        message_box.reveal(0.2);
+        assert!((message_box.revealed - 4.0).abs() < 1e-4);
+        assert!(!message_box.is_page_revealed());
+
+        message_box.reveal(1.0);
+        assert!(message_box.is_page_revealed());
+
+        message_box.open_page(message_box.page + 1);
+        assert!(!message_box.is_finished());
+        assert_eq!(message_box.revealed, 0.0);
+        message_box.open_page(message_box.page + 1);
+        assert!(message_box.is_finished());
+    }
+
+    #[test]
+    fn waits_for_pause_at_page_end() {
+        let mut message_box = create_message_box("あ{w:1}", 10.0);
+        message_box.reveal(0.5);
+        assert!((message_box.revealed - 1.0).abs() < 1e-4);
+        assert!(!message_box.is_page_revealed());
+        message_box.reveal(0.7);
+        assert!(message_box.is_page_revealed());
+    }
+
+    #[test]
+    fn reveals_all_at_once() {
+        // 決定で停止も含めて全文を表示する
+        let mut message_box = create_message_box("あいう{w:1}えお", 10.0);
+        message_box.reveal(0.5);
+        message_box.reveal_all();
+        assert!(message_box.is_page_revealed());
+        assert_eq!(message_box.revealed, 5.0);
+
+        // 速さが0以下であれば開いたページを即座に表示する
+        let message_box = create_message_box("あいう{w}{p}え", 0.0);
+        assert!(message_box.is_page_revealed());
+    }
+
+    #[test]
+    fn toggles_modes() {
+        let mut message_box = create_message_box("あ", 10.0);
+        message_box.toggle_mode(MessageMode::Auto);
+        assert!(message_box.get_mode() == MessageMode::Auto);
+        message_box.toggle_mode(MessageMode::Skip);
+        assert!(message_box.get_mode() == MessageMode::Skip);
+        message_box.toggle_mode(MessageMode::Skip);
+        assert!(message_box.get_mode() == MessageMode::Normal);
     }
 }
@@ -64,7 +64,7 @@ impl Sprite {
         self.coords.clone()
     }
     pub fn get_align(&self) -> Alignment {
-        self.align.clone()
+        self.align
     }
     pub fn set_scl(&mut self, scl: Vec2) {
         self.scl = scl;
@@ -144,12 +144,9 @@ impl Sprite {
             ),
         };
 
-        let pos = match self.align {
-            Alignment::Center => pos,
-            Alignment::TopLeft => {
-                Vec3::new(pos.x + self.scl.x / 2.0, pos.y - self.scl.y / 2.0, pos.z)
-            }
-        };
+        // NOTE: 回転は原点を中心に行う。
+        let offset = self.align.get_center_offset(self.scl);
+        let pos = pos + self.rot * Vec3::new(offset.x, offset.y, 0.0);
 
         instances.push(InstanceMeta {
             instance: BaseInstance {
@@ -0,0 +1,145 @@
+use super::*;
+
+use crate::engine::stats::FRAME_TIME_HISTORY_LENGTH;
+use std::collections::HashSet;
+
+/// 文字の高さ [px]。
+const LINE_HEIGHT: f32 = 16.0;
+/// 行の数。
+const LINE_COUNT: usize = 6;
+/// 余白 [px]。
+const PADDING: f32 = 8.0;
+/// フレーム時間のグラフの棒1本の幅 [px]。
+const BAR_WIDTH: f32 = 2.0;
+/// フレーム時間のグラフの高さ [px]。
+const GRAPH_HEIGHT: f32 = 64.0;
+/// グラフの高さに相当するフレーム時間 [ms]。
+const GRAPH_MAX_MS: f32 = 50.0;
+
+/// フレームの統計情報(FrameStats)を表示するオーバーレイコンポーネント。
+///
+/// キャンバスの左上に、フレームレート・ドローコール数・アップロード数・文字画像のテクスチャアトラスの使用率・
+/// テクスチャのメモリ量の数値・現在のシーンの名前と、フレーム時間の履歴のグラフを描画する。
+/// 毎フレームupdate()を呼ぶこと。
+pub struct StatsOverlay {
+    bg: Sprite,
+    lines: Vec<Text>,
+    bars: Vec<Sprite>,
+}
+
+impl StatsOverlay {
+    /// コンストラクタ。
+    ///
+    /// 他のUIより手前に描画されるよう、深度値は0付近である。
+    pub fn new(font_name: &'static str) -> Self {
+        let width = PADDING * 2.0 + BAR_WIDTH * FRAME_TIME_HISTORY_LENGTH as f32;
+        let height = PADDING * 3.0 + LINE_HEIGHT * LINE_COUNT as f32 + GRAPH_HEIGHT;
+        let bg = Sprite::new(WHITE_IMAGE_ID)
+            .with_scl(Vec2::new(width, height))
+            .with_pos(Vec3::new(0.0, 0.0, 0.1))
+            .with_col(Vec4::new(0.0, 0.0, 0.0, 0.6))
+            .with_is_ui(true)
+            .with_coods(CoordinateSystem::Canvas)
+            .with_align(Alignment::TopLeft);
+        let lines = (0..LINE_COUNT)
+            .map(|i| {
+                Text::new(font_name, String::new(), LINE_HEIGHT).with_pos(Vec3::new(
+                    PADDING,
+                    PADDING + LINE_HEIGHT * i as f32,
+                    0.0,
+                ))
+            })
+            .collect();
+        let bars = (0..FRAME_TIME_HISTORY_LENGTH)
+            .map(|_| {
+                Sprite::new(WHITE_IMAGE_ID)
+                    .with_scl(Vec2::ZERO)
+                    .with_is_ui(true)
+                    .with_coods(CoordinateSystem::Canvas)
+                    .with_align(Alignment::TopLeft)
+            })
+            .collect();
+        Self { bg, lines, bars }
+    }
+
+    /// 表示する数値・グラフを最新の統計情報に合わせるメソッド。
+    pub fn update(&mut self, mngrs: &Managers, scene_name: &str) {
+        let stats = &mngrs.frame_stats;
+        let render_stats = stats.get_render_stats();
+        let ms = |n: Duration| n.as_secs_f32() * 1000.0;
+        let values = [
+            format!(
+                "FPS {:.1}  AVG {:.1} ms  MAX {:.1} ms",
+                stats.get_fps(),
+                ms(stats.get_average_frame_time()),
+                ms(stats.get_max_frame_time()),
+            ),
+            format!("DRAW CALLS {}", render_stats.draw_calls),
+            format!(
+                "UPLOADS {} instances / {} blocks",
+                render_stats.instance_uploads, render_stats.upload_blocks,
+            ),
+            format!(
+                "GLYPHS {} ({} pages, {:.2} % used)",
+                render_stats.glyph_count,
+                render_stats.glyph_atlas_pages,
+                render_stats.glyph_atlas_usage * 100.0,
+            ),
+            format!(
+                "TEXTURES {:.1} MiB",
+                render_stats.texture_memory as f32 / (1024.0 * 1024.0),
+            ),
+            format!("SCENE {scene_name}"),
+        ];
+        for (line, value) in self.lines.iter_mut().zip(values) {
+            line.set_text(value);
+        }
+
+        // NOTE: 履歴が埋まるまでは右詰めで描画する。
+        let bottom = PADDING * 2.0 + LINE_HEIGHT * LINE_COUNT as f32 + GRAPH_HEIGHT;
+        let skip = FRAME_TIME_HISTORY_LENGTH - stats.get_frame_times().len();
+        for (i, bar) in self.bars.iter_mut().enumerate() {
+            let Some(frame_time) = i
+                .checked_sub(skip)
+                .and_then(|n| stats.get_frame_times().get(n))
+            else {
+                bar.set_scl(Vec2::ZERO);
+                continue;
+            };
+            let ms = ms(*frame_time);
+            let height = (ms / GRAPH_MAX_MS).min(1.0) * GRAPH_HEIGHT;
+            // 60fps以内なら緑、30fps以内なら黄、それ以上なら赤
+            let col = if ms <= 1000.0 / 60.0 + 0.5 {
+                Vec4::new(0.3, 1.0, 0.3, 1.0)
+            } else if ms <= 1000.0 / 30.0 + 0.5 {
+                Vec4::new(1.0, 1.0, 0.3, 1.0)
+            } else {
+                Vec4::new(1.0, 0.3, 0.3, 1.0)
+            };
+            let scl = Vec2::new(BAR_WIDTH, height);
+            let pos = Vec3::new(PADDING + BAR_WIDTH * i as f32, bottom - height, 0.0);
+            if bar.get_scl() != scl || bar.get_pos() != pos || bar.get_col() != col {
+                bar.set_scl(scl);
+                bar.set_pos(pos);
+                bar.set_col(col);
+            }
+        }
+    }
+
+    pub fn collect_characters(&self, chars: &mut HashSet<CharacterKey>) {
+        self.lines.iter().for_each(|n| n.collect_characters(chars));
+    }
+
+    pub fn push_to(
+        &mut self,
+        instances: &mut Vec<InstanceMeta>,
+        mngrs: &Managers,
+        should_push_text: bool,
+    ) {
+        self.bg.push_to(instances);
+        self.lines
+            .iter_mut()
+            .for_each(|n| n.push_to(instances, mngrs, should_push_text));
+        self.bars.iter_mut().for_each(|n| n.push_to(instances));
+    }
+}
@@ -2,18 +2,36 @@ use super::*;
 
 use std::collections::HashSet;
 
+/// 行頭に来てはいけない文字(行頭禁則文字)。
+const NOT_LINE_START: &str = "、。，．,.・：；:;？！?!ー～…‥ゝゞヽヾ々\
+    ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶ」』）〕］｝〉》】〙〗〟’”)]}";
+/// 行末に来てはいけない文字(行末禁則文字)。
+const NOT_LINE_END: &str = "「『（〔［｛〈《【〘〖〝‘“([{";
+
 /// 文字列コンポーネント。
+///
+/// `\n`で改行し、最大幅を設定した場合は禁則処理をしつつ折り返す。
 pub struct Text {
     uuids: Vec<Uuid>,
     font_name: &'static str,
     text: String,
     height: f32,
+    /// 1行の最大幅 [px]。Noneの場合は折り返さない。
+    max_width: Option<f32>,
+    /// 行送り。文字の高さに対する比。
+    line_spacing: f32,
+    /// 描画する文字数。先頭からこの数の文字のみ描画する。
+    visible_length: usize,
     pos: Vec3,
     col: Vec4,
     param: Vec4,
     coords: CoordinateSystem,
     align: Alignment,
     should_push: bool,
+    /// レイアウトの結果。文字列・最大幅が変わるまで再利用する。
+    lines: Vec<Line>,
+    /// linesが最新か否か。
+    is_laid_out: bool,
 }
 
 impl Text {
@@ -25,6 +43,9 @@ impl Text {
     /// - UIか：はい
     /// - 座標系：キャンバス座標系
     /// - アラインメント：左上詰め
+    /// - 最大幅：なし
+    /// - 行送り：1.5
+    /// - 描画する文字数：無制限
     pub fn new(font_name: &'static str, text: String, height: f32) -> Self {
         let uuids = (0..text.chars().count()).map(|_| Uuid::new_v4()).collect();
         Self {
@@ -32,13 +53,30 @@ impl Text {
             font_name,
             text,
             height,
+            max_width: None,
+            line_spacing: 1.5,
+            visible_length: usize::MAX,
             pos: Vec3::ZERO,
             col: Vec4::new(1.0, 1.0, 1.0, 1.0),
             param: Vec4::new(1.0, 0.0, 0.0, 0.0),
             coords: CoordinateSystem::Canvas,
             align: Alignment::TopLeft,
             should_push: true,
+            lines: Vec::new(),
+            is_laid_out: false,
+        }
+    }
+    /// 文字列を変更するメソッド。
+    ///
+    /// 同じ文字列の場合、何もしない(毎フレーム呼んでも再アップロードされない)。
+    pub fn set_text(&mut self, text: String) {
+        if self.text == text {
+            return;
         }
+        self.uuids = (0..text.chars().count()).map(|_| Uuid::new_v4()).collect();
+        self.text = text;
+        self.should_push = true;
+        self.is_laid_out = false;
     }
     pub fn set_pos(&mut self, pos: Vec3) {
         self.pos = pos;
@@ -64,6 +102,22 @@ impl Text {
         self.align = align;
         self.should_push = true;
     }
+    pub fn set_max_width(&mut self, max_width: Option<f32>) {
+        self.max_width = max_width;
+        self.should_push = true;
+        self.is_laid_out = false;
+    }
+    pub fn set_line_spacing(&mut self, line_spacing: f32) {
+        self.line_spacing = line_spacing;
+        self.should_push = true;
+    }
+    /// 描画する文字数を変更するメソッド。
+    ///
+    /// レイアウトは文字列全体で行うので、文字数を増やしても既に描画された文字の位置は変わらない。
+    /// 改行文字も1文字と数える。
+    pub fn set_visible_length(&mut self, visible_length: usize) {
+        self.visible_length = visible_length;
+    }
     pub fn with_pos(mut self, pos: Vec3) -> Self {
         self.set_pos(pos);
         self
@@ -84,33 +138,52 @@ impl Text {
         self.set_align(align);
         self
     }
-    pub fn collect_characters(&self, chars: &mut HashSet<(&'static str, char)>) {
+    pub fn with_max_width(mut self, max_width: Option<f32>) -> Self {
+        self.set_max_width(max_width);
+        self
+    }
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.set_line_spacing(line_spacing);
+        self
+    }
+    pub fn collect_characters(&self, chars: &mut HashSet<CharacterKey>) {
         self.text.chars().for_each(|c| {
-            chars.insert((self.font_name, c));
+            chars.insert(get_character_key(self.font_name, c, self.height));
         });
     }
+    /// レイアウトした文字列の外接矩形を求めるメソッド。
+    ///
+    /// 外接矩形はアラインメントを適用したもので、位置は文字列と同じ座標系での左上の位置である。
+    ///
+    /// WARN: 文字画像がロードされていない文字は幅0とみなされる。
+    pub fn measure(&mut self, mngrs: &Managers) -> Rect {
+        self.update_layout(mngrs);
+        let size = self.get_size();
+        let offset = self.align.get_center_offset(size);
+        let pos = match self.coords {
+            CoordinateSystem::World => Vec2::new(
+                self.pos.x + offset.x - size.x / 2.0,
+                self.pos.y + offset.y + size.y / 2.0,
+            ),
+            CoordinateSystem::Canvas => Vec2::new(
+                self.pos.x + offset.x - size.x / 2.0,
+                self.pos.y - offset.y - size.y / 2.0,
+            ),
+        };
+        Rect { pos, size }
+    }
     pub fn push_to(
         &mut self,
         instances: &mut Vec<InstanceMeta>,
         mngrs: &Managers,
         should_push_text: bool,
     ) {
-        let mut width = 0.0;
-        let mut char_images = Vec::with_capacity(self.text.chars().count());
-        for c in self.text.chars() {
-            // WARN: 文字画像の情報を取得できなかった場合、その文字はスキップされる。
-            if let Some(n) = mngrs.gr_mngr.get_character_image(self.font_name, c) {
-                let (w, _, _, _, _) = n.scale(self.height);
-                width += w;
-                char_images.push(n);
-            }
-        }
-
-        if char_images.is_empty() {
+        self.update_layout(mngrs);
+        if self.lines.iter().all(|n| n.glyphs.is_empty()) {
             return;
         }
 
-        let mut pos = match self.coords {
+        let pos = match self.coords {
             CoordinateSystem::World => self.pos,
             CoordinateSystem::Canvas => Vec3::new(
                 -SCENE_WIDTH / 2.0 + self.pos.x,
@@ -118,36 +191,255 @@ impl Text {
                 self.pos.z,
             ),
         };
-        match self.align {
-            Alignment::Center => pos.x -= width / 2.0,
-            Alignment::TopLeft => {
-                let (w, _, _, _, _) = char_images[0].scale(self.height);
-                pos.x += w / 2.0;
-                pos.y -= self.height / 2.0;
-            }
-        };
+        // 外接矩形の左上の位置
+        let size = self.get_size();
+        let center = pos.truncate() + self.align.get_center_offset(size);
+        let left = center.x - size.x / 2.0;
+        let top = center.y + size.y / 2.0;
+        // NOTE: 各行は原点の左右の位置に合わせて揃える(右寄せなら右揃え)。
+        let pivot_x = self.align.get_pivot().x;
 
-        for (i, n) in char_images.iter().enumerate() {
-            let (w, h, ox, oy, ad) = n.scale(self.height);
-            instances.push(InstanceMeta {
-                instance: BaseInstance {
-                    _world: Mat4::from_scale_rotation_translation(
-                        Vec3::new(w, h, 1.0),
-                        Quat::IDENTITY,
-                        Vec3::new(pos.x + w / 2.0 + ox, pos.y - h / 2.0 - oy, pos.z),
-                    ),
-                    _uv: n.uv,
-                    _color: self.col,
-                    _param: self.param,
-                },
-                uuid: self.uuids[i],
-                updated: self.should_push || should_push_text,
-                image_id: "chars",
-                depth: pos.z,
-            });
-            pos.x += ad;
+        for (i, line) in self.lines.iter().enumerate() {
+            let x = left + (size.x - line.width) * pivot_x;
+            let y = top - self.get_line_height() * i as f32;
+            for glyph in line.glyphs.iter().filter(|n| n.index < self.visible_length) {
+                // NOTE: UV座標は再ロードで変わるので、レイアウトには含めず毎回取得する。
+                let Some(image) =
+                    mngrs
+                        .gr_mngr
+                        .get_character_image(self.font_name, glyph.character, self.height)
+                else {
+                    continue;
+                };
+                let (w, h, ox, oy, _) = image.scale(self.height);
+                instances.push(InstanceMeta {
+                    instance: BaseInstance {
+                        _world: Mat4::from_scale_rotation_translation(
+                            Vec3::new(w, h, 1.0),
+                            Quat::IDENTITY,
+                            Vec3::new(x + glyph.x + w / 2.0 + ox, y - h / 2.0 - oy, pos.z),
+                        ),
+                        _uv: image.uv,
+                        _color: self.col,
+                        _param: self.param,
+                    },
+                    uuid: self.uuids[glyph.index],
+                    updated: self.should_push || should_push_text,
+                    image_id: image.image_id,
+                    depth: pos.z,
+                });
+            }
         }
 
         self.should_push = false;
     }
+
+    /// レイアウトした文字列の外接矩形の大きさを求めるメソッド。
+    fn get_size(&self) -> Vec2 {
+        let width = self.lines.iter().map(|n| n.width).fold(0.0, f32::max);
+        let height =
+            self.height + self.get_line_height() * self.lines.len().saturating_sub(1) as f32;
+        Vec2::new(width, height)
+    }
+
+    /// 行の間隔 [px] を取得するメソッド。
+    fn get_line_height(&self) -> f32 {
+        self.height * self.line_spacing
+    }
+
+    /// レイアウトが最新でなければ文字列をレイアウトし直すメソッド。
+    ///
+    /// NOTE: 文字画像を取得できない文字があった場合、ロードされた後にやり直せるよう最新とみなさない。
+    fn update_layout(&mut self, mngrs: &Managers) {
+        if self.is_laid_out {
+            return;
+        }
+        let mut is_complete = true;
+        let lines = layout(&self.text, self.max_width, |c| {
+            let image = mngrs
+                .gr_mngr
+                .get_character_image(self.font_name, c, self.height);
+            is_complete &= image.is_some();
+            image.map(|n| n.scale(self.height).4)
+        });
+        self.lines = lines;
+        self.is_laid_out = is_complete;
+        // NOTE: 配置が変わりうるので、インスタンスを更新する。
+        self.should_push = true;
+    }
+}
+
+/// 配置された1文字。
+#[derive(Debug, PartialEq)]
+struct Glyph {
+    /// 文字列中の文字の番号。
+    index: usize,
+    character: char,
+    /// 行頭からのX座標 [px]。
+    x: f32,
+}
+
+/// 配置された1行。
+#[derive(Debug, PartialEq)]
+struct Line {
+    glyphs: Vec<Glyph>,
+    /// 行の幅 [px]。
+    width: f32,
+}
+
+/// 文字列を行に分けて配置する関数。
+///
+/// `\n`で改行し、最大幅を超える場合は直前の改行可能な位置で折り返す。
+/// 改行可能な位置が無い場合、最大幅を超える文字の直前で折り返す。
+/// advanceは文字の送り幅 [px] を返す。Noneを返した文字は幅0とみなし、配置しない。
+fn layout(
+    text: &str,
+    max_width: Option<f32>,
+    mut advance: impl FnMut(char) -> Option<f32>,
+) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut index = 0;
+    for paragraph in text.split('\n') {
+        let chars = paragraph
+            .chars()
+            .enumerate()
+            .map(|(i, c)| (index + i, c, advance(c)))
+            .collect::<Vec<_>>();
+        // NOTE: 改行文字の分も数える。
+        index += chars.len() + 1;
+        let advance = |k: usize| chars[k].2.unwrap_or(0.0);
+
+        let mut start = 0;
+        let mut x = 0.0;
+        let mut last_break = None;
+        for k in 0..chars.len() {
+            let c = chars[k].1;
+            if k > start && can_break(chars[k - 1].1, c) {
+                last_break = Some(k);
+            }
+            // NOTE: 空白は行末にぶら下げるので、最大幅を超えても折り返さない。
+            if let Some(max_width) = max_width {
+                if k > start && !c.is_whitespace() && x + advance(k) > max_width {
+                    let end = last_break.unwrap_or(k);
+                    lines.push(build_line(&chars[start..end]));
+                    x = (end..k).map(advance).sum();
+                    start = end;
+                    last_break = None;
+                }
+            }
+            x += advance(k);
+        }
+        lines.push(build_line(&chars[start..]));
+    }
+    lines
+}
+
+/// 1行分の文字を左から並べる関数。
+fn build_line(chars: &[(usize, char, Option<f32>)]) -> Line {
+    let mut glyphs = Vec::with_capacity(chars.len());
+    let mut x = 0.0;
+    let mut width = 0.0;
+    for (index, c, advance) in chars {
+        let Some(advance) = advance else {
+            continue;
+        };
+        glyphs.push(Glyph {
+            index: *index,
+            character: *c,
+            x,
+        });
+        x += advance;
+        // NOTE: 行末の空白は幅に含めない。
+        if !c.is_whitespace() {
+            width = x;
+        }
+    }
+    Line { glyphs, width }
+}
+
+/// prevとnextの間で改行できるか判定する関数。
+///
+/// 禁則処理に従い、英単語の途中や空白の直前では改行しない。
+fn can_break(prev: char, next: char) -> bool {
+    if next.is_whitespace() || NOT_LINE_START.contains(next) || NOT_LINE_END.contains(prev) {
+        false
+    } else if prev.is_whitespace() {
+        true
+    } else {
+        !(is_word_character(prev) && is_word_character(next))
+    }
+}
+
+/// 英単語を構成する(CJKでない)文字か判定する関数。
+fn is_word_character(c: char) -> bool {
+    (c as u32) < 0x2E80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// すべての文字の送り幅を10pxとしてレイアウトし、各行の文字列を返す関数。
+    fn wrap(text: &str, max_width: Option<f32>) -> Vec<String> {
+        layout(text, max_width, |_| Some(10.0))
+            .iter()
+            .map(|n| n.glyphs.iter().map(|n| n.character).collect())
+            .collect()
+    }
+
+    #[test]
+    fn breaks_at_newlines() {
+        assert_eq!(wrap("あい\nう\n\nえ", None), ["あい", "う", "", "え"]);
+
+        // 改行文字も番号に数える
+        let lines = layout("あ\nい", None, |_| Some(10.0));
+        assert_eq!(lines[1].glyphs[0].index, 2);
+    }
+
+    #[test]
+    fn wraps_at_max_width() {
+        assert_eq!(wrap("あいうえお", Some(30.0)), ["あいう", "えお"]);
+        assert_eq!(wrap("あいうえお", None), ["あいうえお"]);
+
+        let lines = layout("あいうえお", Some(30.0), |_| Some(10.0));
+        assert_eq!(lines[1].glyphs[1].x, 10.0);
+        assert_eq!(lines[1].width, 20.0);
+    }
+
+    #[test]
+    fn keeps_closing_marks_off_line_start() {
+        assert_eq!(wrap("あいう。えお", Some(30.0)), ["あい", "う。え", "お"]);
+        assert_eq!(wrap("あいう」、え", Some(30.0)), ["あい", "う」、", "え"]);
+    }
+
+    #[test]
+    fn keeps_opening_marks_off_line_end() {
+        assert_eq!(wrap("あい「う」", Some(30.0)), ["あい", "「う」"]);
+    }
+
+    #[test]
+    fn wraps_words_and_hangs_spaces() {
+        let lines = layout("hello world", Some(60.0), |_| Some(10.0));
+        let texts = lines
+            .iter()
+            .map(|n| n.glyphs.iter().map(|n| n.character).collect::<String>())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["hello ", "world"]);
+        // 行末の空白は幅に含めない
+        assert_eq!(lines[0].width, 50.0);
+
+        // 改行可能な位置が無い場合は最大幅で折り返す
+        assert_eq!(wrap("abcdefgh", Some(30.0)), ["abc", "def", "gh"]);
+        // 英単語とCJKの間は改行できる
+        assert_eq!(wrap("abcあい", Some(40.0)), ["abcあ", "い"]);
+        assert_eq!(wrap("あabcd", Some(40.0)), ["あ", "abcd"]);
+    }
+
+    #[test]
+    fn skips_missing_characters() {
+        let lines = layout("あ?い", None, |c| (c != '?').then_some(10.0));
+        assert_eq!(lines[0].glyphs.len(), 2);
+        assert_eq!(lines[0].glyphs[1].index, 2);
+        assert_eq!(lines[0].glyphs[1].x, 10.0);
+    }
 }
@@ -0,0 +1,273 @@
+use super::*;
+
+use std::collections::HashSet;
+use winit::keyboard::KeyCode;
+
+/// カーソルの点滅の周期 [s]。
+const CARET_BLINK_PERIOD: f32 = 1.0;
+/// カーソル・下線の太さ [px]。
+const LINE_THICKNESS: f32 = 2.0;
+
+/// テキストフィールドコンポーネント。
+///
+/// 名前入力等の1行の文字列の入力に用いる。
+/// IMEの未確定文字列はカーソル位置に下線付きで表示される。
+///
+/// 入力を受け付けるには、シーンがInputManager.start_text_input()を呼んでおく必要がある。
+pub struct TextField {
+    font_name: &'static str,
+    height: f32,
+    pos: Vec3,
+    col: Vec4,
+    /// 入力できる最大の文字数。
+    max_length: usize,
+    /// 確定済みの文字列。
+    value: String,
+    /// カーソルの位置 [文字]。
+    cursor: usize,
+    /// IMEの未確定文字列。
+    preedit: String,
+    /// 未確定文字列中のカーソルの位置 [byte]。
+    preedit_cursor: Option<(usize, usize)>,
+    /// 確定済みの文字列に未確定文字列を挿入した、表示される文字列。
+    text: Text,
+    underline: Sprite,
+    caret: Sprite,
+    total_time: f32,
+}
+
+impl TextField {
+    /// コンストラクタ。
+    ///
+    /// 初期設定として次が設定される：
+    /// - 座標：(0, 0, 0)
+    /// - 色：白
+    /// - 最大の文字数：無制限
+    /// - 座標系：キャンバス座標系(左上詰め)
+    pub fn new(font_name: &'static str, height: f32) -> Self {
+        let line = || {
+            Sprite::new(WHITE_IMAGE_ID)
+                .with_is_ui(true)
+                .with_coods(CoordinateSystem::Canvas)
+                .with_align(Alignment::TopLeft)
+        };
+        Self {
+            font_name,
+            height,
+            pos: Vec3::ZERO,
+            col: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            max_length: usize::MAX,
+            value: String::new(),
+            cursor: 0,
+            preedit: String::new(),
+            preedit_cursor: None,
+            text: Text::new(font_name, String::new(), height),
+            underline: line(),
+            caret: line(),
+            total_time: 0.0,
+        }
+    }
+    /// 確定済みの文字列を取得するメソッド。
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+    /// 確定済みの文字列を設定するメソッド。
+    ///
+    /// カーソルは末尾に移動する。
+    pub fn set_value(&mut self, value: String) {
+        self.value = value.chars().take(self.max_length).collect();
+        self.cursor = self.value.chars().count();
+        self.rebuild_text();
+    }
+    pub fn set_pos(&mut self, pos: Vec3) {
+        self.pos = pos;
+        self.rebuild_text();
+    }
+    pub fn set_col(&mut self, col: Vec4) {
+        self.col = col;
+        self.underline.set_col(col);
+        self.caret.set_col(col);
+        self.rebuild_text();
+    }
+    pub fn set_max_length(&mut self, max_length: usize) {
+        self.max_length = max_length;
+        let value = self.value.clone();
+        self.set_value(value);
+    }
+    pub fn with_value(mut self, value: String) -> Self {
+        self.set_value(value);
+        self
+    }
+    pub fn with_pos(mut self, pos: Vec3) -> Self {
+        self.set_pos(pos);
+        self
+    }
+    pub fn with_col(mut self, col: Vec4) -> Self {
+        self.set_col(col);
+        self
+    }
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.set_max_length(max_length);
+        self
+    }
+
+    /// 入力を反映するメソッド。
+    ///
+    /// - 確定された文字列をカーソル位置に挿入する
+    /// - Backspace・Deleteで文字を削除する
+    /// - 左右キー・Home・Endでカーソルを移動する
+    ///
+    /// 未確定文字列がある間の編集はIMEが処理するため、キー入力は無視される。
+    pub fn update(&mut self, mngrs: &mut Managers, duration: Duration) {
+        let in_mngr = &mngrs.in_mngr;
+        let mut is_changed = false;
+
+        // 確定された文字列を挿入
+        for c in in_mngr.get_text().chars() {
+            if self.value.chars().count() >= self.max_length {
+                break;
+            }
+            let i = self.byte_index(self.cursor);
+            self.value.insert(i, c);
+            self.cursor += 1;
+            is_changed = true;
+        }
+
+        // 編集
+        if in_mngr.get_preedit().is_empty() {
+            let length = self.value.chars().count();
+            if in_mngr.repeated(&KeyCode::Backspace) && self.cursor > 0 {
+                self.cursor -= 1;
+                self.value.remove(self.byte_index(self.cursor));
+                is_changed = true;
+            }
+            if in_mngr.repeated(&KeyCode::Delete) && self.cursor < length {
+                self.value.remove(self.byte_index(self.cursor));
+                is_changed = true;
+            }
+            if in_mngr.repeated(&KeyCode::ArrowLeft) && self.cursor > 0 {
+                self.cursor -= 1;
+                is_changed = true;
+            }
+            if in_mngr.repeated(&KeyCode::ArrowRight) && self.cursor < length {
+                self.cursor += 1;
+                is_changed = true;
+            }
+            if in_mngr.just_pressed(&KeyCode::Home) {
+                self.cursor = 0;
+                is_changed = true;
+            }
+            if in_mngr.just_pressed(&KeyCode::End) {
+                self.cursor = self.value.chars().count();
+                is_changed = true;
+            }
+        }
+
+        // 未確定文字列を取得
+        if self.preedit != in_mngr.get_preedit()
+            || self.preedit_cursor != in_mngr.get_preedit_cursor()
+        {
+            self.preedit = in_mngr.get_preedit().to_string();
+            self.preedit_cursor = in_mngr.get_preedit_cursor();
+            is_changed = true;
+        }
+
+        if is_changed {
+            self.rebuild_text();
+            // NOTE: 入力中はカーソルを点灯させておく。
+            self.total_time = 0.0;
+        } else {
+            self.total_time += duration.as_secs_f32();
+        }
+
+        // 変換候補ウィンドウを入力中の位置の下に出す
+        let x = self.pos.x + self.measure(mngrs, &self.value[..self.byte_index(self.cursor)]);
+        mngrs.in_mngr.set_ime_cursor_area(
+            Vec2::new(x, self.pos.y),
+            Vec2::new(self.measure(mngrs, &self.preedit).max(1.0), self.height),
+        );
+    }
+
+    pub fn collect_characters(&self, chars: &mut HashSet<CharacterKey>) {
+        self.text.collect_characters(chars);
+    }
+
+    pub fn push_to(
+        &mut self,
+        instances: &mut Vec<InstanceMeta>,
+        mngrs: &Managers,
+        should_push_text: bool,
+    ) {
+        self.text.push_to(instances, mngrs, should_push_text);
+
+        let value_head = &self.value[..self.byte_index(self.cursor)];
+        let x = self.pos.x + self.measure(mngrs, value_head);
+
+        // 未確定文字列の下線
+        if !self.preedit.is_empty() {
+            let pos = Vec3::new(x, self.pos.y + self.height, self.pos.z);
+            let scl = Vec2::new(self.measure(mngrs, &self.preedit), LINE_THICKNESS);
+            if self.underline.get_pos() != pos || self.underline.get_scl() != scl {
+                self.underline.set_pos(pos);
+                self.underline.set_scl(scl);
+            }
+            self.underline.push_to(instances);
+        }
+
+        // カーソル
+        let is_caret_visible = self.total_time % CARET_BLINK_PERIOD < CARET_BLINK_PERIOD / 2.0;
+        let preedit_head = match self.preedit_cursor {
+            Some((n, _)) => self.preedit.get(..n).unwrap_or(&self.preedit),
+            None if self.preedit.is_empty() => "",
+            // NOTE: IMEがカーソルを隠すよう求めている。
+            None => return,
+        };
+        if is_caret_visible {
+            let pos = Vec3::new(
+                x + self.measure(mngrs, preedit_head),
+                self.pos.y,
+                self.pos.z,
+            );
+            let scl = Vec2::new(LINE_THICKNESS, self.height);
+            if self.caret.get_pos() != pos || self.caret.get_scl() != scl {
+                self.caret.set_pos(pos);
+                self.caret.set_scl(scl);
+            }
+            self.caret.push_to(instances);
+        }
+    }
+
+    /// 表示される文字列を作り直すメソッド。
+    fn rebuild_text(&mut self) {
+        self.text = Text::new(self.font_name, self.display_string(), self.height)
+            .with_pos(self.pos)
+            .with_col(self.col);
+    }
+
+    fn display_string(&self) -> String {
+        let i = self.byte_index(self.cursor);
+        format!("{}{}{}", &self.value[..i], self.preedit, &self.value[i..])
+    }
+
+    /// 確定済みの文字列のn文字目のバイト位置を求めるメソッド。
+    fn byte_index(&self, n: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(n)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+
+    /// 文字列を描画したときの幅を求めるメソッド。
+    ///
+    /// WARN: 文字画像がロードされていない文字は幅0とみなされる。
+    fn measure(&self, mngrs: &Managers, s: &str) -> f32 {
+        s.chars()
+            .filter_map(|c| {
+                mngrs
+                    .gr_mngr
+                    .get_character_image(self.font_name, c, self.height)
+            })
+            .map(|n| n.scale(self.height).4)
+            .sum()
+    }
+}
@@ -1,13 +1,39 @@
+pub mod keyconfig;
 pub mod load;
 pub mod map;
+pub mod name;
+pub mod options;
 pub mod title;
 
 use crate::engine::Managers;
 use std::time::Duration;
 
 pub type Scene = Box<dyn SceneTrait>;
-pub type NextScene = Option<Scene>;
+pub type NextScene = Option<SceneCommand>;
+
+/// シーンからGameManagerへの要求。
+pub enum SceneCommand {
+    /// 次のシーンに移る。
+    Change(Scene),
+    /// ゲームを終了する。
+    Quit,
+}
 
 pub trait SceneTrait {
+    /// シーンの名前を取得するメソッド。
+    ///
+    /// デバッグ表示・テストでシーンを識別するために用いる。
+    fn get_name(&self) -> &'static str;
+    /// シーンの状態を進めるメソッド。
+    ///
+    /// 一定の時間ごとに呼ばれる。次のシーンに移る・ゲームを終了する場合、Someを返す。
     fn update(&mut self, mngrs: &mut Managers, duration: Duration) -> NextScene;
+    /// シーンを描画するメソッド。
+    ///
+    /// 毎フレーム呼ばれる。alphaは直前のupdate()から次のupdate()までの進行度 [0, 1) である。
+    fn render(&mut self, mngrs: &mut Managers, alpha: f32);
+    /// ゲームの終了時に呼ばれるメソッド。
+    ///
+    /// オートセーブ等に用いる。既定では何もしない。
+    fn shutdown(&mut self, _mngrs: &mut Managers) {}
 }
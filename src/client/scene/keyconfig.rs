@@ -0,0 +1,184 @@
+use super::*;
+
+use crate::engine::input::{
+    action::{self, Action, Binding},
+    key,
+};
+use crate::{client::component::*, *};
+use glam::*;
+use options::OptionsScene;
+use std::collections::HashSet;
+use winit::keyboard::KeyCode;
+
+/// アクション以外の項目。
+const EXTRA_ITEMS: [&str; 2] = ["初期設定に戻す", "もどる"];
+
+/// キーの割り当てを変更するシーン。
+///
+/// 上下で項目を選び、決定の後に押したキーをそのアクションに割り当てる。
+/// キーボードの割り当てのみ変更でき、ゲームパッドの割り当ては保たれる。
+/// シーンを抜けるときに設定ファイルに保存される。
+pub struct KeyConfigScene {
+    cursor: usize,
+    /// キーが押されるのを待っているか否か。
+    is_waiting: bool,
+    label: Text,
+    items: Vec<Text>,
+}
+
+impl KeyConfigScene {
+    pub fn new(mngrs: &mut Managers) -> Self {
+        let label = Text::new("UtsukushiFONT.otf", "キー設定".to_string(), 40.0)
+            .with_pos(Vec3::new(SCENE_WIDTH * 0.5, SCENE_HEIGHT * 0.15, 0.0))
+            .with_align(Alignment::Top);
+        let items = (0..Action::ALL.len() + EXTRA_ITEMS.len())
+            .map(|i| {
+                Text::new("UtsukushiFONT.otf", String::new(), 28.0).with_pos(Vec3::new(
+                    SCENE_WIDTH * 0.2,
+                    SCENE_HEIGHT * 0.22 + 44.0 * i as f32,
+                    0.0,
+                ))
+            })
+            .collect();
+
+        let mut scene = Self {
+            cursor: 0,
+            is_waiting: false,
+            label,
+            items,
+        };
+        scene.update_items(mngrs);
+        scene
+    }
+
+    /// アクションのキーボードの割り当てをkey_codeのみにするメソッド。
+    fn rebind(mngrs: &mut Managers, action: Action, key_code: KeyCode) {
+        let action_map = mngrs.in_mngr.get_action_map_mut();
+        let keys = action_map
+            .get_bindings(action)
+            .iter()
+            .filter(|n| matches!(n, Binding::Key(_)))
+            .copied()
+            .collect::<Vec<_>>();
+        for n in keys {
+            action_map.unbind(action, n);
+        }
+        action_map.bind(action, key_code);
+    }
+
+    /// 項目の文字列と色を現在の割り当てに合わせるメソッド。
+    fn update_items(&mut self, mngrs: &Managers) {
+        let action_map = mngrs.in_mngr.get_action_map();
+        for (i, text) in self.items.iter_mut().enumerate() {
+            let value = match Action::ALL.get(i) {
+                Some(_) if self.is_waiting && i == self.cursor => {
+                    format!("{}：キーを押してください", get_label(Action::ALL[i]))
+                }
+                Some(action) => {
+                    let keys = action_map
+                        .get_bindings(*action)
+                        .iter()
+                        .filter_map(|n| match n {
+                            Binding::Key(n) => Some(key::get_name(*n)),
+                            Binding::Gamepad(_) => None,
+                        })
+                        .collect::<Vec<_>>();
+                    format!("{}：{}", get_label(*action), keys.join(" "))
+                }
+                None => EXTRA_ITEMS[i - Action::ALL.len()].to_string(),
+            };
+            text.set_text(value);
+            text.set_col(if i == self.cursor {
+                Vec4::new(1.0, 1.0, 0.4, 1.0)
+            } else {
+                Vec4::new(1.0, 1.0, 1.0, 1.0)
+            });
+        }
+    }
+}
+
+impl SceneTrait for KeyConfigScene {
+    fn get_name(&self) -> &'static str {
+        "keyconfig"
+    }
+
+    fn update(&mut self, mngrs: &mut Managers, _: Duration) -> NextScene {
+        // キーが押されたら割り当てる
+        if self.is_waiting {
+            let pressed = key::ALL_KEYS
+                .into_iter()
+                .find(|n| mngrs.in_mngr.just_pressed(n));
+            if let Some(key_code) = pressed {
+                Self::rebind(mngrs, Action::ALL[self.cursor], key_code);
+                self.is_waiting = false;
+                self.update_items(mngrs);
+            }
+            return None;
+        }
+
+        // 取消か「もどる」の決定で保存して設定に戻る
+        let len = self.items.len();
+        let is_confirmed = mngrs.in_mngr.action_just_pressed(Action::Confirm);
+        if mngrs.in_mngr.action_just_pressed(Action::Cancel)
+            || is_confirmed && self.cursor == len - 1
+        {
+            if let Err(e) = mngrs.in_mngr.get_action_map().save(action::CONFIG_PATH) {
+                eprintln!("failed to save {}: {e}", action::CONFIG_PATH);
+            }
+            return Some(SceneCommand::Change(Box::new(OptionsScene::new(mngrs))));
+        }
+
+        let cursor = self.cursor;
+        if is_confirmed && self.cursor < Action::ALL.len() {
+            self.is_waiting = true;
+        } else if is_confirmed {
+            mngrs.in_mngr.get_action_map_mut().reset();
+        }
+        if mngrs.in_mngr.action_repeated(Action::Up) {
+            self.cursor = options::cycle(self.cursor, len, -1);
+        }
+        if mngrs.in_mngr.action_repeated(Action::Down) {
+            self.cursor = options::cycle(self.cursor, len, 1);
+        }
+        if is_confirmed || cursor != self.cursor {
+            self.update_items(mngrs);
+        }
+
+        None
+    }
+
+    fn render(&mut self, mngrs: &mut Managers, _: f32) {
+        // すべての文字画像をロード
+        let mut chars = HashSet::new();
+        self.label.collect_characters(&mut chars);
+        self.items
+            .iter()
+            .for_each(|n| n.collect_characters(&mut chars));
+        let should_push_text = mngrs
+            .gr_mngr
+            .load_all_character_images(&mut mngrs.rs_mngr, chars);
+
+        // 描画
+        let mut instances = Vec::new();
+        self.label.push_to(&mut instances, mngrs, should_push_text);
+        self.items
+            .iter_mut()
+            .for_each(|n| n.push_to(&mut instances, mngrs, should_push_text));
+        mngrs.gr_mngr.render_with_metas(instances);
+    }
+}
+
+/// アクションの表示名を取得する関数。
+fn get_label(action: Action) -> &'static str {
+    match action {
+        Action::Confirm => "決定",
+        Action::Cancel => "取消",
+        Action::Menu => "メニュー",
+        Action::Up => "上",
+        Action::Down => "下",
+        Action::Left => "左",
+        Action::Right => "右",
+        Action::Auto => "オート",
+        Action::Skip => "スキップ",
+    }
+}
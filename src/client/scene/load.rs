@@ -3,27 +3,39 @@ use super::*;
 use crate::{client::component::*, *};
 use glam::*;
 
-pub struct LoadScene;
+pub struct LoadScene {
+    bg: Sprite,
+}
 
 impl LoadScene {
     pub fn new(mngrs: &mut Managers) -> Scene {
         // TODO: エラー時はダイアログ出して落とす方が親切かも。
         let _ = mngrs.gr_mngr.load_image(&mngrs.rs_mngr, "load");
 
-        // ロード画面描画
-        let mut instances = Vec::new();
-        Sprite::new("load")
-            .with_scl(Vec2::new(SCENE_WIDTH, SCENE_HEIGHT))
-            .with_uv(Vec4::new(0.0, 0.0, 1.0, 0.5625))
-            .push_to(&mut instances);
-        mngrs.gr_mngr.render_with_metas(instances);
+        let mut scene = Self {
+            bg: Sprite::new("load")
+                .with_scl(Vec2::new(SCENE_WIDTH, SCENE_HEIGHT))
+                .with_uv(Vec4::new(0.0, 0.0, 1.0, 0.5625)),
+        };
+        // NOTE: 次のシーンのロード中に表示されるよう、すぐに描画しておく。
+        scene.render(mngrs, 0.0);
 
-        Box::new(Self)
+        Box::new(scene)
     }
 }
 
 impl SceneTrait for LoadScene {
+    fn get_name(&self) -> &'static str {
+        "load"
+    }
+
     fn update(&mut self, mngrs: &mut Managers, _: Duration) -> NextScene {
-        Some(title::TitleScene::new(mngrs))
+        Some(SceneCommand::Change(title::TitleScene::new(mngrs)))
+    }
+
+    fn render(&mut self, mngrs: &mut Managers, _: f32) {
+        let mut instances = Vec::new();
+        self.bg.push_to(&mut instances);
+        mngrs.gr_mngr.render_with_metas(instances);
     }
 }
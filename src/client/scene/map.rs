@@ -1,9 +1,12 @@
 use super::*;
 
 use crate::client::component::*;
+use crate::engine::input::{
+    action::{Action, Binding},
+    key,
+};
 use glam::*;
 use std::collections::HashSet;
-use winit::keyboard::KeyCode;
 
 pub struct MapScene {
     coms: Components,
@@ -15,6 +18,8 @@ impl MapScene {
         let _ = mngrs.gr_mngr.load_image(&mut mngrs.rs_mngr, "tiles");
         let _ = mngrs.gr_mngr.load_image(&mut mngrs.rs_mngr, "actors");
         let _ = mngrs.gr_mngr.load_image(&mut mngrs.rs_mngr, "uis");
+        let _ = mngrs.au_mngr.load_sound(&mngrs.rs_mngr, "map");
+        let _ = mngrs.au_mngr.crossfade_bgm("map", Duration::from_secs(1));
 
         let camera = Camera::new();
 
@@ -55,7 +60,14 @@ impl MapScene {
 }
 
 impl SceneTrait for MapScene {
+    fn get_name(&self) -> &'static str {
+        "map"
+    }
+
     fn update(&mut self, mngrs: &mut Managers, duration: Duration) -> NextScene {
+        // 補間のために直前の位置を保存
+        self.coms.save_positions();
+
         // イベントを実行
         let mut events = Vec::new();
         for event in &self.events {
@@ -65,8 +77,13 @@ impl SceneTrait for MapScene {
         }
         self.events = events;
 
+        // 終了
+        None
+    }
+
+    fn render(&mut self, mngrs: &mut Managers, alpha: f32) {
         // カメラバッファを更新
-        mngrs.gr_mngr.update_camera(&self.coms.camera.get());
+        mngrs.gr_mngr.update_camera(&self.coms.camera.get(alpha));
 
         // 文字画像をすべてロード
         let mut chars = HashSet::new();
@@ -77,30 +94,60 @@ impl SceneTrait for MapScene {
 
         // 描画
         let mut instances = Vec::new();
-        self.coms.push_to(&mut instances, mngrs, should_push_text);
+        self.coms
+            .push_to(&mut instances, mngrs, should_push_text, alpha);
         mngrs.gr_mngr.render_with_metas(instances);
-
-        // 終了
-        None
     }
 }
 
 // DEBUG:
-fn message_event(mngrs: &mut Managers, coms: &mut Components, _: Duration) -> bool {
+fn message_event(mngrs: &mut Managers, coms: &mut Components, duration: Duration) -> bool {
     if coms.message_box.is_none() {
+        // NOTE: キー設定で割り当て直されても正しいキーを案内する。
+        let key = |action| get_key_label(mngrs, action);
+        let message = format!(
+            "メッセージです。{{w}}{}キーで全文を表示し、もう一度押すと次へ進みます。{{p}}\
+             {}キーでオート、{}キーでスキップを切り替えます。",
+            key(Action::Confirm),
+            key(Action::Auto),
+            key(Action::Skip),
+        );
         let mut message_box = MessageBox::new("uis", Vec4::new(0.0, 0.0, 1.0, 1.0));
-        message_box.set_message("UtsukushiFONT.otf", "メッセージです".to_string(), 24.0);
+        message_box.set_message("UtsukushiFONT.otf", message, 24.0);
         coms.message_box = Some(message_box);
     }
 
-    if mngrs.in_mngr.get(&KeyCode::KeyZ) == 1 {
-        coms.message_box = None;
-        return false;
+    if let Some(message_box) = &mut coms.message_box {
+        message_box.update(mngrs, duration);
+        if message_box.is_finished() {
+            coms.message_box = None;
+            return false;
+        }
     }
 
     return true;
 }
 
+/// アクションに割り当てられた最初のキーの表示名を取得する関数。
+///
+/// キーが割り当てられていない場合、"?"を返す。
+fn get_key_label(mngrs: &Managers, action: Action) -> String {
+    let key_code = mngrs
+        .in_mngr
+        .get_action_map()
+        .get_bindings(action)
+        .iter()
+        .find_map(|n| match n {
+            Binding::Key(n) => Some(*n),
+            Binding::Gamepad(_) => None,
+        });
+    let Some(key_code) = key_code else {
+        return "?".to_string();
+    };
+    let name = key::get_name(key_code);
+    name.strip_prefix("Key").unwrap_or(&name).to_string()
+}
+
 // TODO: 引っ越す？
 fn move_player_event(mngrs: &mut Managers, coms: &mut Components, duration: Duration) -> bool {
     // プレイヤーが移動不可であれば早期リターン
@@ -116,16 +163,16 @@ fn move_player_event(mngrs: &mut Managers, coms: &mut Components, duration: Dura
     }
 
     // 新しく入力された方向を取得
-    const DIRECTIONS: [(KeyCode, (i32, i32, ActorDirection)); 4] = [
-        (KeyCode::ArrowLeft, (0, -1, ActorDirection::Left)),
-        (KeyCode::ArrowRight, (0, 1, ActorDirection::Right)),
-        (KeyCode::ArrowUp, (-1, 0, ActorDirection::Up)),
-        (KeyCode::ArrowDown, (1, 0, ActorDirection::Down)),
+    const DIRECTIONS: [(Action, (i32, i32, ActorDirection)); 4] = [
+        (Action::Left, (0, -1, ActorDirection::Left)),
+        (Action::Right, (0, 1, ActorDirection::Right)),
+        (Action::Up, (-1, 0, ActorDirection::Up)),
+        (Action::Down, (1, 0, ActorDirection::Down)),
     ];
     let mut recent_input = None;
     let mut min_state = std::u32::MAX;
-    for (kc, n) in &DIRECTIONS {
-        let state = mngrs.in_mngr.get(kc);
+    for (action, n) in &DIRECTIONS {
+        let state = mngrs.in_mngr.get_action(*action);
         if state > 0 && state < min_state {
             recent_input = Some(n.clone());
             min_state = state;
@@ -0,0 +1,71 @@
+use super::*;
+
+use crate::{client::component::*, *};
+use glam::*;
+use map::MapScene;
+use std::collections::HashSet;
+use winit::keyboard::KeyCode;
+
+/// 名前の最大の文字数。
+const NAME_MAX_LENGTH: usize = 8;
+
+pub struct NameScene {
+    label: Text,
+    field: TextField,
+}
+
+impl NameScene {
+    pub fn new(mngrs: &mut Managers) -> Self {
+        mngrs.in_mngr.start_text_input();
+
+        let label = Text::new(
+            "UtsukushiFONT.otf",
+            "なまえを入力してください".to_string(),
+            32.0,
+        )
+        .with_pos(Vec3::new(SCENE_WIDTH * 0.5, SCENE_HEIGHT * 0.35, 0.0))
+        .with_align(Alignment::Top);
+        let field = TextField::new("UtsukushiFONT.otf", 48.0)
+            .with_pos(Vec3::new(SCENE_WIDTH * 0.35, SCENE_HEIGHT * 0.5, 0.0))
+            .with_max_length(NAME_MAX_LENGTH);
+
+        Self { label, field }
+    }
+}
+
+impl SceneTrait for NameScene {
+    fn get_name(&self) -> &'static str {
+        "name"
+    }
+
+    fn update(&mut self, mngrs: &mut Managers, duration: Duration) -> NextScene {
+        // NOTE: 決定アクションのキー(Z等)は文字として入力されるので、Enterのみで確定する。
+        if mngrs.in_mngr.just_pressed(&KeyCode::Enter)
+            && mngrs.in_mngr.get_preedit().is_empty()
+            && !self.field.get_value().is_empty()
+        {
+            mngrs.in_mngr.stop_text_input();
+            return Some(SceneCommand::Change(Box::new(MapScene::new(mngrs))));
+        }
+
+        self.field.update(mngrs, duration);
+
+        None
+    }
+
+    fn render(&mut self, mngrs: &mut Managers, _: f32) {
+        // すべての文字画像をロード
+        let mut chars = HashSet::new();
+        self.label.collect_characters(&mut chars);
+        self.field.collect_characters(&mut chars);
+        let should_push_text = mngrs
+            .gr_mngr
+            .load_all_character_images(&mut mngrs.rs_mngr, chars);
+
+        // 描画
+        let mut instances = Vec::new();
+        self.label.push_to(&mut instances, mngrs, should_push_text);
+        self.field.push_to(&mut instances, mngrs, should_push_text);
+        mngrs.gr_mngr.render_with_metas(instances);
+    }
+}
@@ -0,0 +1,263 @@
+use super::*;
+
+use crate::engine::{
+    graphic::{
+        display::{self, FullscreenMode},
+        ScalingMode,
+    },
+    input::action::Action,
+};
+use crate::{client::component::*, *};
+use glam::*;
+use keyconfig::KeyConfigScene;
+use std::{collections::HashSet, num::NonZeroU32};
+use title::TitleScene;
+
+/// フレームレートの上限の選択肢。0は上限なし。
+const FRAME_RATE_CAPS: [u32; 6] = [0, 30, 60, 120, 144, 240];
+
+/// 設定項目。
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Item {
+    VSync,
+    FrameRateCap,
+    Fullscreen,
+    FullscreenMode,
+    Monitor,
+    Scaling,
+    KeyConfig,
+    Back,
+}
+
+impl Item {
+    const ALL: [Item; 8] = [
+        Item::VSync,
+        Item::FrameRateCap,
+        Item::Fullscreen,
+        Item::FullscreenMode,
+        Item::Monitor,
+        Item::Scaling,
+        Item::KeyConfig,
+        Item::Back,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Item::VSync => "垂直同期",
+            Item::FrameRateCap => "フレームレート上限",
+            Item::Fullscreen => "画面モード",
+            Item::FullscreenMode => "フルスクリーン方式",
+            Item::Monitor => "モニタ",
+            Item::Scaling => "拡大方式",
+            Item::KeyConfig => "キー設定",
+            Item::Back => "もどる",
+        }
+    }
+}
+
+/// 表示設定を変更するシーン。
+///
+/// 上下で項目を選び、左右で値を変える。変更は即座に適用され、シーンを抜けるときに設定ファイルに保存される。
+/// 「キー設定」の決定でキー設定のシーンに移る。
+pub struct OptionsScene {
+    cursor: usize,
+    /// 項目に表示している設定。
+    ///
+    /// F11等、このシーン以外で設定が変わった場合も表示を更新するために保持する。
+    shown: display::DisplaySettings,
+    /// 接続されているモニタの名前の一覧。
+    monitors: Vec<String>,
+    label: Text,
+    items: Vec<Text>,
+}
+
+impl OptionsScene {
+    pub fn new(mngrs: &mut Managers) -> Self {
+        let label = Text::new("UtsukushiFONT.otf", "設定".to_string(), 40.0)
+            .with_pos(Vec3::new(SCENE_WIDTH * 0.5, SCENE_HEIGHT * 0.15, 0.0))
+            .with_align(Alignment::Top);
+        let items = (0..Item::ALL.len())
+            .map(|i| {
+                Text::new("UtsukushiFONT.otf", String::new(), 32.0).with_pos(Vec3::new(
+                    SCENE_WIDTH * 0.2,
+                    SCENE_HEIGHT * 0.25 + 56.0 * i as f32,
+                    0.0,
+                ))
+            })
+            .collect();
+
+        let mut scene = Self {
+            cursor: 0,
+            shown: mngrs.gr_mngr.get_display_settings().clone(),
+            monitors: mngrs.gr_mngr.get_monitor_names(),
+            label,
+            items,
+        };
+        scene.update_items(mngrs);
+        scene
+    }
+
+    /// 選択中の項目の値をdeltaだけ送るメソッド。
+    fn change(&mut self, mngrs: &mut Managers, delta: isize) {
+        let mut settings = mngrs.gr_mngr.get_display_settings().clone();
+        match Item::ALL[self.cursor] {
+            Item::VSync => settings.vsync = !settings.vsync,
+            Item::FrameRateCap => {
+                let i = FRAME_RATE_CAPS
+                    .iter()
+                    .position(|n| NonZeroU32::new(*n) == settings.frame_rate_cap)
+                    .unwrap_or(0);
+                settings.frame_rate_cap =
+                    NonZeroU32::new(FRAME_RATE_CAPS[cycle(i, FRAME_RATE_CAPS.len(), delta)]);
+            }
+            Item::Fullscreen => settings.is_fullscreen = !settings.is_fullscreen,
+            Item::FullscreenMode => {
+                settings.fullscreen_mode = match settings.fullscreen_mode {
+                    FullscreenMode::Borderless => FullscreenMode::Exclusive,
+                    FullscreenMode::Exclusive => FullscreenMode::Borderless,
+                }
+            }
+            Item::Monitor => {
+                // NOTE: 0番目は「自動」(ウィンドウのあるモニタ)である。
+                let i = settings
+                    .monitor
+                    .as_ref()
+                    .and_then(|name| self.monitors.iter().position(|n| n == name))
+                    .map_or(0, |n| n + 1);
+                let i = cycle(i, self.monitors.len() + 1, delta);
+                settings.monitor = i.checked_sub(1).map(|n| self.monitors[n].clone());
+            }
+            Item::Scaling => {
+                settings.scaling_mode = match settings.scaling_mode {
+                    ScalingMode::Fit => ScalingMode::Integer,
+                    ScalingMode::Integer => ScalingMode::Fit,
+                }
+            }
+            Item::KeyConfig | Item::Back => return,
+        }
+        mngrs.gr_mngr.set_display_settings(settings);
+    }
+
+    /// 項目の文字列と色を現在の設定に合わせるメソッド。
+    fn update_items(&mut self, mngrs: &Managers) {
+        self.shown = mngrs.gr_mngr.get_display_settings().clone();
+        let settings = &self.shown;
+        for (i, (item, text)) in Item::ALL.iter().zip(&mut self.items).enumerate() {
+            let value = match item {
+                Item::VSync => Some(if settings.vsync { "オン" } else { "オフ" }.to_string()),
+                Item::FrameRateCap => Some(match settings.frame_rate_cap {
+                    Some(n) => format!("{n} fps"),
+                    None => "無制限".to_string(),
+                }),
+                Item::Fullscreen => Some(
+                    if settings.is_fullscreen {
+                        "フルスクリーン"
+                    } else {
+                        "ウィンドウ"
+                    }
+                    .to_string(),
+                ),
+                Item::FullscreenMode => Some(
+                    match settings.fullscreen_mode {
+                        FullscreenMode::Borderless => "ボーダーレス",
+                        FullscreenMode::Exclusive => "排他",
+                    }
+                    .to_string(),
+                ),
+                Item::Monitor => Some(settings.monitor.clone().unwrap_or("自動".to_string())),
+                Item::Scaling => Some(
+                    match settings.scaling_mode {
+                        ScalingMode::Fit => "画面に合わせる",
+                        ScalingMode::Integer => "整数倍",
+                    }
+                    .to_string(),
+                ),
+                Item::KeyConfig | Item::Back => None,
+            };
+            text.set_text(match value {
+                Some(n) => format!("{}：{n}", item.label()),
+                None => item.label().to_string(),
+            });
+            text.set_col(if i == self.cursor {
+                Vec4::new(1.0, 1.0, 0.4, 1.0)
+            } else {
+                Vec4::new(1.0, 1.0, 1.0, 1.0)
+            });
+        }
+    }
+}
+
+impl SceneTrait for OptionsScene {
+    fn get_name(&self) -> &'static str {
+        "options"
+    }
+
+    fn update(&mut self, mngrs: &mut Managers, _: Duration) -> NextScene {
+        // 取消か「もどる」の決定で保存してタイトルに戻る
+        let is_back = Item::ALL[self.cursor] == Item::Back;
+        if mngrs.in_mngr.action_just_pressed(Action::Cancel)
+            || is_back && mngrs.in_mngr.action_just_pressed(Action::Confirm)
+        {
+            if let Err(e) = mngrs
+                .gr_mngr
+                .get_display_settings()
+                .save(display::CONFIG_PATH)
+            {
+                eprintln!("failed to save {}: {e}", display::CONFIG_PATH);
+            }
+            return Some(SceneCommand::Change(TitleScene::new(mngrs)));
+        }
+
+        // 「キー設定」の決定でキー設定に移る
+        if Item::ALL[self.cursor] == Item::KeyConfig
+            && mngrs.in_mngr.action_just_pressed(Action::Confirm)
+        {
+            return Some(SceneCommand::Change(Box::new(KeyConfigScene::new(mngrs))));
+        }
+
+        let len = Item::ALL.len();
+        let cursor = self.cursor;
+        if mngrs.in_mngr.action_repeated(Action::Up) {
+            self.cursor = cycle(self.cursor, len, -1);
+        }
+        if mngrs.in_mngr.action_repeated(Action::Down) {
+            self.cursor = cycle(self.cursor, len, 1);
+        }
+        if mngrs.in_mngr.action_repeated(Action::Left) {
+            self.change(mngrs, -1);
+        }
+        if mngrs.in_mngr.action_repeated(Action::Right) {
+            self.change(mngrs, 1);
+        }
+        if cursor != self.cursor || self.shown != *mngrs.gr_mngr.get_display_settings() {
+            self.update_items(mngrs);
+        }
+
+        None
+    }
+
+    fn render(&mut self, mngrs: &mut Managers, _: f32) {
+        // すべての文字画像をロード
+        let mut chars = HashSet::new();
+        self.label.collect_characters(&mut chars);
+        self.items
+            .iter()
+            .for_each(|n| n.collect_characters(&mut chars));
+        let should_push_text = mngrs
+            .gr_mngr
+            .load_all_character_images(&mut mngrs.rs_mngr, chars);
+
+        // 描画
+        let mut instances = Vec::new();
+        self.label.push_to(&mut instances, mngrs, should_push_text);
+        self.items
+            .iter_mut()
+            .for_each(|n| n.push_to(&mut instances, mngrs, should_push_text));
+        mngrs.gr_mngr.render_with_metas(instances);
+    }
+}
+
+/// 長さlenの選択肢の番号indexをdeltaだけ循環して送る関数。
+pub(super) fn cycle(index: usize, len: usize, delta: isize) -> usize {
+    (index as isize + delta).rem_euclid(len as isize) as usize
+}
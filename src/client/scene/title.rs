@@ -1,22 +1,27 @@
 use super::*;
 
+use crate::engine::input::action::Action;
 use crate::{client::component::*, *};
 use glam::*;
-use map::MapScene;
+use name::NameScene;
+use options::OptionsScene;
 use std::collections::HashSet;
 use std::f32;
-use winit::keyboard::KeyCode;
+use winit::event::MouseButton;
 
 pub struct TitleScene {
     total_time: f32,
     bg: Sprite,
     logo: Sprite,
     text: Text,
+    hint: Text,
 }
 
 impl TitleScene {
     pub fn new(mngrs: &mut Managers) -> Scene {
         let _ = mngrs.gr_mngr.load_image(&mngrs.rs_mngr, "title");
+        let _ = mngrs.au_mngr.load_sound(&mngrs.rs_mngr, "title");
+        let _ = mngrs.au_mngr.crossfade_bgm("title", Duration::from_secs(1));
 
         let bg = Sprite::new("title")
             .with_scl(Vec2::new(SCENE_WIDTH, SCENE_HEIGHT))
@@ -36,22 +41,42 @@ impl TitleScene {
             24.0,
         )
         .with_pos(Vec3::new(SCENE_WIDTH * 0.5, SCENE_HEIGHT * 0.75, 0.0))
-        .with_align(Alignment::Center);
+        .with_align(Alignment::Top);
+        let hint = Text::new("UtsukushiFONT.otf", "C KEY: OPTIONS".to_string(), 16.0)
+            .with_pos(Vec3::new(SCENE_WIDTH * 0.5, SCENE_HEIGHT * 0.8, 0.0))
+            .with_align(Alignment::Top);
 
         Box::new(Self {
             total_time: 0.0,
             bg,
             logo,
             text,
+            hint,
         })
     }
 }
 
 impl SceneTrait for TitleScene {
+    fn get_name(&self) -> &'static str {
+        "title"
+    }
+
     fn update(&mut self, mngrs: &mut Managers, duration: Duration) -> NextScene {
-        // Zボタンで次のシーン
-        if mngrs.in_mngr.get(&KeyCode::KeyZ) > 0 {
-            return Some(Box::new(MapScene::new(mngrs)));
+        // 決定かクリックで次のシーン
+        let is_clicked = mngrs.in_mngr.mouse_just_pressed(&MouseButton::Left)
+            && mngrs.in_mngr.get_cursor_canvas().is_some();
+        if mngrs.in_mngr.get_action(Action::Confirm) > 0 || is_clicked {
+            return Some(SceneCommand::Change(Box::new(NameScene::new(mngrs))));
+        }
+
+        // メニューで表示設定
+        if mngrs.in_mngr.action_just_pressed(Action::Menu) {
+            return Some(SceneCommand::Change(Box::new(OptionsScene::new(mngrs))));
+        }
+
+        // 取消でゲームを終了
+        if mngrs.in_mngr.action_just_pressed(Action::Cancel) {
+            return Some(SceneCommand::Quit);
         }
 
         // PRESS Z KE(ry の色を変える
@@ -63,9 +88,15 @@ impl SceneTrait for TitleScene {
             (f32::consts::PI * self.total_time / 2.0).cos().abs(),
         ));
 
+        self.total_time += duration.as_secs_f32();
+        None
+    }
+
+    fn render(&mut self, mngrs: &mut Managers, _: f32) {
         // すべての文字画像をロード
         let mut chars = HashSet::new();
         self.text.collect_characters(&mut chars);
+        self.hint.collect_characters(&mut chars);
         let should_push_text = mngrs
             .gr_mngr
             .load_all_character_images(&mut mngrs.rs_mngr, chars);
@@ -75,9 +106,7 @@ impl SceneTrait for TitleScene {
         self.bg.push_to(&mut instances);
         self.logo.push_to(&mut instances);
         self.text.push_to(&mut instances, mngrs, should_push_text);
+        self.hint.push_to(&mut instances, mngrs, should_push_text);
         mngrs.gr_mngr.render_with_metas(instances);
-
-        self.total_time += duration.as_secs_f32();
-        None
     }
 }
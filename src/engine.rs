@@ -1,9 +1,15 @@
+pub mod audio;
 pub mod graphic;
+pub mod headless;
 pub mod input;
 pub mod resource;
+pub mod stats;
+pub mod timestep;
 
 use std::{
-    mem, slice,
+    mem,
+    path::Path,
+    slice,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -27,6 +33,16 @@ pub struct ApplicationInfo {
     pub window_width: f32,
     pub window_height: f32,
     pub is_fullscreen: bool,
+    /// シーンをウィンドウに拡大縮小する方法。
+    pub scaling_mode: graphic::ScalingMode,
+    /// 入力の記録・再生の設定。Noneであれば記録も再生もしない。
+    pub replay_mode: Option<input::replay::ReplayMode>,
+    /// 描画に用いるバックエンドの候補。先頭から順に試される。
+    pub graphic_backends: Vec<graphic::backend::GraphicBackend>,
+    /// シミュレーションの進め方。
+    pub timestep_mode: timestep::TimestepMode,
+    /// 1フレームの経過時間の上限。これより長いフレームは上限の長さとみなされる。
+    pub max_frame_duration: Duration,
 }
 
 /// マネージャオブジェクトの集合。
@@ -36,6 +52,9 @@ pub struct Managers<'a> {
     pub gr_mngr: graphic::GraphicManager<'a>,
     pub in_mngr: input::InputManager,
     pub rs_mngr: resource::ResourceManager,
+    pub au_mngr: audio::AudioManager,
+    /// 直前までのフレームの統計情報。
+    pub frame_stats: stats::FrameStats,
 }
 
 /// クライアントが実装すべきトレイト。
@@ -44,15 +63,117 @@ pub trait ClientHandler {
     fn new(mngrs: &mut Managers) -> Self;
     /// クライアント更新メソッド。
     ///
+    /// 可変タイムステップの場合、毎フレーム呼ばれる。
     /// アプリケーションを続行する場合true、終了する場合falseを返す。
     fn update(&mut self, mngrs: &mut Managers, duration: Duration) -> bool;
+    /// 固定タイムステップにおけるクライアント更新メソッド。
+    ///
+    /// 一定の時間ごとに呼ばれ、durationは常にその間隔である。
+    /// 1フレームに複数回呼ばれることも、一度も呼ばれないこともある。
+    /// アプリケーションを続行する場合true、終了する場合falseを返す。
+    ///
+    /// 既定ではupdate()を呼ぶ。
+    fn fixed_update(&mut self, mngrs: &mut Managers, duration: Duration) -> bool {
+        self.update(mngrs, duration)
+    }
+    /// 固定タイムステップにおけるクライアント描画メソッド。
+    ///
+    /// 毎フレーム、fixed_update()の後に呼ばれる。
+    /// alphaは直前のステップから次のステップまでの進行度 [0, 1) であり、
+    /// 直前のステップと現在のステップの状態を補間して描画するために用いる。
+    ///
+    /// 既定では何もしない。
+    fn render(&mut self, _mngrs: &mut Managers, _alpha: f32) {}
+    /// クライアント終了メソッド。
+    ///
+    /// アプリケーションが終了する直前に一度だけ呼ばれる。
+    /// クライアントが終了を望んだ場合・ウィンドウが閉じられた場合のいずれでも呼ばれるため、オートセーブ等に用いる。
+    ///
+    /// 既定では何もしない。
+    fn shutdown(&mut self, _mngrs: &mut Managers) {}
+}
+
+/// 1フレーム進める関数。
+///
+/// 経過時間に応じたステップ数をrun_steps()に渡す。
+/// クライアントが終了を望んだ場合、falseを返す。
+fn run_frame<T>(
+    mngrs: &mut Managers,
+    client: &mut T,
+    timestep: &mut timestep::Timestep,
+    elapsed: Duration,
+) -> bool
+where
+    T: ClientHandler,
+{
+    let steps = timestep.accumulate(elapsed);
+    run_steps(mngrs, client, timestep, elapsed, steps)
+}
+
+/// 指定したステップ数だけシミュレーションを進めて1フレームを終える関数。
+///
+/// ウィンドウ・ヘッドレスで共有される。
+/// - 可変タイムステップ：update()を1回呼ぶ(stepsは無視される)
+/// - 固定タイムステップ：fixed_update()をsteps回呼び、render()を呼ぶ
+///
+/// 入力の再生中は、各ステップで記録された入力を反映し、記録された経過時間を用いる。
+/// 最後にフレームの統計情報を記録する。
+/// クライアントが終了を望んだ場合、falseを返す。
+fn run_steps<T>(
+    mngrs: &mut Managers,
+    client: &mut T,
+    timestep: &timestep::Timestep,
+    elapsed: Duration,
+    steps: u32,
+) -> bool
+where
+    T: ClientHandler,
+{
+    let frame_time = elapsed;
+    let elapsed = timestep.clamp(elapsed);
+    let mut running = true;
+
+    match timestep.get_step() {
+        None => {
+            let duration = mngrs.in_mngr.replay_frame().unwrap_or(elapsed);
+            mngrs.in_mngr.update_gamepad();
+            running = client.update(mngrs, duration);
+            mngrs.in_mngr.go_next(duration);
+        }
+        Some(step) => {
+            for _ in 0..steps {
+                let duration = mngrs.in_mngr.replay_frame().unwrap_or(step);
+                mngrs.in_mngr.update_gamepad();
+                running = client.fixed_update(mngrs, duration);
+                mngrs.in_mngr.go_next(duration);
+                if !running {
+                    break;
+                }
+            }
+            if running {
+                client.render(mngrs, timestep.get_alpha());
+            }
+        }
+    }
+
+    mngrs.au_mngr.update(elapsed);
+
+    let render_stats = mngrs.gr_mngr.take_render_stats();
+    mngrs.frame_stats.push(frame_time, render_stats);
+
+    running
 }
 
 /// アプリケーションのコアとなるオブジェクトの集合。
 struct ApplicationCore<'a, T> {
-    _window: Arc<Window>,
+    window: Arc<Window>,
+    /// ウィンドウに適用済みのIMEの状態(有効か否か, 変換候補ウィンドウの領域)。
+    ime_state: (bool, Option<(glam::Vec2, glam::Vec2)>),
+    /// InputManagerに適用済みのビューポート。
+    viewport: Option<graphic::Viewport>,
     mngrs: Managers<'a>,
     client: T,
+    timestep: timestep::Timestep,
 }
 
 /// winitベースウィンドウアプリケーションの構造体。
@@ -78,63 +199,119 @@ where
             return;
         }
 
-        let primary_monitor = event_loop
-            .primary_monitor()
-            .expect("no primary monitor is found.");
-
-        let fullscreen = if self.info.is_fullscreen {
-            Some(Fullscreen::Borderless(Some(primary_monitor)))
-        } else {
-            None
+        // NOTE: 設定ファイルが無い・壊れている場合はApplicationInfoの設定を用いる。
+        let mut display_settings = graphic::display::DisplaySettings {
+            is_fullscreen: self.info.is_fullscreen,
+            scaling_mode: self.info.scaling_mode,
+            ..Default::default()
         };
+        if std::path::Path::new(graphic::display::CONFIG_PATH).exists() {
+            if let Err(e) = display_settings.load(graphic::display::CONFIG_PATH) {
+                eprintln!("failed to load {}: {e}", graphic::display::CONFIG_PATH);
+            }
+        }
 
         let window_attributes = Window::default_attributes()
             .with_title(self.info.title)
-            .with_resizable(false)
+            .with_resizable(true)
             .with_inner_size(LogicalSize::new(
                 self.info.window_width,
                 self.info.window_height,
-            ))
-            .with_fullscreen(fullscreen);
+            ));
         let window = event_loop
             .create_window(window_attributes)
             .expect("failed to create a window.");
-        window.set_enabled_buttons(WindowButtons::CLOSE | WindowButtons::MINIMIZE);
-
         let window = Arc::new(window);
 
-        let gr_mngr = graphic::GraphicManager::new(
+        // NOTE: アダプタ選択の診断が複数行になるので、expect()ではなくDisplayで表示する。
+        let mut gr_mngr = graphic::GraphicManager::new(
             window.clone(),
             self.info.scene_width,
             self.info.scene_height,
+            &self.info.graphic_backends,
         )
-        .expect("failed to create a graphic manager.");
-        let in_mngr = input::InputManager::new();
-        let rs_mngr = resource::ResourceManager::new();
+        .unwrap_or_else(|e| panic!("failed to create a graphic manager: {e}"));
+        eprintln!(
+            "graphic adapter: {}",
+            graphic::backend::describe_adapter(gr_mngr.get_adapter_info())
+        );
+        gr_mngr.set_display_settings(display_settings);
+        let mut in_mngr = input::InputManager::new();
+        // NOTE: 設定ファイルが無い・壊れている場合は既定のバインディングを用いる。
+        if std::path::Path::new(input::action::CONFIG_PATH).exists() {
+            if let Err(e) = in_mngr
+                .get_action_map_mut()
+                .load(input::action::CONFIG_PATH)
+            {
+                eprintln!("failed to load {}: {e}", input::action::CONFIG_PATH);
+            }
+        }
+        in_mngr.set_gamepad(input::gamepad::create_default_backend());
+        match &self.info.replay_mode {
+            Some(input::replay::ReplayMode::Record(path)) => {
+                if let Err(e) = in_mngr.start_recording(path) {
+                    eprintln!("failed to start recording to {}: {e}", path.display());
+                }
+            }
+            Some(input::replay::ReplayMode::Replay(path)) => {
+                let replay =
+                    input::replay::InputReplay::load(path).expect("failed to load a replay.");
+                in_mngr
+                    .start_replay(replay)
+                    .expect("failed to start a replay.");
+            }
+            None => (),
+        }
+        let rs_mngr =
+            resource::ResourceManager::new().expect("failed to create a resource manager.");
+        let au_mngr = audio::AudioManager::new(audio::backend::create_default_backend());
         let mut mngrs = Managers {
             gr_mngr,
             in_mngr,
             rs_mngr,
+            au_mngr,
+            frame_stats: stats::FrameStats::new(),
         };
 
         let client = T::new(&mut mngrs);
 
+        let timestep =
+            timestep::Timestep::new(self.info.timestep_mode, self.info.max_frame_duration);
+
         self.core = Some(ApplicationCore {
-            _window: window,
+            window,
+            ime_state: (false, None),
+            viewport: None,
             mngrs,
             client,
+            timestep,
         });
     }
 
     /// ウィンドウイベントを処理するメソッド。
     ///
     /// - ウィンドウ破棄イベント -> アプリケーション終了
-    /// - キーボード入力イベント -> InputManager
+    /// - キーボード・マウス・IME入力イベント -> InputManager
+    /// - リサイズイベント -> GraphicManager
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
         let Some(core) = &mut self.core else {
             return;
         };
 
+        // NOTE: 入力の再生中は実際の入力を無視する。
+        let is_input = matches!(
+            event,
+            WindowEvent::KeyboardInput { .. }
+                | WindowEvent::Ime(_)
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::CursorMoved { .. }
+                | WindowEvent::CursorLeft { .. }
+        );
+        if is_input && core.mngrs.in_mngr.is_replaying() {
+            return;
+        }
+
         match event {
             WindowEvent::KeyboardInput {
                 device_id: _,
@@ -143,6 +320,35 @@ where
             } => {
                 core.mngrs.in_mngr.on_key_event_happened(event);
             }
+            WindowEvent::Ime(ime) => core.mngrs.in_mngr.on_ime_happened(ime),
+            WindowEvent::MouseInput {
+                device_id: _,
+                state,
+                button,
+            } => {
+                if state.is_pressed() {
+                    core.mngrs.in_mngr.press_mouse(button);
+                } else {
+                    core.mngrs.in_mngr.release_mouse(button);
+                }
+            }
+            WindowEvent::MouseWheel {
+                device_id: _,
+                delta,
+                phase: _,
+            } => {
+                core.mngrs.in_mngr.on_mouse_wheel_happened(delta);
+            }
+            WindowEvent::CursorMoved {
+                device_id: _,
+                position,
+            } => {
+                let position = glam::Vec2::new(position.x as f32, position.y as f32);
+                core.mngrs.in_mngr.move_cursor(Some(position));
+            }
+            WindowEvent::CursorLeft { device_id: _ } => core.mngrs.in_mngr.move_cursor(None),
+            // NOTE: HiDPI環境でスケールファクタが変わった場合も、実際のサイズでこれが通知される。
+            WindowEvent::Resized(size) => core.mngrs.gr_mngr.resize(size.width, size.height),
             WindowEvent::CloseRequested => event_loop.exit(),
             WindowEvent::Destroyed => event_loop.exit(),
             _ => (),
@@ -157,34 +363,82 @@ where
         let Some(core) = &mut self.core else {
             return;
         };
+        // NOTE: 終了を要求した後もイベントループが回る場合があるので、クライアントを更新しない。
+        if event_loop.exiting() {
+            return;
+        }
+
+        // フレームレートの上限を超えるならば、次のフレームの時刻まで待機
+        if let Some(cap) = core.mngrs.gr_mngr.get_display_settings().frame_rate_cap {
+            let next = self.last + Duration::from_secs(1) / cap.get();
+            if Instant::now() < next {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(next));
+                return;
+            }
+        }
+        event_loop.set_control_flow(ControlFlow::Poll);
 
         let duration = self.last.elapsed();
         self.last = Instant::now();
 
-        #[cfg(debug_assertions)]
-        {
-            use std::io::Write;
-            let mut stdout = std::io::stdout();
-            write!(stdout, "\x1B[2J\x1B[H").unwrap();
-            stdout.flush().unwrap();
-            println!(
-                "duration: {:.1} ms ({:.1} fps)",
-                duration.as_secs_f32() * 1000.0,
-                1.0 / duration.as_secs_f32(),
+        // シーンが描画される領域が変わっていればInputManagerに反映
+        // NOTE: 入力の再生中は記録された領域を用いる。
+        let viewport = core.mngrs.gr_mngr.get_viewport();
+        if !core.mngrs.in_mngr.is_replaying() && core.viewport != Some(viewport) {
+            core.mngrs.in_mngr.set_viewport(
+                glam::Vec2::new(viewport.x, viewport.y),
+                glam::Vec2::new(viewport.width, viewport.height),
+                glam::Vec2::new(self.info.scene_width as f32, self.info.scene_height as f32),
             );
+            core.viewport = Some(viewport);
         }
 
-        if !core.client.update(&mut core.mngrs, duration) {
+        let was_replaying = core.mngrs.in_mngr.is_replaying();
+        if !run_frame(
+            &mut core.mngrs,
+            &mut core.client,
+            &mut core.timestep,
+            duration,
+        ) {
             event_loop.exit();
         }
 
-        core.mngrs.in_mngr.go_next();
+        // 再生が終わったら、次のフレームで実際の領域を反映
+        if was_replaying && !core.mngrs.in_mngr.is_replaying() {
+            eprintln!("the replay has finished.");
+            core.viewport = None;
+        }
+
+        // IMEの状態が変わっていればウィンドウに適用
+        let ime_state = (
+            core.mngrs.in_mngr.is_text_input_active(),
+            core.mngrs.in_mngr.get_ime_cursor_area(),
+        );
+        if ime_state != core.ime_state {
+            core.window.set_ime_allowed(ime_state.0);
+            if let (true, Some((pos, size))) = ime_state {
+                core.window.set_ime_cursor_area(
+                    PhysicalPosition::new(pos.x, pos.y),
+                    PhysicalSize::new(size.x, size.y),
+                );
+            }
+            core.ime_state = ime_state;
+        }
+    }
+
+    /// イベントループが終了する直前に呼ばれるメソッド。
+    ///
+    /// クライアントの終了メソッドを呼ぶ。
+    fn exiting(&mut self, _: &ActiveEventLoop) {
+        if let Some(core) = &mut self.core {
+            core.client.shutdown(&mut core.mngrs);
+        }
     }
 }
 
 /// winitベースウィンドウアプリケーションを実行する関数。
 ///
-/// ウィンドウが閉じられるまでスレッドを待機する。
+/// ウィンドウが閉じられる・クライアントが終了を望むまでスレッドを待機する。
 pub fn run<T>(info: ApplicationInfo) -> Result<(), EError>
 where
     T: ClientHandler,
@@ -198,3 +452,26 @@ where
     })?;
     Ok(())
 }
+
+/// ウィンドウを作らずに、記録された入力を再生する関数。
+///
+/// info.replay_modeに再生するファイルを指定すること。
+/// 再生し終えたら、capture_pathが指定されていれば最後のフレームをPNGファイルに保存する。
+pub fn run_headless<T>(info: ApplicationInfo, capture_path: Option<&Path>) -> Result<(), EError>
+where
+    T: ClientHandler,
+{
+    let Some(input::replay::ReplayMode::Replay(path)) = &info.replay_mode else {
+        return Err("headless mode requires a replay.".into());
+    };
+    let replay = input::replay::InputReplay::load(path)?;
+
+    let mut runner = headless::HeadlessRunner::<T>::new(&info)?;
+    let count = runner.run_replay(replay)?;
+    eprintln!("replayed {count} frames.");
+    if let Some(path) = capture_path {
+        runner.capture()?.save_png(path)?;
+    }
+    runner.close();
+    Ok(())
+}
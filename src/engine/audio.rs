@@ -0,0 +1,260 @@
+pub mod backend;
+pub mod mixer;
+mod wav;
+
+use super::*;
+
+use crate::engine::resource::ResourceManager;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// 音声の再生を管理するオブジェクト。
+///
+/// - BGMは1つのみ再生され、ループする(ループ区間が指定されていれば、イントロを一度だけ再生してからループ区間をループする)
+/// - BGMはクロスフェードで切り替えられる
+/// - 効果音は重ねて再生でき、終端まで再生されたら破棄される
+/// - 音量はマスター・BGM・効果音の3系統で、いずれも[0.0, 1.0]
+///
+/// 実際の出力はバックエンドが担う。
+pub struct AudioManager {
+    mixer: Arc<Mutex<mixer::Mixer>>,
+    backend: Box<dyn backend::AudioBackend>,
+    sounds: HashMap<&'static str, Arc<wav::Sound>>,
+}
+
+impl AudioManager {
+    /// コンストラクタ。
+    ///
+    /// バックエンドの接続に失敗した場合、ヌルバックエンドを用いる。
+    pub fn new(backend: Box<dyn backend::AudioBackend>) -> Self {
+        let mixer = Arc::new(Mutex::new(mixer::Mixer::new(backend.sample_rate())));
+        let mut au_mngr = Self {
+            mixer,
+            backend: Box::new(backend::NullBackend::new()),
+            sounds: HashMap::new(),
+        };
+        if let Err(e) = au_mngr.set_backend(backend) {
+            eprintln!("failed to attach an audio backend: {e}");
+            let _ = au_mngr.set_backend(Box::new(backend::NullBackend::new()));
+        }
+        au_mngr
+    }
+
+    /// バックエンドを差し替えるメソッド。
+    ///
+    /// 再生中の音声は再生位置を保ったまま新たなバックエンドから出力される。
+    pub fn set_backend(
+        &mut self,
+        mut backend: Box<dyn backend::AudioBackend>,
+    ) -> Result<(), EError> {
+        backend.attach(self.mixer.clone())?;
+        self.lock().set_sample_rate(backend.sample_rate());
+        self.backend = backend;
+        Ok(())
+    }
+
+    /// 音声リソースをロードするメソッド。
+    ///
+    /// 音声リソースはWAVファイル(id.wav)である。
+    /// ループ区間はWAVファイルのsmplチャンクから読み込まれる。
+    ///
+    /// WARN: 既に音声リソースがidでロードされている場合、エラーを返す。
+    pub fn load_sound(
+        &mut self,
+        rs_mngr: &ResourceManager,
+        id: &'static str,
+    ) -> Result<(), EError> {
+        if self.sounds.contains_key(id) {
+            return Err(format!("sound '{id}' is already registered.").into());
+        }
+        let sound = wav::parse(&rs_mngr.read(&format!("{id}.wav"))?)?;
+        self.sounds.insert(id, Arc::new(sound));
+        Ok(())
+    }
+
+    /// BGMをクロスフェードで切り替えるメソッド。
+    ///
+    /// 再生中のBGMをlengthかけてフェードアウトさせつつ、新しいBGMをlengthかけてフェードインさせる。
+    /// フェードはupdate()に渡される経過時間に従って進む。
+    /// 既にidのBGMが再生中の場合、何もしない。
+    pub fn crossfade_bgm(&mut self, id: &'static str, length: Duration) -> Result<(), EError> {
+        if self.get_bgm_id() == Some(id) {
+            return Ok(());
+        }
+        let sound = self.get_sound(id)?;
+        self.lock().play_bgm(id, sound, Some(length));
+        Ok(())
+    }
+
+    /// 再生中のBGMのidを取得するメソッド。
+    ///
+    /// フェードアウト中のBGMは含まない。
+    pub fn get_bgm_id(&self) -> Option<&'static str> {
+        self.lock().bgm_id()
+    }
+
+    /// 毎フレーム呼ばれるメソッド。
+    ///
+    /// フェードを経過時間分だけ進める。
+    /// また、プッシュ型のバックエンドは経過時間分だけ出力を進める。
+    pub fn update(&mut self, duration: Duration) {
+        self.lock().update(duration);
+        self.backend.update(duration);
+    }
+
+    fn get_sound(&self, id: &'static str) -> Result<Arc<wav::Sound>, EError> {
+        self.sounds
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("sound '{id}' is not registered.").into())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, mixer::Mixer> {
+        // NOTE: オーディオスレッドがパニックしてもミキサの状態は壊れないので、毒は無視する。
+        self.mixer.lock().unwrap_or_else(|n| n.into_inner())
+    }
+}
+
+// NOTE: 次のメソッドはゲームからはまだ使われていないが、エンジンのAPIとして提供する(テストで検証している)。
+#[allow(dead_code)]
+impl AudioManager {
+    /// 音声リソースをアンロードするメソッド。
+    ///
+    /// 再生中の音声は再生し終わるまで破棄されない。
+    pub fn unload_sound(&mut self, id: &'static str) {
+        self.sounds.remove(id);
+    }
+
+    /// BGMを再生するメソッド。
+    ///
+    /// 既に再生中のBGMは停止される。
+    pub fn play_bgm(&mut self, id: &'static str) -> Result<(), EError> {
+        let sound = self.get_sound(id)?;
+        self.lock().play_bgm(id, sound, None);
+        Ok(())
+    }
+
+    /// BGMをlengthかけてフェードアウトさせて停止するメソッド。
+    pub fn fade_out_bgm(&mut self, length: Duration) {
+        self.lock().fade_out_bgm(length);
+    }
+
+    /// BGMを即座に停止するメソッド。
+    pub fn stop_bgm(&mut self) {
+        self.lock().stop_bgm();
+    }
+
+    /// BGMが再生中か否かを取得するメソッド。
+    pub fn is_bgm_playing(&self) -> bool {
+        self.get_bgm_id().is_some()
+    }
+
+    /// 効果音を再生するメソッド。
+    ///
+    /// 再生中の効果音に重ねて再生される。
+    pub fn play_se(&mut self, id: &'static str) -> Result<(), EError> {
+        let sound = self.get_sound(id)?;
+        self.lock().play_se(id, sound);
+        Ok(())
+    }
+
+    /// すべての効果音を停止するメソッド。
+    pub fn stop_all_se(&mut self) {
+        self.lock().stop_all_se();
+    }
+
+    /// 再生中の効果音の数を取得するメソッド。
+    pub fn get_playing_se_count(&self) -> usize {
+        self.lock().playing_se_count()
+    }
+
+    pub fn get_master_volume(&self) -> f32 {
+        self.lock().master_volume
+    }
+    pub fn get_bgm_volume(&self) -> f32 {
+        self.lock().bgm_volume
+    }
+    pub fn get_se_volume(&self) -> f32 {
+        self.lock().se_volume
+    }
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.lock().master_volume = volume.clamp(0.0, 1.0);
+    }
+    pub fn set_bgm_volume(&mut self, volume: f32) {
+        self.lock().bgm_volume = volume.clamp(0.0, 1.0);
+    }
+    pub fn set_se_volume(&mut self, volume: f32) {
+        self.lock().se_volume = volume.clamp(0.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_mixed_output() {
+        let path = std::env::temp_dir().join(format!("prototype-{}.wav", std::process::id()));
+        let sample_rate = backend::DEFAULT_SAMPLE_RATE;
+        let backend = backend::WavWriterBackend::create(&path, sample_rate).unwrap();
+        let mut au_mngr = AudioManager::new(Box::new(backend));
+        let frames = vec![[0.5, -0.25]; sample_rate as usize / 10];
+        au_mngr.sounds.insert(
+            "se",
+            Arc::new(wav::Sound::from_frames(sample_rate, &frames, None)),
+        );
+
+        au_mngr.set_se_volume(0.5);
+        au_mngr.play_se("se").unwrap();
+        assert!(au_mngr.play_se("unknown").is_err());
+        assert_eq!(au_mngr.get_playing_se_count(), 1);
+
+        // 0.2秒分書き出すと、前半に効果音が、後半に無音が書き出される
+        au_mngr.update(Duration::from_millis(100));
+        au_mngr.update(Duration::from_millis(100));
+        assert_eq!(au_mngr.get_playing_se_count(), 0);
+        drop(au_mngr);
+
+        let sound = wav::parse(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(sound.sample_rate, sample_rate);
+        assert_eq!(sound.length, 2 * frames.len());
+        let mut written = vec![[0.0; 2]; sound.length];
+        sound.decode(0, &mut written);
+        let quantize = |n: f32| (n * i16::MAX as f32) as i16 as f32 / 32768.0;
+        assert!(written[..frames.len()]
+            .iter()
+            .all(|n| *n == [quantize(0.25), quantize(-0.125)]));
+        assert!(written[frames.len()..].iter().all(|n| *n == [0.0; 2]));
+    }
+
+    #[test]
+    fn switches_bgm() {
+        let mut au_mngr = AudioManager::new(Box::new(backend::NullBackend::new()));
+        let sound = wav::Sound::from_frames(backend::DEFAULT_SAMPLE_RATE, &[[0.0; 2]; 100], None);
+        let sound = Arc::new(sound);
+        au_mngr.sounds.insert("a", sound.clone());
+        au_mngr.sounds.insert("b", sound);
+
+        au_mngr.play_bgm("a").unwrap();
+        assert_eq!(au_mngr.get_bgm_id(), Some("a"));
+        au_mngr.crossfade_bgm("b", Duration::from_secs(1)).unwrap();
+        assert_eq!(au_mngr.get_bgm_id(), Some("b"));
+        au_mngr.fade_out_bgm(Duration::from_secs(1));
+        assert!(!au_mngr.is_bgm_playing());
+
+        // ループするBGMは再生し続ける
+        au_mngr.play_bgm("a").unwrap();
+        au_mngr.update(Duration::from_secs(3));
+        assert!(au_mngr.is_bgm_playing());
+        au_mngr.stop_bgm();
+        assert!(!au_mngr.is_bgm_playing());
+
+        au_mngr.unload_sound("a");
+        assert!(au_mngr.play_bgm("a").is_err());
+        au_mngr.set_master_volume(2.0);
+        assert_eq!(au_mngr.get_master_volume(), 1.0);
+    }
+}
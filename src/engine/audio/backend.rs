@@ -0,0 +1,274 @@
+use super::*;
+
+#[cfg(test)]
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// ヌルバックエンド・WAVライタバックエンドのサンプリング周波数 [Hz]。
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/// 音声の出力先を抽象化するトレイト。
+///
+/// - プル型：オーディオスレッドがミキサから必要な分だけサンプルを取り出す(例：cpal)
+/// - プッシュ型：毎フレームupdate()で経過時間分だけサンプルを取り出す(例：ヌル・WAVライタ)
+pub trait AudioBackend {
+    /// 出力のサンプリング周波数 [Hz] を取得するメソッド。
+    fn sample_rate(&self) -> u32;
+    /// ミキサを接続するメソッド。
+    ///
+    /// AudioManagerに渡されたときに一度だけ呼ばれる。
+    fn attach(&mut self, mixer: Arc<Mutex<mixer::Mixer>>) -> Result<(), EError>;
+    /// 毎フレーム呼ばれるメソッド。
+    fn update(&mut self, duration: Duration);
+}
+
+/// プッシュ型バックエンドのためにミキサから経過時間分のサンプルを取り出すヘルパーオブジェクト。
+struct Pusher {
+    mixer: Option<Arc<Mutex<mixer::Mixer>>>,
+    sample_rate: u32,
+    /// 前フレームまでに取り出しきれなかった端数 [フレーム]。
+    remainder: f64,
+    buffer: Vec<f32>,
+}
+
+impl Pusher {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            mixer: None,
+            sample_rate,
+            remainder: 0.0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// 経過時間分のサンプルを取り出すメソッド。
+    fn pull(&mut self, duration: Duration) -> &[f32] {
+        let Some(mixer) = &self.mixer else {
+            return &[];
+        };
+
+        let frames = duration.as_secs_f64() * self.sample_rate as f64 + self.remainder;
+        self.remainder = frames.fract();
+        self.buffer.resize(2 * frames as usize, 0.0);
+
+        match mixer.lock() {
+            Ok(mut n) => n.mix(&mut self.buffer),
+            Err(_) => self.buffer.fill(0.0),
+        }
+        &self.buffer
+    }
+}
+
+/// 何も出力しないバックエンド。
+///
+/// サウンドデバイスの無い環境で用いる。
+/// 再生位置は経過時間に従って進む。
+pub struct NullBackend {
+    pusher: Pusher,
+}
+
+impl NullBackend {
+    pub fn new() -> Self {
+        Self {
+            pusher: Pusher::new(DEFAULT_SAMPLE_RATE),
+        }
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn sample_rate(&self) -> u32 {
+        self.pusher.sample_rate
+    }
+
+    fn attach(&mut self, mixer: Arc<Mutex<mixer::Mixer>>) -> Result<(), EError> {
+        self.pusher.mixer = Some(mixer);
+        Ok(())
+    }
+
+    fn update(&mut self, duration: Duration) {
+        self.pusher.pull(duration);
+    }
+}
+
+/// 出力を16bitステレオのWAVファイルに書き出すバックエンド。
+///
+/// 出力を検証するためのもので、テストでのみ用いる。
+/// ヘッダのサイズ情報はドロップ時に書き込まれる。
+#[cfg(test)]
+pub struct WavWriterBackend {
+    pusher: Pusher,
+    writer: BufWriter<File>,
+    data_size: u32,
+}
+
+#[cfg(test)]
+impl WavWriterBackend {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32) -> Result<Self, EError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_wav_header(&mut writer, sample_rate, 0)?;
+        Ok(Self {
+            pusher: Pusher::new(sample_rate),
+            writer,
+            data_size: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+impl AudioBackend for WavWriterBackend {
+    fn sample_rate(&self) -> u32 {
+        self.pusher.sample_rate
+    }
+
+    fn attach(&mut self, mixer: Arc<Mutex<mixer::Mixer>>) -> Result<(), EError> {
+        self.pusher.mixer = Some(mixer);
+        Ok(())
+    }
+
+    fn update(&mut self, duration: Duration) {
+        for n in self.pusher.pull(duration) {
+            let n = (n * i16::MAX as f32) as i16;
+            // WARN: 書き込みに失敗した場合、そのサンプルは捨てられる。
+            if self.writer.write_all(&n.to_le_bytes()).is_ok() {
+                self.data_size += 2;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl Drop for WavWriterBackend {
+    fn drop(&mut self) {
+        let _ = self.writer.seek(SeekFrom::Start(0)).and_then(|_| {
+            write_wav_header(&mut self.writer, self.pusher.sample_rate, self.data_size)?;
+            self.writer.flush()
+        });
+    }
+}
+
+/// 16bitステレオのWAVファイルのヘッダを書き込む関数。
+#[cfg(test)]
+fn write_wav_header<W: Write>(
+    writer: &mut W,
+    sample_rate: u32,
+    data_size: u32,
+) -> std::io::Result<()> {
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(4 * sample_rate).to_le_bytes())?;
+    writer.write_all(&4u16.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    Ok(())
+}
+
+/// サウンドデバイスに出力するバックエンド。
+///
+/// cpalのオーディオスレッドがミキサから直接サンプルを取り出す。
+#[cfg(feature = "cpal")]
+pub struct CpalBackend {
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    stream: Option<cpal::Stream>,
+}
+
+#[cfg(feature = "cpal")]
+impl CpalBackend {
+    /// 既定の出力デバイスを開くメソッド。
+    pub fn new() -> Result<Self, EError> {
+        use cpal::traits::{DeviceTrait, HostTrait};
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device is found.")?;
+        let config = device.default_output_config()?;
+        Ok(Self {
+            device,
+            config,
+            stream: None,
+        })
+    }
+
+    fn build_stream<T>(&self, mixer: Arc<Mutex<mixer::Mixer>>) -> Result<cpal::Stream, EError>
+    where
+        T: cpal::SizedSample + cpal::FromSample<f32>,
+    {
+        use cpal::traits::DeviceTrait;
+        use std::sync::TryLockError;
+
+        let config: cpal::StreamConfig = self.config.clone().into();
+        let channels = config.channels as usize;
+        let mut buffer = Vec::new();
+        let stream = self.device.build_output_stream(
+            &config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                buffer.resize(2 * (data.len() / channels), 0.0);
+                // NOTE: オーディオスレッドをブロックしないよう、メインスレッドがミキサを操作中であれば無音とする。
+                match mixer.try_lock() {
+                    Ok(mut n) => n.mix(&mut buffer),
+                    Err(TryLockError::Poisoned(n)) => n.into_inner().mix(&mut buffer),
+                    Err(TryLockError::WouldBlock) => buffer.fill(0.0),
+                }
+                // ステレオをデバイスのチャンネル数に合わせる
+                for (frame, lr) in data.chunks_mut(channels).zip(buffer.chunks_exact(2)) {
+                    for (c, n) in frame.iter_mut().enumerate() {
+                        let sample = match (channels, c) {
+                            (1, _) => (lr[0] + lr[1]) * 0.5,
+                            (_, 0) => lr[0],
+                            (_, 1) => lr[1],
+                            _ => 0.0,
+                        };
+                        *n = T::from_sample(sample);
+                    }
+                }
+            },
+            |e| eprintln!("audio stream error: {e}"),
+            None,
+        )?;
+        Ok(stream)
+    }
+}
+
+#[cfg(feature = "cpal")]
+impl AudioBackend for CpalBackend {
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate().0
+    }
+
+    fn attach(&mut self, mixer: Arc<Mutex<mixer::Mixer>>) -> Result<(), EError> {
+        use cpal::{traits::StreamTrait, SampleFormat};
+
+        let stream = match self.config.sample_format() {
+            SampleFormat::F32 => self.build_stream::<f32>(mixer)?,
+            SampleFormat::I16 => self.build_stream::<i16>(mixer)?,
+            SampleFormat::U16 => self.build_stream::<u16>(mixer)?,
+            n => return Err(format!("unsupported sample format {n}.").into()),
+        };
+        stream.play()?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    fn update(&mut self, _: Duration) {}
+}
+
+/// 既定のバックエンドを作成する関数。
+///
+/// サウンドデバイスを開けない場合、ヌルバックエンドを返す。
+pub fn create_default_backend() -> Box<dyn AudioBackend> {
+    #[cfg(feature = "cpal")]
+    if let Ok(n) = CpalBackend::new() {
+        return Box::new(n);
+    }
+    Box::new(NullBackend::new())
+}
@@ -0,0 +1,394 @@
+use super::*;
+
+use std::collections::VecDeque;
+
+/// 音量のフェード。
+struct Fade {
+    from: f32,
+    to: f32,
+    /// 経過時間 [s]。
+    elapsed: f32,
+    /// フェードにかける時間 [s]。
+    length: f32,
+    /// フェード完了時に停止するか否か。
+    stop: bool,
+}
+
+/// 一度にデコードするフレーム数。
+const CHUNK_LENGTH: usize = 1024;
+
+/// 再生中の音声1つ分の状態。
+///
+/// 音声データは再生位置の先をCHUNK_LENGTHずつリングバッファへデコードしながら再生する。
+struct Voice {
+    id: &'static str,
+    sound: Arc<wav::Sound>,
+    /// デコード済みで未再生のフレーム。先頭が現在のフレームである。
+    ///
+    /// ループ区間の終端に達したら始端からデコードするため、ループの継ぎ目も連続している。
+    buffer: VecDeque<[f32; 2]>,
+    /// デコードの作業領域。
+    chunk: Vec<[f32; 2]>,
+    /// 次にデコードするフレーム。
+    cursor: usize,
+    /// 現在のフレームから次のフレームまでの再生位置 [0, 1)。
+    phase: f64,
+    /// ループ区間の終端に達したら始端に戻るか否か。
+    looping: bool,
+    /// 音量 [0.0, 1.0]。
+    volume: f32,
+    /// 前回の合成時に用いた音量。
+    ///
+    /// 音量の変化によるノイズを防ぐため、合成時にはこの値からvolumeまで線形に変化させる。
+    applied_volume: f32,
+    fade: Option<Fade>,
+}
+
+impl Voice {
+    fn new(id: &'static str, sound: Arc<wav::Sound>, looping: bool, volume: f32) -> Self {
+        // NOTE: オーディオスレッドで確保しないよう、バッファはここで確保しておく。
+        Self {
+            id,
+            sound,
+            buffer: VecDeque::with_capacity(2 * CHUNK_LENGTH),
+            chunk: vec![[0.0; 2]; CHUNK_LENGTH],
+            cursor: 0,
+            phase: 0.0,
+            looping,
+            volume,
+            applied_volume: volume,
+            fade: None,
+        }
+    }
+
+    /// 未再生のフレームが2つ(補間に要する数)未満であれば、次のチャンクをデコードするメソッド。
+    fn fill(&mut self) {
+        let length = self.sound.length;
+        let loop_range = self.sound.loop_range.clone().unwrap_or(0..length);
+        let end = if self.looping { loop_range.end } else { length };
+        while self.buffer.len() < 2 {
+            if self.looping && self.cursor >= end {
+                self.cursor = loop_range.start;
+            }
+            let count = end.saturating_sub(self.cursor).min(CHUNK_LENGTH);
+            if count == 0 {
+                return;
+            }
+            self.sound.decode(self.cursor, &mut self.chunk[..count]);
+            self.buffer.extend(&self.chunk[..count]);
+            self.cursor += count;
+        }
+    }
+
+    /// 現在の音量からtoまでlength秒かけてフェードさせるメソッド。
+    fn fade_to(&mut self, to: f32, length: Duration, stop: bool) {
+        self.fade = Some(Fade {
+            from: self.volume,
+            to,
+            elapsed: 0.0,
+            length: length.as_secs_f32(),
+            stop,
+        });
+    }
+
+    /// フェードを経過時間分だけ進めるメソッド。
+    ///
+    /// フェードが完了して停止すべき場合、falseを返す。
+    fn update(&mut self, duration: Duration) -> bool {
+        let Some(fade) = &mut self.fade else {
+            return true;
+        };
+        fade.elapsed += duration.as_secs_f32();
+        let t = if fade.length > 0.0 {
+            (fade.elapsed / fade.length).min(1.0)
+        } else {
+            1.0
+        };
+        self.volume = fade.from + (fade.to - fade.from) * t;
+        if t < 1.0 {
+            return true;
+        }
+        let stop = fade.stop;
+        self.fade = None;
+        !stop
+    }
+
+    /// outへ加算合成するメソッド。
+    ///
+    /// 再生が終了した場合、falseを返す。
+    fn mix(&mut self, out: &mut [f32], sample_rate: u32, gain: f32) -> bool {
+        // NOTE: サンプリング周波数が異なる場合は線形補間でリサンプリングする。
+        let step = self.sound.sample_rate as f64 / sample_rate as f64;
+        let count = (out.len() / 2).max(1) as f32;
+        for (k, n) in out.chunks_exact_mut(2).enumerate() {
+            self.fill();
+            let Some(&a) = self.buffer.front() else {
+                return false;
+            };
+            // NOTE: 終端では最後のフレームを保つ。
+            let b = self.buffer.get(1).copied().unwrap_or(a);
+
+            let t = self.phase as f32;
+            let volume =
+                self.applied_volume + (self.volume - self.applied_volume) * (k as f32 / count);
+            let g = gain * volume;
+            n[0] += g * (a[0] + (b[0] - a[0]) * t);
+            n[1] += g * (a[1] + (b[1] - a[1]) * t);
+
+            self.phase += step;
+            while self.phase >= 1.0 {
+                self.phase -= 1.0;
+                self.fill();
+                if self.buffer.pop_front().is_none() {
+                    break;
+                }
+            }
+        }
+        self.applied_volume = self.volume;
+        true
+    }
+}
+
+/// 再生中のすべての音声を合成するオブジェクト。
+///
+/// 出力はインターリーブされたステレオのf32である。
+/// バックエンドのオーディオスレッドから呼ばれ得るため、Arc<Mutex<_>>で共有される。
+pub struct Mixer {
+    sample_rate: u32,
+    pub(super) master_volume: f32,
+    pub(super) bgm_volume: f32,
+    pub(super) se_volume: f32,
+    /// 現在のBGM。
+    bgm: Option<Voice>,
+    /// フェードアウト中の以前のBGM。
+    fading_bgms: Vec<Voice>,
+    ses: Vec<Voice>,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            master_volume: 1.0,
+            bgm_volume: 1.0,
+            se_volume: 1.0,
+            bgm: None,
+            fading_bgms: Vec::new(),
+            ses: Vec::new(),
+        }
+    }
+
+    /// 出力のサンプリング周波数 [Hz] を設定するメソッド。
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// outを再生中のすべての音声を合成したもので上書きするメソッド。
+    ///
+    /// outはインターリーブされたステレオのサンプル列である。
+    pub fn mix(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        let sample_rate = self.sample_rate;
+
+        let bgm_gain = self.master_volume * self.bgm_volume;
+        if let Some(n) = &mut self.bgm {
+            if !n.mix(out, sample_rate, bgm_gain) {
+                self.bgm = None;
+            }
+        }
+        self.fading_bgms
+            .retain_mut(|n| n.mix(out, sample_rate, bgm_gain));
+
+        let se_gain = self.master_volume * self.se_volume;
+        self.ses.retain_mut(|n| n.mix(out, sample_rate, se_gain));
+
+        for n in out.iter_mut() {
+            *n = n.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// フェードを経過時間分だけ進めるメソッド。
+    pub(super) fn update(&mut self, duration: Duration) {
+        if let Some(n) = &mut self.bgm {
+            if !n.update(duration) {
+                self.bgm = None;
+            }
+        }
+        self.fading_bgms.retain_mut(|n| n.update(duration));
+    }
+
+    /// BGMを再生するメソッド。
+    ///
+    /// fade_inがSomeの場合、以前のBGMをその時間でフェードアウトさせつつ、新しいBGMをフェードインさせる。
+    /// Noneの場合、以前のBGMは即座に停止される。
+    pub(super) fn play_bgm(
+        &mut self,
+        id: &'static str,
+        sound: Arc<wav::Sound>,
+        fade_in: Option<Duration>,
+    ) {
+        match fade_in {
+            Some(length) => {
+                self.fade_out_bgm(length);
+                let mut voice = Voice::new(id, sound, true, 0.0);
+                voice.fade_to(1.0, length, false);
+                self.bgm = Some(voice);
+            }
+            None => {
+                self.fading_bgms.clear();
+                self.bgm = Some(Voice::new(id, sound, true, 1.0));
+            }
+        }
+    }
+
+    /// 現在のBGMをlength秒かけてフェードアウトさせて停止するメソッド。
+    pub(super) fn fade_out_bgm(&mut self, length: Duration) {
+        if let Some(mut n) = self.bgm.take() {
+            n.fade_to(0.0, length, true);
+            self.fading_bgms.push(n);
+        }
+    }
+
+    pub(super) fn stop_bgm(&mut self) {
+        self.bgm = None;
+        self.fading_bgms.clear();
+    }
+
+    /// 現在のBGMのidを取得するメソッド。
+    pub(super) fn bgm_id(&self) -> Option<&'static str> {
+        self.bgm.as_ref().map(|n| n.id)
+    }
+
+    pub(super) fn play_se(&mut self, id: &'static str, sound: Arc<wav::Sound>) {
+        self.ses.push(Voice::new(id, sound, false, 1.0));
+    }
+
+    pub(super) fn stop_all_se(&mut self) {
+        self.ses.clear();
+    }
+
+    pub(super) fn playing_se_count(&self) -> usize {
+        self.ses.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 8000;
+
+    fn create_sound(
+        samples: &[f32],
+        loop_range: Option<std::ops::Range<usize>>,
+    ) -> Arc<wav::Sound> {
+        let frames: Vec<[f32; 2]> = samples.iter().map(|n| [*n, -*n]).collect();
+        Arc::new(wav::Sound::from_frames(SAMPLE_RATE, &frames, loop_range))
+    }
+
+    /// frames分を合成し、左チャンネルのサンプルを返す関数。
+    fn mix_left(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+        let mut out = vec![0.0; 2 * frames];
+        mixer.mix(&mut out);
+        assert!(out.chunks_exact(2).all(|n| n[0] == -n[1]));
+        out.iter().step_by(2).copied().collect()
+    }
+
+    #[test]
+    fn plays_se_until_end() {
+        let mut mixer = Mixer::new(SAMPLE_RATE);
+        mixer.play_se("se", create_sound(&[0.1, 0.2, 0.3], None));
+        assert_eq!(mixer.playing_se_count(), 1);
+        assert_eq!(mix_left(&mut mixer, 5), [0.1, 0.2, 0.3, 0.0, 0.0]);
+        assert_eq!(mix_left(&mut mixer, 1), [0.0]);
+        assert_eq!(mixer.playing_se_count(), 0);
+    }
+
+    #[test]
+    fn loops_bgm_after_intro() {
+        let mut mixer = Mixer::new(SAMPLE_RATE);
+        mixer.play_bgm("bgm", create_sound(&[0.1, 0.2, 0.3, 0.4], Some(2..4)), None);
+        assert_eq!(
+            mix_left(&mut mixer, 8),
+            [0.1, 0.2, 0.3, 0.4, 0.3, 0.4, 0.3, 0.4]
+        );
+        assert_eq!(mixer.bgm_id(), Some("bgm"));
+    }
+
+    #[test]
+    fn streams_across_chunks() {
+        // チャンクの境界・ループの継ぎ目をまたいでも途切れない
+        let length = CHUNK_LENGTH * 2 + 100;
+        let samples: Vec<f32> = (0..length).map(|n| n as f32 / length as f32).collect();
+        let loop_start = CHUNK_LENGTH / 2;
+        let mut mixer = Mixer::new(SAMPLE_RATE);
+        mixer.play_bgm(
+            "bgm",
+            create_sound(&samples, Some(loop_start..length)),
+            None,
+        );
+
+        let mut expected = samples.clone();
+        expected.extend_from_slice(&samples[loop_start..]);
+        let mut mixed = Vec::new();
+        for _ in 0..expected.len() / 300 + 1 {
+            mixed.extend(mix_left(&mut mixer, 300));
+        }
+        mixed.truncate(expected.len());
+        assert_eq!(mixed, expected);
+    }
+
+    #[test]
+    fn resamples_linearly() {
+        // 出力のサンプリング周波数が2倍であれば、フレームの間が補間される
+        let mut mixer = Mixer::new(SAMPLE_RATE * 2);
+        mixer.play_se("se", create_sound(&[0.0, 0.5, 1.0], None));
+        assert_eq!(
+            mix_left(&mut mixer, 7),
+            [0.0, 0.25, 0.5, 0.75, 1.0, 1.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn applies_volumes_and_clamps() {
+        let mut mixer = Mixer::new(SAMPLE_RATE);
+        mixer.master_volume = 0.5;
+        mixer.se_volume = 0.5;
+        mixer.play_se("se", create_sound(&[0.8], None));
+        assert_eq!(mix_left(&mut mixer, 1), [0.2]);
+
+        mixer.master_volume = 1.0;
+        mixer.se_volume = 1.0;
+        mixer.play_se("a", create_sound(&[0.8], None));
+        mixer.play_se("b", create_sound(&[0.8], None));
+        assert_eq!(mix_left(&mut mixer, 1), [1.0]);
+    }
+
+    #[test]
+    fn crossfades_bgm() {
+        let mut mixer = Mixer::new(SAMPLE_RATE);
+        mixer.play_bgm("a", create_sound(&[0.5; 4], None), None);
+        mixer.play_bgm(
+            "b",
+            create_sound(&[0.25; 4], None),
+            Some(Duration::from_secs(1)),
+        );
+        assert_eq!(mixer.bgm_id(), Some("b"));
+
+        // 中間では両方が半分ずつ聞こえる
+        mixer.update(Duration::from_millis(500));
+        mix_left(&mut mixer, 1);
+        assert_eq!(mix_left(&mut mixer, 1), [0.5 * 0.5 + 0.25 * 0.5]);
+
+        // フェードし終えると以前のBGMは破棄される
+        mixer.update(Duration::from_millis(500));
+        mix_left(&mut mixer, 1);
+        assert_eq!(mix_left(&mut mixer, 1), [0.25]);
+        assert!(mixer.fading_bgms.is_empty());
+
+        mixer.fade_out_bgm(Duration::ZERO);
+        assert_eq!(mixer.bgm_id(), None);
+        mixer.update(Duration::ZERO);
+        assert!(mixer.fading_bgms.is_empty());
+    }
+}
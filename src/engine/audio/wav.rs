@@ -0,0 +1,284 @@
+use super::*;
+
+use std::ops::Range;
+
+/// WAVファイルから読み込んだ音声データ。
+///
+/// サンプルはエンコードされたまま保持され、再生時にdecode()で少しずつデコードされる。
+/// そのため、長いBGMであってもロード時に全体をデコードせず、メモリもf32に展開するより少なく済む。
+pub struct Sound {
+    /// サンプリング周波数 [Hz]。
+    pub sample_rate: u32,
+    /// 長さ [フレーム]。
+    pub length: usize,
+    /// ループ区間 [フレーム]。
+    ///
+    /// ループ再生時、区間の終端に達したら区間の始端に戻る。
+    /// つまり、区間より前の部分(イントロ)は一度だけ再生される。
+    /// Noneの場合、音声全体をループする。
+    pub loop_range: Option<Range<usize>>,
+    /// フォーマットタグ(1: リニアPCM, 3: IEEE float)。
+    tag: u16,
+    /// 1サンプルのバイト数。
+    bytes: usize,
+    channels: usize,
+    /// エンコードされたサンプル(dataチャンクの中身)。
+    data: Vec<u8>,
+}
+
+impl Sound {
+    /// startフレーム目からout.len()フレーム分をデコードするメソッド。
+    ///
+    /// サンプルはステレオ化され(3チャンネル以上の場合、先頭2チャンネルのみ用いる)、[-1.0, 1.0]に正規化される。
+    /// 終端を超えた分は0で埋められる。
+    pub fn decode(&self, start: usize, out: &mut [[f32; 2]]) {
+        let bytes = self.bytes;
+        let block = bytes * self.channels;
+        let sample = |n: &[u8]| -> f32 {
+            match (self.tag, bytes) {
+                (1, 1) => (n[0] as f32 - 128.0) / 128.0,
+                (1, 2) => i16::from_le_bytes([n[0], n[1]]) as f32 / 32768.0,
+                (1, 3) => i32::from_le_bytes([0, n[0], n[1], n[2]]) as f32 / 2147483648.0,
+                (1, _) => i32::from_le_bytes([n[0], n[1], n[2], n[3]]) as f32 / 2147483648.0,
+                _ => f32::from_le_bytes([n[0], n[1], n[2], n[3]]),
+            }
+        };
+
+        let data = self.data.get(start * block..).unwrap_or_default();
+        let mut frames = data.chunks_exact(block);
+        for n in out.iter_mut() {
+            *n = match frames.next() {
+                Some(n) if self.channels >= 2 => {
+                    [sample(&n[0..bytes]), sample(&n[bytes..2 * bytes])]
+                }
+                Some(n) => [sample(&n[0..bytes]); 2],
+                None => [0.0; 2],
+            };
+        }
+    }
+}
+
+/// WAVファイルを解析する関数。
+///
+/// サンプルはデコードせずにSoundへ複製される。
+/// 対応フォーマット：
+/// - リニアPCM 8/16/24/32bit
+/// - IEEE float 32bit
+/// - モノラル/ステレオ(3チャンネル以上の場合、先頭2チャンネルのみ用いる)
+///
+/// smplチャンクが存在する場合、その最初のループをループ区間とする。
+pub fn parse(buf: &[u8]) -> Result<Sound, EError> {
+    if buf.len() < 12 || &buf[0..4] != b"RIFF" || &buf[8..12] != b"WAVE" {
+        return Err("not a wave file.".into());
+    }
+
+    // チャンクを走査
+    let mut format = None;
+    let mut data = None;
+    let mut sampler = None;
+    let mut offset = 12;
+    while offset + 8 <= buf.len() {
+        let id = &buf[offset..offset + 4];
+        let size = u32::from_le_bytes(buf[offset + 4..offset + 8].try_into()?) as usize;
+        let body = buf
+            .get(offset + 8..offset + 8 + size)
+            .ok_or("wave chunk is out of range.")?;
+        match id {
+            b"fmt " => format = Some(body),
+            b"data" => data = Some(body),
+            b"smpl" => sampler = Some(body),
+            _ => (),
+        }
+        // NOTE: チャンクは2byte境界に揃えられている。
+        offset += 8 + size + size % 2;
+    }
+    let format = format.ok_or("wave file has no fmt chunk.")?;
+    let data = data.ok_or("wave file has no data chunk.")?;
+    if format.len() < 16 {
+        return Err("wave fmt chunk is too short.".into());
+    }
+
+    // フォーマットを取得
+    let mut tag = u16::from_le_bytes([format[0], format[1]]);
+    let channels = u16::from_le_bytes([format[2], format[3]]) as usize;
+    let sample_rate = u32::from_le_bytes(format[4..8].try_into()?);
+    let bits = u16::from_le_bytes([format[14], format[15]]);
+    if tag == 0xfffe {
+        // WAVE_FORMAT_EXTENSIBLE: サブフォーマットGUIDの先頭2byteが実際のタグ
+        if format.len() < 26 {
+            return Err("wave fmt chunk is too short.".into());
+        }
+        tag = u16::from_le_bytes([format[24], format[25]]);
+    }
+    if channels == 0 || sample_rate == 0 {
+        return Err("wave file has no channels or sample rate.".into());
+    }
+    if !matches!((tag, bits), (1, 8 | 16 | 24 | 32) | (3, 32)) {
+        return Err(format!("unsupported wave format (tag {tag}, {bits} bits).").into());
+    }
+    let bytes = bits as usize / 8;
+    let length = data.len() / (bytes * channels);
+
+    // ループ区間を取得
+    // NOTE: smplチャンクは36byteのヘッダの後に24byteのループ情報が続き、
+    //       ループ情報の8byte目から始端・終端(終端を含む)が並ぶ。
+    let loop_range = sampler
+        .filter(|n| n.len() >= 36 + 24 && u32::from_le_bytes([n[28], n[29], n[30], n[31]]) > 0)
+        .map(|n| {
+            let start = u32::from_le_bytes([n[44], n[45], n[46], n[47]]) as usize;
+            let end = u32::from_le_bytes([n[48], n[49], n[50], n[51]]) as usize + 1;
+            start..end.min(length)
+        })
+        .filter(|n| !n.is_empty());
+
+    Ok(Sound {
+        sample_rate,
+        length,
+        loop_range,
+        tag,
+        bytes,
+        channels,
+        data: data[..length * bytes * channels].to_vec(),
+    })
+}
+
+#[cfg(test)]
+impl Sound {
+    /// (左, 右)のサンプルの配列からIEEE float 32bitステレオの音声データを作成するメソッド。
+    pub fn from_frames(
+        sample_rate: u32,
+        frames: &[[f32; 2]],
+        loop_range: Option<Range<usize>>,
+    ) -> Self {
+        Self {
+            sample_rate,
+            length: frames.len(),
+            loop_range,
+            tag: 3,
+            bytes: 4,
+            channels: 2,
+            data: frames
+                .iter()
+                .flatten()
+                .flat_map(|n| n.to_le_bytes())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// WAVファイルを組み立てる関数。
+    fn create_wav(
+        tag: u16,
+        channels: u16,
+        bits: u16,
+        data: &[u8],
+        smpl: Option<(u32, u32)>,
+    ) -> Vec<u8> {
+        let mut chunks = Vec::new();
+        chunks.extend(b"fmt ");
+        chunks.extend(16u32.to_le_bytes());
+        chunks.extend(tag.to_le_bytes());
+        chunks.extend(channels.to_le_bytes());
+        chunks.extend(8000u32.to_le_bytes());
+        chunks.extend((8000 * (channels * bits / 8) as u32).to_le_bytes());
+        chunks.extend((channels * bits / 8).to_le_bytes());
+        chunks.extend(bits.to_le_bytes());
+        chunks.extend(b"data");
+        chunks.extend((data.len() as u32).to_le_bytes());
+        chunks.extend(data);
+        if data.len() % 2 == 1 {
+            chunks.push(0);
+        }
+        if let Some((start, end)) = smpl {
+            chunks.extend(b"smpl");
+            chunks.extend(60u32.to_le_bytes());
+            let mut body = [0; 60];
+            body[28..32].copy_from_slice(&1u32.to_le_bytes());
+            body[44..48].copy_from_slice(&start.to_le_bytes());
+            body[48..52].copy_from_slice(&end.to_le_bytes());
+            chunks.extend(body);
+        }
+        let mut buf = Vec::new();
+        buf.extend(b"RIFF");
+        buf.extend((4 + chunks.len() as u32).to_le_bytes());
+        buf.extend(b"WAVE");
+        buf.extend(chunks);
+        buf
+    }
+
+    fn decode_all(sound: &Sound) -> Vec<[f32; 2]> {
+        let mut frames = vec![[0.0; 2]; sound.length];
+        sound.decode(0, &mut frames);
+        frames
+    }
+
+    #[test]
+    fn decodes_16bit_mono() {
+        let data: Vec<u8> = [0i16, 16384, -32768]
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .collect();
+        let sound = parse(&create_wav(1, 1, 16, &data, None)).unwrap();
+        assert_eq!(sound.sample_rate, 8000);
+        assert_eq!(sound.length, 3);
+        assert_eq!(sound.loop_range, None);
+        assert_eq!(decode_all(&sound), [[0.0, 0.0], [0.5, 0.5], [-1.0, -1.0]]);
+    }
+
+    #[test]
+    fn decodes_8bit_and_24bit_stereo() {
+        let sound = parse(&create_wav(1, 2, 8, &[128, 192, 0, 128], None)).unwrap();
+        assert_eq!(decode_all(&sound), [[0.0, 0.5], [-1.0, 0.0]]);
+
+        let data = [0x00, 0x00, 0x40, 0x00, 0x00, 0xc0];
+        let sound = parse(&create_wav(1, 2, 24, &data, None)).unwrap();
+        assert_eq!(decode_all(&sound), [[0.5, -0.5]]);
+    }
+
+    #[test]
+    fn decodes_float_and_drops_extra_channels() {
+        let data: Vec<u8> = [0.25f32, -0.75, 1.0]
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .collect();
+        let sound = parse(&create_wav(3, 3, 32, &data, None)).unwrap();
+        assert_eq!(decode_all(&sound), [[0.25, -0.75]]);
+    }
+
+    #[test]
+    fn decodes_from_any_position() {
+        let sound = parse(&create_wav(1, 1, 8, &[0, 64, 128, 192], None)).unwrap();
+        let mut frames = [[9.0; 2]; 3];
+        sound.decode(2, &mut frames);
+        assert_eq!(frames, [[0.0, 0.0], [0.5, 0.5], [0.0, 0.0]]);
+        sound.decode(10, &mut frames);
+        assert_eq!(frames, [[0.0; 2]; 3]);
+    }
+
+    #[test]
+    fn reads_loop_range() {
+        let sound = parse(&create_wav(1, 1, 8, &[128; 10], Some((2, 5)))).unwrap();
+        assert_eq!(sound.loop_range, Some(2..6));
+
+        // 終端は音声の長さで切り詰められる
+        let sound = parse(&create_wav(1, 1, 8, &[128; 10], Some((2, 100)))).unwrap();
+        assert_eq!(sound.loop_range, Some(2..10));
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(parse(b"RIFF\0\0\0\0AVI ").is_err());
+        assert!(parse(&create_wav(2, 1, 4, &[0; 4], None)).is_err());
+        assert!(parse(&create_wav(1, 1, 12, &[0; 4], None)).is_err());
+        assert!(parse(&create_wav(1, 0, 16, &[0; 4], None)).is_err());
+
+        // dataチャンクが範囲外
+        let mut buf = create_wav(1, 1, 16, &[0; 4], None);
+        buf.truncate(buf.len() - 2);
+        assert!(parse(&buf).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 use super::*;
 
+pub mod backend;
+pub mod capture;
 pub mod character;
+pub mod display;
 mod image;
 mod model;
 pub mod pipeline;
@@ -17,6 +20,11 @@ use uuid::Uuid;
 use wgpu::*;
 use winit::window::Window;
 
+/// 組み込みの1x1の白い画像のID。
+///
+/// 色を指定して単色の矩形を描画するために用いる。
+pub const WHITE_IMAGE_ID: &str = "white";
+
 /// Baseレンダーパイプラインのインスタンスデータ及びそのメタ情報の集合体。
 ///
 /// メタ情報は半透明オブジェクトを正確に描画するため・効率良くインスタンシングを行うためのソートに用いる。
@@ -29,61 +37,138 @@ pub struct InstanceMeta {
 }
 
 /// 1回のインスタンシングに必要なデータの集合体。
+#[derive(Clone)]
 pub struct RenderCommand {
     pub image_id: &'static str,
     pub instances_range: Range<u32>,
 }
 
+/// 描画先。
+enum RenderTarget<'a> {
+    /// ウィンドウのサーフェス。
+    Surface(Arc<Window>, Surface<'a>, SurfaceConfiguration),
+    /// オフスクリーンのテクスチャ。
+    Texture(Texture),
+}
+
+/// シーンを描画先に拡大縮小する方法。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalingMode {
+    /// 縦横比を保ったまま最大限に拡大縮小し、余白を黒で埋める(レターボックス)。
+    Fit,
+    /// 縦横比を保ったまま整数倍に拡大し、余白を黒で埋める。
+    ///
+    /// ドット絵がにじまない。描画先がシーンより小さい場合、Fitと同じになる。
+    Integer,
+}
+
+/// シーンが描画される描画先上の領域 [px]。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    /// シーンを描画先に収める領域を求めるメソッド。
+    ///
+    /// 領域は描画先の中央に置かれ、位置は整数に丸められる。
+    pub fn fit(
+        target_width: u32,
+        target_height: u32,
+        scene_width: u32,
+        scene_height: u32,
+        mode: ScalingMode,
+    ) -> Self {
+        let (tw, th) = (target_width as f32, target_height as f32);
+        let (sw, sh) = (scene_width.max(1) as f32, scene_height.max(1) as f32);
+        let scale = (tw / sw).min(th / sh);
+        let scale = match mode {
+            ScalingMode::Integer if scale >= 1.0 => scale.floor(),
+            _ => scale,
+        };
+        let (width, height) = ((sw * scale).min(tw), (sh * scale).min(th));
+        Self {
+            x: ((tw - width) / 2.0).floor(),
+            y: ((th - height) / 2.0).floor(),
+            width,
+            height,
+        }
+    }
+}
+
+/// 描画の統計情報。
+///
+/// 描画・アップロードの回数はGraphicManager.take_render_stats()を呼んでから次に呼ぶまでの合計である。
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderStats {
+    /// ドローコールの数。
+    pub draw_calls: u32,
+    /// インスタンスバッファを更新した回数。連続するインスタンスの更新は1回にまとめられる。
+    pub upload_blocks: u32,
+    /// インスタンスバッファにアップロードしたインスタンスの数。
+    pub instance_uploads: u32,
+    /// テクスチャアトラス上の文字画像の数。
+    pub glyph_count: u32,
+    /// 文字画像のテクスチャアトラスのページの数。
+    pub glyph_atlas_pages: u32,
+    /// 文字画像のテクスチャアトラスの作成済みのページの使用率 [0, 1]。
+    pub glyph_atlas_usage: f32,
+    /// ロードしたテクスチャの合計のサイズ [byte]。
+    pub texture_memory: u64,
+}
+
 /// WebGPUベースのレンダラ。
 ///
-/// - リサイズ不可
-/// - FIFO
+/// - リサイズ可(シーンの解像度は固定であり、ScalingModeに従って描画先に収める)
+/// - 垂直同期の有無・フルスクリーン等は表示設定(display::DisplaySettings)で実行時に変更可
 pub struct GraphicManager<'a> {
-    surface: Surface<'a>,
+    target: RenderTarget<'a>,
+    adapter_info: AdapterInfo,
     device: Device,
     queue: Queue,
+    format: TextureFormat,
+    /// シーンの幅。
+    width: u32,
+    /// シーンの高さ。
+    height: u32,
+    display_settings: display::DisplaySettings,
+    /// シーンが描画される描画先上の領域。
+    viewport: Viewport,
     base_pipeline: pipeline::BasePipeline,
     square_model: model::Model,
     image_texture_views: HashMap<&'static str, TextureView>,
     char_images_texture_atlas: character::CharacterImagesTextureAtlas,
+    /// 画像のテクスチャの合計のサイズ [byte]。文字画像のテクスチャアトラスは含まない。
+    texture_memory: u64,
+    render_stats: RenderStats,
+    /// 次のrender_with_metas()で一緒に描画するインスタンス。
+    overlay_metas: Vec<InstanceMeta>,
     uuids: Vec<Uuid>,
+    last_commands: Vec<RenderCommand>,
 }
 
 impl<'a> GraphicManager<'a> {
-    pub fn new(window: Arc<Window>, width: u32, height: u32) -> Result<Self, EError> {
-        let backends = if cfg!(target_os = "windows") {
-            Backends::DX12
-        } else if cfg!(target_os = "macos") {
-            Backends::METAL
-        } else if cfg!(target_os = "linux") {
-            Backends::VULKAN
-        } else {
-            Backends::all()
-        };
-        let instance = Instance::new(InstanceDescriptor {
-            backends,
-            ..Default::default()
-        });
-
-        let surface = instance.create_surface(Arc::clone(&window))?;
-
-        let request = instance.request_adapter(&RequestAdapterOptions {
-            power_preference: PowerPreference::default(),
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        });
-        let adapter = executor::block_on(request).ok_or("failed to get an adapter.".to_owned())?;
-
-        let request = adapter.request_device(
-            &DeviceDescriptor {
-                label: None,
-                required_features: Features::empty(),
-                required_limits: Limits::default(),
-                memory_hints: MemoryHints::MemoryUsage,
-            },
-            None,
-        );
-        let (device, queue) = executor::block_on(request)?;
+    /// コンストラクタ。
+    ///
+    /// width・heightはシーンのサイズであり、サーフェスはウィンドウの実際のサイズ [px] で作成される。
+    /// アダプタはbackendsの順に探される(backend::GraphicBackend::DEFAULT_ORDER等)。
+    pub fn new(
+        window: Arc<Window>,
+        width: u32,
+        height: u32,
+        backends: &[backend::GraphicBackend],
+    ) -> Result<Self, EError> {
+        let backend::SelectedAdapter {
+            adapter,
+            device,
+            queue,
+            surface,
+        } = backend::select_adapter(backends, Some(&window))?;
+        let surface = surface.ok_or("failed to create a surface.".to_owned())?;
+        let adapter_info = adapter.get_info();
 
         let surface_capabilities = surface.get_capabilities(&adapter);
         let surface_format = surface_capabilities
@@ -93,45 +178,297 @@ impl<'a> GraphicManager<'a> {
             .next()
             .map(|n| n.clone())
             .unwrap_or(surface_capabilities.formats[0]);
-        surface.configure(
-            &device,
-            &SurfaceConfiguration {
-                usage: TextureUsages::RENDER_ATTACHMENT,
-                format: surface_format,
+        let inner_size = window.inner_size();
+        let surface_configuration = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: inner_size.width.max(1),
+            height: inner_size.height.max(1),
+            present_mode: get_present_mode(true),
+            view_formats: Vec::new(),
+            alpha_mode: surface_capabilities.alpha_modes[0],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(&device, &surface_configuration);
+
+        Ok(Self::with_target(
+            RenderTarget::Surface(window, surface, surface_configuration),
+            adapter_info,
+            device,
+            queue,
+            surface_format,
+            width,
+            height,
+        ))
+    }
+
+    /// ウィンドウを持たず、オフスクリーンのテクスチャに描画するレンダラを作成するメソッド。
+    ///
+    /// 描画先のテクスチャのサイズはシーンのサイズと等しい。
+    /// アダプタはbackendsの順に探される(ソフトウェアアダプタを含めればディスプレイの無い環境でも動作する)。
+    /// サイズが0である・デバイスの上限を超える場合、エラーを返す。
+    pub fn new_headless(
+        width: u32,
+        height: u32,
+        backends: &[backend::GraphicBackend],
+    ) -> Result<Self, EError> {
+        if width == 0 || height == 0 {
+            return Err(format!("invalid scene size {width}x{height}.").into());
+        }
+        let backend::SelectedAdapter {
+            adapter,
+            device,
+            queue,
+            ..
+        } = backend::select_adapter(backends, None)?;
+        let adapter_info = adapter.get_info();
+
+        let max_size = device.limits().max_texture_dimension_2d;
+        if width > max_size || height > max_size {
+            return Err(format!(
+                "scene size {width}x{height} exceeds the device limit {max_size}."
+            )
+            .into());
+        }
+
+        let format = TextureFormat::Rgba8UnormSrgb;
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
                 width,
                 height,
-                present_mode: PresentMode::AutoVsync,
-                view_formats: Vec::new(),
-                alpha_mode: surface_capabilities.alpha_modes[0],
-                desired_maximum_frame_latency: 2,
+                depth_or_array_layers: 1,
             },
-        );
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
 
-        let mut base_pipeline =
-            pipeline::BasePipeline::new(&device, surface_format.into(), width, height);
+        Ok(Self::with_target(
+            RenderTarget::Texture(texture),
+            adapter_info,
+            device,
+            queue,
+            format,
+            width,
+            height,
+        ))
+    }
+
+    /// 描画先以外のオブジェクトを作成してレンダラを組み立てるメソッド。
+    fn with_target(
+        target: RenderTarget<'a>,
+        adapter_info: AdapterInfo,
+        device: Device,
+        queue: Queue,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut base_pipeline = pipeline::BasePipeline::new(&device, format.into(), width, height);
 
         let square_model = model::create_square_model(&device);
 
         let mut image_texture_views = HashMap::new();
 
-        let char_images_texture_atlas =
-            character::CharacterImagesTextureAtlas::new(&device, &queue);
-        let char_images_texture_atlas_view = char_images_texture_atlas
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-        base_pipeline.load_bind_group_for_image(&device, "chars", &char_images_texture_atlas_view);
-        image_texture_views.insert("chars", char_images_texture_atlas_view);
+        let char_images_texture_atlas = character::CharacterImagesTextureAtlas::new(&device);
 
-        Ok(Self {
-            surface,
+        let white_image_texture_view =
+            image::create_image_texture_view(&device, &queue, 1, 1, &[255, 255, 255, 255]);
+        base_pipeline.load_bind_group_for_image(&device, WHITE_IMAGE_ID, &white_image_texture_view);
+        image_texture_views.insert(WHITE_IMAGE_ID, white_image_texture_view);
+
+        let display_settings = display::DisplaySettings::default();
+        let (target_width, target_height) = match &target {
+            RenderTarget::Surface(_, _, n) => (n.width, n.height),
+            RenderTarget::Texture(n) => (n.width(), n.height()),
+        };
+        let viewport = Viewport::fit(
+            target_width,
+            target_height,
+            width,
+            height,
+            display_settings.scaling_mode,
+        );
+
+        // NOTE: 画像はすべてRGBA8である。
+        let texture_memory = 4;
+
+        Self {
+            target,
+            adapter_info,
             device,
             queue,
+            format,
+            width,
+            height,
+            display_settings,
+            viewport,
             base_pipeline,
             square_model,
             image_texture_views,
             char_images_texture_atlas,
+            texture_memory,
+            render_stats: RenderStats::default(),
+            overlay_metas: Vec::new(),
             uuids: Vec::new(),
-        })
+            last_commands: Vec::new(),
+        }
+    }
+
+    /// 描画先のサイズが変わったときに呼ぶメソッド。
+    ///
+    /// width・heightはウィンドウの実際のサイズ [px] である(HiDPI環境では論理サイズより大きい)。
+    /// サーフェスを作り直し、シーンが描画される領域を求め直す。
+    /// 最小化等でサイズが0の場合、サーフェスは作り直さない。
+    /// オフスクリーンのテクスチャに描画している場合、何もしない。
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let RenderTarget::Surface(_, surface, configuration) = &mut self.target else {
+            return;
+        };
+        if width == 0 || height == 0 {
+            return;
+        }
+        configuration.width = width;
+        configuration.height = height;
+        surface.configure(&self.device, configuration);
+        self.update_viewport();
+    }
+
+    /// 選ばれたアダプタの情報を取得するメソッド。
+    ///
+    /// 不具合の報告等で、どのバックエンド・アダプタで描画しているかを確認するために用いる。
+    pub fn get_adapter_info(&self) -> &AdapterInfo {
+        &self.adapter_info
+    }
+
+    pub fn get_scaling_mode(&self) -> ScalingMode {
+        self.display_settings.scaling_mode
+    }
+    pub fn set_scaling_mode(&mut self, scaling_mode: ScalingMode) {
+        self.display_settings.scaling_mode = scaling_mode;
+        self.update_viewport();
+    }
+
+    /// シーンが描画される描画先上の領域 [px] を取得するメソッド。
+    ///
+    /// InputManager.set_viewport()に渡し、カーソルの位置をシーン上の座標に変換するために用いる。
+    pub fn get_viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// フルスクリーンか否かを取得するメソッド。
+    pub fn is_fullscreen(&self) -> bool {
+        match &self.target {
+            RenderTarget::Surface(window, _, _) => window.fullscreen().is_some(),
+            RenderTarget::Texture(_) => false,
+        }
+    }
+
+    /// フルスクリーンとウィンドウを切り替えるメソッド。
+    ///
+    /// フルスクリーンの方式・モニタは表示設定に従う。
+    /// 切り替えた結果のサイズはウィンドウのリサイズとして通知される。
+    pub fn set_fullscreen(&mut self, is_fullscreen: bool) {
+        self.display_settings.is_fullscreen = is_fullscreen;
+        self.apply_fullscreen();
+    }
+
+    pub fn get_display_settings(&self) -> &display::DisplaySettings {
+        &self.display_settings
+    }
+
+    /// 表示設定を適用するメソッド。
+    ///
+    /// 垂直同期が変わった場合、サーフェスを作り直す。
+    /// フレームレートの上限はエンジンのメインループが参照する。
+    /// オフスクリーンのテクスチャに描画している場合、拡大の方式のみ適用される。
+    pub fn set_display_settings(&mut self, display_settings: display::DisplaySettings) {
+        let last = std::mem::replace(&mut self.display_settings, display_settings);
+        if last.vsync != self.display_settings.vsync {
+            if let RenderTarget::Surface(_, surface, configuration) = &mut self.target {
+                configuration.present_mode = get_present_mode(self.display_settings.vsync);
+                surface.configure(&self.device, configuration);
+            }
+        }
+        if last.is_fullscreen != self.display_settings.is_fullscreen
+            || self.display_settings.is_fullscreen
+                && (last.fullscreen_mode != self.display_settings.fullscreen_mode
+                    || last.monitor != self.display_settings.monitor)
+        {
+            self.apply_fullscreen();
+        }
+        self.update_viewport();
+    }
+
+    /// 接続されているモニタの名前の一覧を取得するメソッド。
+    ///
+    /// 表示設定のmonitorに指定するために用いる。
+    /// 名前を取得できないモニタは含まれない。
+    pub fn get_monitor_names(&self) -> Vec<String> {
+        match &self.target {
+            RenderTarget::Surface(window, _, _) => window
+                .available_monitors()
+                .filter_map(|n| n.name())
+                .collect(),
+            RenderTarget::Texture(_) => Vec::new(),
+        }
+    }
+
+    /// 表示設定に従ってウィンドウのフルスクリーンを設定するメソッド。
+    fn apply_fullscreen(&self) {
+        let RenderTarget::Surface(window, _, _) = &self.target else {
+            return;
+        };
+        let settings = &self.display_settings;
+        if !settings.is_fullscreen {
+            window.set_fullscreen(None);
+            return;
+        }
+
+        let monitor = settings
+            .monitor
+            .as_ref()
+            .and_then(|name| {
+                window
+                    .available_monitors()
+                    .find(|n| n.name().as_ref() == Some(name))
+            })
+            .or_else(|| window.current_monitor());
+        let fullscreen = match settings.fullscreen_mode {
+            display::FullscreenMode::Borderless => winit::window::Fullscreen::Borderless(monitor),
+            display::FullscreenMode::Exclusive => {
+                // NOTE: 最も大きく、リフレッシュレートが最も高いビデオモードを用いる。
+                let video_mode = monitor.as_ref().and_then(|n| {
+                    n.video_modes().max_by_key(|n| {
+                        let size = n.size();
+                        (size.width * size.height, n.refresh_rate_millihertz())
+                    })
+                });
+                match video_mode {
+                    Some(n) => winit::window::Fullscreen::Exclusive(n),
+                    None => winit::window::Fullscreen::Borderless(monitor),
+                }
+            }
+        };
+        window.set_fullscreen(Some(fullscreen));
+    }
+
+    fn update_viewport(&mut self) {
+        let (target_width, target_height) = match &self.target {
+            RenderTarget::Surface(_, _, n) => (n.width, n.height),
+            RenderTarget::Texture(n) => (n.width(), n.height()),
+        };
+        self.viewport = Viewport::fit(
+            target_width,
+            target_height,
+            self.width,
+            self.height,
+            self.display_settings.scaling_mode,
+        );
     }
 
     /// 画像リソースをロードするメソッド。
@@ -156,45 +493,57 @@ impl<'a> GraphicManager<'a> {
         self.base_pipeline
             .load_bind_group_for_image(&self.device, id, &image_texture_view);
         self.image_texture_views.insert(id, image_texture_view);
+        self.texture_memory += 4 * width as u64 * height as u64;
         Ok(())
     }
 
     /// 文字画像をロードするメソッド。
     ///
-    /// 既に文字画像がロードされている場合、無視される。
+    /// 既に文字画像がロードされている場合、このフレームで使われたとみなすのみである。
+    /// 一度追い出された文字画像を再びロードした場合、trueを返す。
     pub fn load_character_image(
         &mut self,
         rs_mngr: &mut ResourceManager,
-        font_name: &'static str,
-        character: char,
+        key: character::CharacterKey,
     ) -> Result<bool, EError> {
-        self.char_images_texture_atlas
-            .load(rs_mngr, &self.queue, font_name, character)
+        let result = self
+            .char_images_texture_atlas
+            .load(rs_mngr, &self.device, &self.queue, key);
+
+        // 追加されたページを画像として登録
+        for (id, texture) in self.char_images_texture_atlas.get_pages() {
+            if !self.image_texture_views.contains_key(id) {
+                let view = texture.create_view(&TextureViewDescriptor::default());
+                self.base_pipeline
+                    .load_bind_group_for_image(&self.device, id, &view);
+                self.image_texture_views.insert(id, view);
+            }
+        }
+
+        result
     }
 
     /// すべての文字画像をロードするメソッド。
     ///
+    /// このフレームで描画する文字画像はまとめてこのメソッドでロードすること。
+    /// ロードされた文字画像はこのフレームの間は追い出されない。
+    /// このフレームで一度追い出された文字画像を再びロードしていた場合、trueを返す。
+    /// このとき、文字画像のUV座標が変わっているので、文字列のインスタンスを更新すること。
+    ///
+    /// NOTE: オーバーレイとシーンのように1フレームで複数回呼ばれる場合、
+    /// 先の呼び出しで再ロードされた文字を後の呼び出しでも更新できるよう、
+    /// この呼び出しで再ロードしたか否かではなくフレーム全体で判定する。
+    ///
     /// WARN: ロードに失敗した文字は無視される。
     pub fn load_all_character_images(
         &mut self,
         rs_mngr: &mut ResourceManager,
-        characters: HashSet<(&'static str, char)>,
+        characters: HashSet<character::CharacterKey>,
     ) -> bool {
-        let mut cleared = false;
-        'outer: loop {
-            for c in &characters {
-                match self.load_character_image(rs_mngr, c.0, c.1) {
-                    Ok(true) => {
-                        cleared = true;
-                        continue 'outer;
-                    }
-                    Ok(false) => (),
-                    Err(_) => continue,
-                }
-            }
-            break;
+        for key in characters {
+            let _ = self.load_character_image(rs_mngr, key);
         }
-        cleared
+        self.char_images_texture_atlas.is_reloaded()
     }
 
     /// 文字画像の情報を取得するメソッド。
@@ -202,8 +551,10 @@ impl<'a> GraphicManager<'a> {
         &self,
         font_name: &'static str,
         character: char,
+        height: f32,
     ) -> Option<&character::CharacterImage> {
-        self.char_images_texture_atlas.get(font_name, character)
+        self.char_images_texture_atlas
+            .get(&character::get_character_key(font_name, character, height))
     }
 
     /// Baseレンダーパイプラインのカメラバッファを更新するメソッド。
@@ -213,32 +564,117 @@ impl<'a> GraphicManager<'a> {
 
     /// Baseレンダーパイプラインのインスタンスバッファを更新するメソッド。
     ///
-    /// WARN: インスタンスバッファを超過した分は無視される。
-    pub fn update_instances(&self, offset: u32, instances: &[pipeline::BaseInstance]) {
+    /// インスタンスバッファの容量が足りない場合、拡張される。
+    pub fn update_instances(&mut self, offset: u32, instances: &[pipeline::BaseInstance]) {
+        self.base_pipeline.reserve_instances(
+            &self.device,
+            &self.queue,
+            offset + instances.len() as u32,
+        );
         self.base_pipeline
             .update_instances(&self.queue, offset, instances);
+        self.render_stats.upload_blocks += 1;
+        self.render_stats.instance_uploads += instances.len() as u32;
     }
 
     /// 描画を行うメソッド。
     ///
     /// 垂直同期を取るため、スレッドが待機される。
-    pub fn render(&self, commands: &[RenderCommand]) {
-        let Ok(surface_texture) = self.surface.get_current_texture() else {
-            // 描画先テクスチャの取得に失敗。
-            // 警告レベルなので早期returnで済ます。
-            return;
+    pub fn render(&mut self, commands: &[RenderCommand]) {
+        let (surface_texture, render_target_view) = match &self.target {
+            RenderTarget::Surface(_, surface, _) => {
+                let Ok(surface_texture) = surface.get_current_texture() else {
+                    // 描画先テクスチャの取得に失敗。
+                    // 警告レベルなので早期returnで済ます。
+                    return;
+                };
+                let render_target_view = surface_texture
+                    .texture
+                    .create_view(&TextureViewDescriptor::default());
+                (Some(surface_texture), render_target_view)
+            }
+            RenderTarget::Texture(texture) => {
+                (None, texture.create_view(&TextureViewDescriptor::default()))
+            }
         };
-        let render_target_view = surface_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
         let mut command_encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor { label: None });
 
+        self.encode_render_pass(
+            &mut command_encoder,
+            &render_target_view,
+            commands,
+            self.viewport,
+        );
+
+        self.queue.submit(Some(command_encoder.finish()));
+        if let Some(surface_texture) = surface_texture {
+            surface_texture.present();
+        }
+        self.render_stats.draw_calls += commands.len() as u32;
+        self.char_images_texture_atlas.next_frame();
+
+        self.last_commands = commands.to_vec();
+    }
+
+    /// 直前に描画したフレームをオフスクリーンのテクスチャに描画し直して読み出すメソッド。
+    ///
+    /// スクリーンショット・セーブスロットのサムネイル・ゴールデンイメージによるテストに用いる。
+    /// 読み出しが完了するまでスレッドが待機される。
+    ///
+    /// WARN: 直前の描画以降にカメラバッファ・インスタンスバッファを更新した場合、更新後の内容で描画される。
+    pub fn capture(&self) -> Result<capture::CapturedImage, EError> {
+        let texture = self.device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let render_target_view = texture.create_view(&TextureViewDescriptor::default());
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+
+        // NOTE: 余白を含めず、シーンのみをシーンのサイズで描画する。
+        self.encode_render_pass(
+            &mut command_encoder,
+            &render_target_view,
+            &self.last_commands,
+            Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: self.width as f32,
+                height: self.height as f32,
+            },
+        );
+
+        self.queue.submit(Some(command_encoder.finish()));
+        capture::read_texture(&self.device, &self.queue, &texture)
+    }
+
+    /// render_target_viewのviewportの領域へ描画するレンダーパスを記録するメソッド。
+    ///
+    /// viewportの外は黒で塗り潰される。
+    fn encode_render_pass(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        render_target_view: &TextureView,
+        commands: &[RenderCommand],
+        viewport: Viewport,
+    ) {
         let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(RenderPassColorAttachment {
-                view: &render_target_view,
+                view: render_target_view,
                 resolve_target: None,
                 ops: Operations {
                     load: LoadOp::Clear(Color {
@@ -255,6 +691,14 @@ impl<'a> GraphicManager<'a> {
             occlusion_query_set: None,
         });
 
+        render_pass.set_viewport(
+            viewport.x,
+            viewport.y,
+            viewport.width,
+            viewport.height,
+            0.0,
+            1.0,
+        );
         self.base_pipeline.start(&mut render_pass);
         self.base_pipeline
             .set_model(&mut render_pass, &self.square_model);
@@ -267,16 +711,41 @@ impl<'a> GraphicManager<'a> {
                 n.instances_range.clone(),
             );
         }
+    }
 
-        render_pass.forget_lifetime();
-        self.queue.submit(Some(command_encoder.finish()));
-        surface_texture.present();
+    /// 次のrender_with_metas()で一緒に描画するインスタンスを追加するメソッド。
+    ///
+    /// デバッグ用のオーバーレイ等、シーンとは独立して描画したいものに用いる。
+    pub fn push_overlay(&mut self, metas: Vec<InstanceMeta>) {
+        self.overlay_metas.extend(metas);
+    }
+
+    /// 描画の統計情報を取得し、描画・アップロードの回数を0に戻すメソッド。
+    ///
+    /// エンジンが毎フレームの終わりに呼び、FrameStatsに記録する。
+    pub fn take_render_stats(&mut self) -> RenderStats {
+        let (glyph_count, glyph_atlas_pages, glyph_atlas_usage) =
+            self.char_images_texture_atlas.get_usage();
+        let glyph_atlas_memory = self
+            .char_images_texture_atlas
+            .get_pages()
+            .map(|(_, n)| 4 * n.width() as u64 * n.height() as u64)
+            .sum::<u64>();
+        RenderStats {
+            glyph_count,
+            glyph_atlas_pages,
+            glyph_atlas_usage,
+            texture_memory: self.texture_memory + glyph_atlas_memory,
+            ..std::mem::take(&mut self.render_stats)
+        }
     }
 
     /// メタ情報を元に自動的に効率良く描画を行うメソッド。
     ///
     /// 垂直同期を取るため、スレッドが待機される。
     pub fn render_with_metas(&mut self, mut metas: Vec<InstanceMeta>) {
+        metas.append(&mut self.overlay_metas);
+
         // 空であれば早期リターン
         if metas.is_empty() {
             // TODO: 1フレーム待機
@@ -339,12 +808,6 @@ impl<'a> GraphicManager<'a> {
         });
         self.uuids = uuids;
 
-        #[cfg(debug_assertions)]
-        {
-            println!("update: {} blocks", instancess.len());
-            println!("draw call: {}", commands.len());
-        }
-
         // インスタンスバッファを更新
         for (o, n) in instancess {
             self.update_instances(o, &n);
@@ -354,3 +817,79 @@ impl<'a> GraphicManager<'a> {
         self.render(&commands);
     }
 }
+
+/// 垂直同期を取るか否かからプレゼントモードを決める関数。
+///
+/// Auto系のモードはすべての環境でサポートされる。
+fn get_present_mode(vsync: bool) -> PresentMode {
+    if vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn fits_with_letterbox_and_pillarbox() {
+        // 横長の描画先では左右に、縦長の描画先では上下に余白ができる
+        assert_eq!(
+            Viewport::fit(1920, 1200, 1280, 720, ScalingMode::Fit),
+            viewport(0.0, 60.0, 1920.0, 1080.0)
+        );
+        assert_eq!(
+            Viewport::fit(2560, 1080, 1280, 720, ScalingMode::Fit),
+            viewport(320.0, 0.0, 1920.0, 1080.0)
+        );
+        assert_eq!(
+            Viewport::fit(640, 360, 1280, 720, ScalingMode::Fit),
+            viewport(0.0, 0.0, 640.0, 360.0)
+        );
+    }
+
+    #[test]
+    fn fits_with_integer_scale() {
+        // 整数倍に切り捨てられ、中央に置かれる
+        assert_eq!(
+            Viewport::fit(1920, 1080, 640, 360, ScalingMode::Integer),
+            viewport(0.0, 0.0, 1920.0, 1080.0)
+        );
+        assert_eq!(
+            Viewport::fit(1600, 900, 640, 360, ScalingMode::Integer),
+            viewport(160.0, 90.0, 1280.0, 720.0)
+        );
+        // 1倍未満は縮小する
+        assert_eq!(
+            Viewport::fit(640, 360, 1280, 720, ScalingMode::Integer),
+            viewport(0.0, 0.0, 640.0, 360.0)
+        );
+    }
+
+    #[test]
+    fn rounds_position_and_handles_empty_sizes() {
+        assert_eq!(
+            Viewport::fit(1281, 721, 1280, 720, ScalingMode::Integer),
+            viewport(0.0, 0.0, 1280.0, 720.0)
+        );
+        assert_eq!(
+            Viewport::fit(0, 0, 1280, 720, ScalingMode::Fit),
+            viewport(0.0, 0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            Viewport::fit(100, 100, 0, 0, ScalingMode::Fit),
+            viewport(0.0, 0.0, 100.0, 100.0)
+        );
+    }
+}
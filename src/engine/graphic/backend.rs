@@ -0,0 +1,178 @@
+use super::*;
+
+/// アダプタを探すバックエンドの候補。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphicBackend {
+    /// OSごとの標準のバックエンド(Windows: Direct3D 12, macOS: Metal, Linux: Vulkan)。
+    Native,
+    Vulkan,
+    Dx12,
+    Metal,
+    /// OpenGL(ES)。
+    Gl,
+    /// ソフトウェアアダプタ。すべてのバックエンドからフォールバックアダプタを探す。
+    Software,
+}
+
+impl GraphicBackend {
+    /// 既定の候補の順序。
+    pub const DEFAULT_ORDER: [GraphicBackend; 3] = [
+        GraphicBackend::Native,
+        GraphicBackend::Gl,
+        GraphicBackend::Software,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GraphicBackend::Native => "native",
+            GraphicBackend::Vulkan => "vulkan",
+            GraphicBackend::Dx12 => "dx12",
+            GraphicBackend::Metal => "metal",
+            GraphicBackend::Gl => "gl",
+            GraphicBackend::Software => "software",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "native" => Some(GraphicBackend::Native),
+            "vulkan" => Some(GraphicBackend::Vulkan),
+            "dx12" => Some(GraphicBackend::Dx12),
+            "metal" => Some(GraphicBackend::Metal),
+            "gl" => Some(GraphicBackend::Gl),
+            "software" => Some(GraphicBackend::Software),
+            _ => None,
+        }
+    }
+
+    fn get_backends(&self) -> Backends {
+        match self {
+            GraphicBackend::Native => {
+                if cfg!(target_os = "windows") {
+                    Backends::DX12
+                } else if cfg!(target_os = "macos") {
+                    Backends::METAL
+                } else if cfg!(target_os = "linux") {
+                    Backends::VULKAN
+                } else {
+                    Backends::all()
+                }
+            }
+            GraphicBackend::Vulkan => Backends::VULKAN,
+            GraphicBackend::Dx12 => Backends::DX12,
+            GraphicBackend::Metal => Backends::METAL,
+            GraphicBackend::Gl => Backends::GL,
+            GraphicBackend::Software => Backends::all(),
+        }
+    }
+}
+
+/// 選ばれたアダプタとそのデバイス等の集合。
+pub(super) struct SelectedAdapter {
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+    /// ウィンドウに描画する場合のサーフェス。
+    pub surface: Option<Surface<'static>>,
+}
+
+/// 候補を順に試し、最初に使えたアダプタを選ぶ関数。
+///
+/// windowがSomeの場合、そのウィンドウのサーフェスに描画できるアダプタのみを選ぶ。
+/// すべての候補で失敗した場合、候補ごとの失敗の理由を列挙したエラーを返す。
+pub(super) fn select_adapter(
+    candidates: &[GraphicBackend],
+    window: Option<&Arc<Window>>,
+) -> Result<SelectedAdapter, EError> {
+    if candidates.is_empty() {
+        return Err("failed to get an adapter: no graphic backend is specified.".into());
+    }
+
+    let mut failures = Vec::new();
+    for candidate in candidates {
+        match try_adapter(*candidate, window) {
+            Ok(n) => return Ok(n),
+            Err(e) => failures.push(format!("  {}: {e}", candidate.name())),
+        }
+    }
+    Err(format!("failed to get an adapter. tried:\n{}", failures.join("\n")).into())
+}
+
+/// 1つの候補でアダプタ・デバイスを取得する関数。
+fn try_adapter(
+    candidate: GraphicBackend,
+    window: Option<&Arc<Window>>,
+) -> Result<SelectedAdapter, String> {
+    let backends = candidate.get_backends();
+    let instance = Instance::new(InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+
+    let surface = window
+        .map(|n| instance.create_surface(Arc::clone(n)))
+        .transpose()
+        .map_err(|e| format!("failed to create a surface ({e})."))?;
+
+    let request = instance.request_adapter(&RequestAdapterOptions {
+        power_preference: PowerPreference::default(),
+        compatible_surface: surface.as_ref(),
+        force_fallback_adapter: candidate == GraphicBackend::Software,
+    });
+    let Some(adapter) = executor::block_on(request) else {
+        // NOTE: 見つかったものの条件に合わなかったアダプタを診断のために列挙する。
+        let found = instance
+            .enumerate_adapters(backends)
+            .iter()
+            .map(|n| describe_adapter(&n.get_info()))
+            .collect::<Vec<_>>();
+        return Err(if found.is_empty() {
+            "no adapter is found.".to_string()
+        } else {
+            format!("no suitable adapter among {}.", found.join(", "))
+        });
+    };
+
+    let info = adapter.get_info();
+    let (device, queue) = request_device(&adapter, candidate).map_err(|e| {
+        format!(
+            "failed to get a device of {} ({e}).",
+            describe_adapter(&info)
+        )
+    })?;
+
+    Ok(SelectedAdapter {
+        adapter,
+        device,
+        queue,
+        surface,
+    })
+}
+
+/// アダプタの情報を診断用の文字列にする関数。
+pub fn describe_adapter(info: &AdapterInfo) -> String {
+    format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+}
+
+/// アダプタからデバイスとキューを取得する関数。
+///
+/// OpenGL・ソフトウェアアダプタは既定の制限を満たさないことがあるので、
+/// WebGL2相当の制限にアダプタの解像度の上限を合わせたものを要求する。
+fn request_device(adapter: &Adapter, candidate: GraphicBackend) -> Result<(Device, Queue), EError> {
+    let required_limits = match candidate {
+        GraphicBackend::Gl | GraphicBackend::Software => {
+            Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
+        }
+        _ => Limits::default(),
+    };
+    let request = adapter.request_device(
+        &DeviceDescriptor {
+            label: None,
+            required_features: Features::empty(),
+            required_limits,
+            memory_hints: MemoryHints::MemoryUsage,
+        },
+        None,
+    );
+    Ok(executor::block_on(request)?)
+}
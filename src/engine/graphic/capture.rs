@@ -0,0 +1,165 @@
+// NOTE: スクリーンショット・サムネイル・テスト用のユーティリティなので、使われなくても良い。
+#![allow(dead_code)]
+
+use super::*;
+
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+use std::{fs::File, io::BufWriter, path::Path, sync::mpsc};
+use wgpu::*;
+
+/// 描画結果をキャプチャした画像。
+///
+/// ビットマップはRGBA8(sRGB)であり、左上から行優先で並ぶ。
+#[derive(Clone)]
+pub struct CapturedImage {
+    pub width: u32,
+    pub height: u32,
+    pub bitmap: Vec<u8>,
+}
+
+impl CapturedImage {
+    /// PNGファイルとして保存するメソッド。
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), EError> {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = Encoder::new(writer, self.width, self.height);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.bitmap)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// PNGファイルから読み込むメソッド。
+    ///
+    /// ゴールデンイメージとの比較に用いる。
+    pub fn load_png<P: AsRef<Path>>(path: P) -> Result<Self, EError> {
+        let mut decoder = Decoder::new(File::open(path)?);
+        decoder.set_transformations(Transformations::EXPAND | Transformations::ALPHA);
+        let mut reader = decoder.read_info()?;
+        let mut bitmap = vec![0; reader.output_buffer_size()];
+        let output_info = reader.next_frame(&mut bitmap)?;
+        if output_info.color_type != ColorType::Rgba || output_info.bit_depth != BitDepth::Eight {
+            return Err("only 8-bit RGBA images are supported.".into());
+        }
+        bitmap.truncate(output_info.buffer_size());
+        Ok(Self {
+            width: output_info.width,
+            height: output_info.height,
+            bitmap,
+        })
+    }
+
+    /// 最近傍補間でwidth x heightに拡大縮小した画像を作成するメソッド。
+    ///
+    /// セーブスロットのサムネイル等に用いる。
+    pub fn resized(&self, width: u32, height: u32) -> Self {
+        let mut bitmap = Vec::with_capacity(4 * width as usize * height as usize);
+        for y in 0..height {
+            let sy = (y as u64 * self.height as u64 / height as u64) as usize;
+            for x in 0..width {
+                let sx = (x as u64 * self.width as u64 / width as u64) as usize;
+                let i = 4 * (sy * self.width as usize + sx);
+                bitmap.extend_from_slice(&self.bitmap[i..i + 4]);
+            }
+        }
+        Self {
+            width,
+            height,
+            bitmap,
+        }
+    }
+
+    /// otherとの各チャンネルの差の最大値を取得するメソッド。
+    ///
+    /// サイズが異なる場合、Noneを返す。
+    pub fn max_difference(&self, other: &Self) -> Option<u8> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+        self.bitmap
+            .iter()
+            .zip(&other.bitmap)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .or(Some(0))
+    }
+}
+
+/// テクスチャの内容をCPU側に読み出す関数。
+///
+/// 読み出しが完了するまでスレッドが待機される。
+pub fn read_texture(
+    device: &Device,
+    queue: &Queue,
+    texture: &Texture,
+) -> Result<CapturedImage, EError> {
+    let is_bgra = match texture.format() {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+        n => return Err(format!("texture format {n:?} cannot be captured.").into()),
+    };
+    let width = texture.width();
+    let height = texture.height();
+
+    // 読み出し用バッファを作成
+    // NOTE: 1行のバイト数はCOPY_BYTES_PER_ROW_ALIGNMENTの倍数でなければならない。
+    let unpadded_bytes_per_row = 4 * width;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+        * COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: padded_bytes_per_row as u64 * height as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    // テクスチャからバッファへコピー
+    let mut command_encoder =
+        device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    command_encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(command_encoder.finish()));
+
+    // マップされるまで待機
+    let (sender, receiver) = mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, move |n| {
+        let _ = sender.send(n);
+    });
+    device.poll(Maintain::Wait);
+    receiver.recv()??;
+
+    // パディングを取り除く
+    let mut bitmap = Vec::with_capacity(unpadded_bytes_per_row as usize * height as usize);
+    {
+        let mapped = slice.get_mapped_range();
+        for row in mapped.chunks(padded_bytes_per_row as usize) {
+            bitmap.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    // BGRAであればRGBAに並び替える
+    if is_bgra {
+        for n in bitmap.chunks_exact_mut(4) {
+            n.swap(0, 2);
+        }
+    }
+
+    Ok(CapturedImage {
+        width,
+        height,
+        bitmap,
+    })
+}
@@ -2,22 +2,43 @@ use super::*;
 
 use crate::engine::resource::ResourceManager;
 use glam::Vec4;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use wgpu::*;
 
-type Key = (&'static str, char);
+/// 文字画像のキー。(フォント名, 文字, 高さ [px])
+pub type CharacterKey = (&'static str, char, u32);
 
-const CHARS_TEXTURE_WIDTH: u32 = 8192;
-const CHARS_TEXTURE_HEIGHT: u32 = 8192;
-const CHARACTER_HEIGHT: u32 = 24;
-const CHARACTER_IMAGES_ROW_COUNT: usize = (CHARS_TEXTURE_HEIGHT / CHARACTER_HEIGHT) as usize;
+/// テクスチャアトラスの1ページの幅・高さの上限。
+///
+/// デバイスのテクスチャの大きさの上限の方が小さい場合、そちらを用いる。
+/// 1ページあたり最大16MiBである。
+const MAX_PAGE_SIZE: u32 = 2048;
+/// テクスチャアトラスのページの最大数。
+const MAX_PAGE_COUNT: usize = 8;
+/// 各ページの画像ID。
+const PAGE_IMAGE_IDS: [&str; MAX_PAGE_COUNT] = [
+    "chars0", "chars1", "chars2", "chars3", "chars4", "chars5", "chars6", "chars7",
+];
+/// 文字画像の周囲の余白 [px]。隣の文字画像が滲まないようにする。
+const PADDING: u32 = 1;
+/// ラスタライズする文字画像の高さの上限 [px]。
+const MAX_CHARACTER_HEIGHT: u32 = 512;
+
+/// 文字列の高さからその文字画像のキーを作成する関数。
+///
+/// 文字画像は文字列の高さを整数に丸めた高さでラスタライズされる。
+pub fn get_character_key(font_name: &'static str, character: char, height: f32) -> CharacterKey {
+    let height = (height.round() as u32).clamp(1, MAX_CHARACTER_HEIGHT);
+    (font_name, character, height)
+}
 
 /// 文字画像の情報。
 #[derive(Clone)]
 pub struct CharacterImage {
-    /// CharacterImagesTextureAtlasで登録されているキー。
-    /// (フォント名, 文字)
-    key: Key,
+    /// 文字画像が描き込まれているページの画像ID。
+    pub image_id: &'static str,
+    /// ラスタライズした高さ [px]。
+    size: u32,
     /// 文字画像のためのテクスチャアトラス上のUV座標。
     pub uv: Vec4,
     /// 文字画像のためのテクスチャアトラス上の幅。
@@ -32,9 +53,11 @@ pub struct CharacterImage {
     advance: f32,
 }
 impl CharacterImage {
-    /// heightを基準の高さにしたときの(幅,高さ,Y座標のオフセット)を取得するメソッド。
+    /// heightを基準の高さにしたときの(幅,高さ,X座標のオフセット,Y座標のオフセット,送り幅)を取得するメソッド。
+    ///
+    /// 文字画像はheightを丸めた高さでラスタライズされているので、拡大率はほぼ1である。
     pub fn scale(&self, height: f32) -> (f32, f32, f32, f32, f32) {
-        let r = height / CHARACTER_HEIGHT as f32;
+        let r = height / self.size as f32;
         (
             self.width * r,
             self.height * r,
@@ -45,138 +68,336 @@ impl CharacterImage {
     }
 }
 
-/// 文字画像のためのテクスチャアトラスを管理するオブジェクト。
-pub struct CharacterImagesTextureAtlas {
-    /// 文字画像のためのテクスチャアトラス。
-    pub texture: Texture,
-    /// 何行目か。
-    index: usize,
-    /// 行中の左から何px目か。
-    offset: u32,
-    /// 文字画像の情報。
-    character_images: Vec<Vec<CharacterImage>>,
+/// 棚。高さが棚以下の文字画像を左から詰めていく横長の領域。
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// 次に文字画像を詰める位置。
+    x: u32,
+    /// 詰められている文字画像。
+    keys: Vec<CharacterKey>,
+    /// 詰められている文字画像のいずれかが最後に使われたフレーム。
+    last_used: u64,
+}
+
+/// テクスチャアトラスの1ページの棚の配置。
+struct Page {
+    /// 棚。上から順に並ぶ。
+    shelves: Vec<Shelf>,
+    /// 棚が使っている高さ。
+    bottom: u32,
+}
+
+/// 文字画像を棚(シェルフ)に詰めて配置し、追い出しを管理するオブジェクト。
+///
+/// テクスチャは持たず、配置のみを計算する。
+/// 空きが無い場合、このフレームで使われていない文字画像の棚のうち最も長く使われていないものを追い出す(LRU)。
+/// 追い出せる棚も無い場合、ページを追加する。
+/// したがって、このフレームで使う文字画像が追い出されることはない。
+struct ShelfPacker {
+    /// 1ページの幅・高さ。
+    page_size: u32,
+    /// ページの最大数。
+    max_page_count: usize,
+    pages: Vec<Page>,
     /// 登録されている文字画像。
-    /// (フォント名, 文字)をキーに持ち、(行, 列)を値に持つ。
-    registered_characters: HashMap<Key, (usize, usize)>,
+    /// (フォント名, 文字, 高さ)をキーに持ち、(文字画像, ページ, 棚)を値に持つ。
+    registered_characters: HashMap<CharacterKey, (CharacterImage, usize, usize)>,
+    /// 追い出されたことのある文字画像。
+    ///
+    /// 再び登録された場合、その文字画像を用いるインスタンスのUV座標は古くなっている。
+    evicted_characters: HashSet<CharacterKey>,
+    /// このフレームで追い出されたことのある文字画像を再びロードしたか否か。
+    is_reloaded: bool,
+    /// 現在のフレーム。
+    frame: u64,
 }
 
-impl CharacterImagesTextureAtlas {
-    pub fn new(device: &Device, queue: &Queue) -> Self {
-        // テクスチャアトラスを作成
-        let texture_size = Extent3d {
-            width: CHARS_TEXTURE_WIDTH,
-            height: CHARS_TEXTURE_HEIGHT,
-            depth_or_array_layers: 1,
+impl ShelfPacker {
+    fn new(page_size: u32, max_page_count: usize) -> Self {
+        Self {
+            page_size,
+            max_page_count,
+            pages: Vec::new(),
+            registered_characters: HashMap::new(),
+            evicted_characters: HashSet::new(),
+            is_reloaded: false,
+            frame: 0,
+        }
+    }
+
+    /// 文字画像が登録されていれば、このフレームで使われたとみなしてtrueを返すメソッド。
+    fn touch(&mut self, key: &CharacterKey) -> bool {
+        let Some((_, page, shelf)) = self.registered_characters.get(key) else {
+            return false;
         };
-        let texture = device.create_texture(&TextureDescriptor {
-            label: None,
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-            view_formats: &[],
+        self.pages[*page].shelves[*shelf].last_used = self.frame;
+        true
+    }
+
+    /// width x heightの場所を確保し、(ページ, 棚, X座標, Y座標)を返すメソッド。
+    ///
+    /// 確保した場所の文字画像はregister()で登録すること。
+    fn reserve(
+        &mut self,
+        key: CharacterKey,
+        width: u32,
+        height: u32,
+    ) -> Result<(usize, usize, u32, u32), EError> {
+        let (page, shelf) = self.allocate(width, height)?;
+        let shelf_ref = &mut self.pages[page].shelves[shelf];
+        let (x, y) = (shelf_ref.x, shelf_ref.y);
+        shelf_ref.x += width;
+        shelf_ref.keys.push(key);
+        shelf_ref.last_used = self.frame;
+        Ok((page, shelf, x, y))
+    }
+
+    /// reserve()で確保した場所に文字画像を登録するメソッド。
+    ///
+    /// 追い出されたことのある文字画像を再び登録した場合、trueを返す。
+    fn register(
+        &mut self,
+        key: CharacterKey,
+        image: CharacterImage,
+        page: usize,
+        shelf: usize,
+    ) -> bool {
+        self.registered_characters.insert(key, (image, page, shelf));
+        let is_reloaded = self.evicted_characters.remove(&key);
+        self.is_reloaded |= is_reloaded;
+        is_reloaded
+    }
+
+    /// width x heightの領域を確保し、(ページ, 棚)を返すメソッド。
+    ///
+    /// 次の順に試す：
+    /// 1. 高さの近い既存の棚の空き
+    /// 2. ページの下の空きに新しい棚を作る
+    /// 3. 高さの足りる既存の棚の空き
+    /// 4. このフレームで使われていない棚のうち最も長く使われていないものを追い出して再利用する
+    /// 5. ページを追加する
+    /// 6. すべての棚がこのフレームで使われていないページを丸ごと空ける
+    fn allocate(&mut self, width: u32, height: u32) -> Result<(usize, usize), EError> {
+        let page_size = self.page_size;
+        if width > page_size || height > page_size {
+            return Err(format!("a character image of {width}x{height} is too large.").into());
+        }
+
+        // 1. 3.
+        let find_shelf = |pages: &[Page], max_height: u32| {
+            pages
+                .iter()
+                .enumerate()
+                .flat_map(|(i, n)| n.shelves.iter().enumerate().map(move |(j, n)| (i, j, n)))
+                .filter(|(_, _, n)| {
+                    n.height >= height && n.height <= max_height && page_size - n.x >= width
+                })
+                .min_by_key(|(_, _, n)| n.height)
+                .map(|(i, j, _)| (i, j))
+        };
+        if let Some(n) = find_shelf(&self.pages, height + height / 2) {
+            return Ok(n);
+        }
+
+        // 2.
+        if let Some(i) = self
+            .pages
+            .iter()
+            .position(|n| page_size - n.bottom >= height)
+        {
+            return Ok((i, self.add_shelf(i, height)));
+        }
+
+        // 3.
+        if let Some(n) = find_shelf(&self.pages, u32::MAX) {
+            return Ok(n);
+        }
+
+        // 4.
+        let lru_shelf = self
+            .pages
+            .iter()
+            .enumerate()
+            .flat_map(|(i, n)| n.shelves.iter().enumerate().map(move |(j, n)| (i, j, n)))
+            .filter(|(_, _, n)| n.height >= height && n.last_used < self.frame)
+            .min_by_key(|(_, _, n)| n.last_used)
+            .map(|(i, j, _)| (i, j));
+        if let Some((i, j)) = lru_shelf {
+            self.evict_shelf(i, j);
+            return Ok((i, j));
+        }
+
+        // 5.
+        if self.pages.len() < self.max_page_count {
+            self.pages.push(Page {
+                shelves: Vec::new(),
+                bottom: 0,
+            });
+            let i = self.pages.len() - 1;
+            return Ok((i, self.add_shelf(i, height)));
+        }
+
+        // 6.
+        let lru_page = self
+            .pages
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.shelves.iter().all(|n| n.last_used < self.frame))
+            .min_by_key(|(_, n)| n.shelves.iter().map(|n| n.last_used).max())
+            .map(|(i, _)| i);
+        if let Some(i) = lru_page {
+            for j in 0..self.pages[i].shelves.len() {
+                self.evict_shelf(i, j);
+            }
+            self.pages[i].shelves.clear();
+            self.pages[i].bottom = 0;
+            return Ok((i, self.add_shelf(i, height)));
+        }
+
+        Err("the character images texture atlas is full.".into())
+    }
+
+    /// ページの下の空きに高さheightの棚を作り、その番号を返すメソッド。
+    fn add_shelf(&mut self, page: usize, height: u32) -> usize {
+        let page = &mut self.pages[page];
+        page.shelves.push(Shelf {
+            y: page.bottom,
+            height,
+            x: 0,
+            keys: Vec::new(),
+            last_used: self.frame,
         });
+        page.bottom += height;
+        page.shelves.len() - 1
+    }
 
-        // テクスチャアトラスを0にクリア
-        queue.write_texture(
-            ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: Origin3d::ZERO,
-                aspect: TextureAspect::All,
-            },
-            &vec![0; 4 * CHARS_TEXTURE_WIDTH as usize * CHARS_TEXTURE_HEIGHT as usize],
-            ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * CHARS_TEXTURE_WIDTH),
-                rows_per_image: None,
-            },
-            texture_size,
-        );
+    /// 棚の文字画像をすべて追い出し、棚を空にするメソッド。
+    fn evict_shelf(&mut self, page: usize, shelf: usize) {
+        let shelf = &mut self.pages[page].shelves[shelf];
+        for key in shelf.keys.drain(..) {
+            self.registered_characters.remove(&key);
+            self.evicted_characters.insert(key);
+        }
+        shelf.x = 0;
+    }
+
+    fn next_frame(&mut self) {
+        self.frame += 1;
+        self.is_reloaded = false;
+    }
 
-        let mut character_images = Vec::with_capacity(CHARACTER_IMAGES_ROW_COUNT);
-        for _ in 0..CHARACTER_IMAGES_ROW_COUNT {
-            character_images.push(Vec::new());
+    /// ページの使用率 [0, 1] を求めるメソッド。
+    fn get_usage(&self) -> f32 {
+        let area = self
+            .pages
+            .iter()
+            .flat_map(|n| &n.shelves)
+            .map(|n| n.x as u64 * n.height as u64)
+            .sum::<u64>();
+        let capacity = self.pages.len() as u64 * self.page_size as u64 * self.page_size as u64;
+        if capacity == 0 {
+            0.0
+        } else {
+            area as f32 / capacity as f32
         }
+    }
+}
+
+/// 文字画像のためのテクスチャアトラスを管理するオブジェクト。
+///
+/// 文字画像は高さごとにラスタライズされ、別々に登録される。
+/// 配置はShelfPackerが決め、このオブジェクトはページのテクスチャへの描き込みを行う。
+pub struct CharacterImagesTextureAtlas {
+    packer: ShelfPacker,
+    /// 各ページのテクスチャ。
+    textures: Vec<Texture>,
+}
 
+impl CharacterImagesTextureAtlas {
+    /// コンストラクタ。
+    ///
+    /// ページは必要になったときに作成される。
+    /// ページの大きさはデバイスの制限に合わせる。
+    pub fn new(device: &Device) -> Self {
         Self {
-            texture,
-            index: 0,
-            offset: 0,
-            character_images,
-            registered_characters: HashMap::new(),
+            packer: ShelfPacker::new(
+                get_page_size(device.limits().max_texture_dimension_2d),
+                MAX_PAGE_COUNT,
+            ),
+            textures: Vec::new(),
         }
     }
 
+    /// 文字画像をロードするメソッド。
+    ///
+    /// 既にロードされている場合、このフレームで使われたとみなすのみである。
+    /// 追い出されたことのある文字画像を再びロードした場合、trueを返す。
+    /// このとき、その文字画像を用いるインスタンスは更新する必要がある。
     pub fn load(
         &mut self,
         rs_mngr: &mut ResourceManager,
+        device: &Device,
         queue: &Queue,
-        font_name: &'static str,
-        character: char,
+        key: CharacterKey,
     ) -> Result<bool, EError> {
+        let (font_name, character, size) = key;
+
         // 存在チェック
-        if self
-            .registered_characters
-            .contains_key(&(font_name, character))
-        {
+        if self.packer.touch(&key) {
             return Ok(false);
         }
 
         // 文字画像を取得
-        let result = rs_mngr.rasterize_character(font_name, character, CHARACTER_HEIGHT as f32)?;
-
-        // 右に行けない場合、行を移動して、移動先の行をクリア
-        // クリアしたか否か、記憶
-        let cleared = if self.offset + result.width >= CHARS_TEXTURE_WIDTH {
-            self.offset = 0;
-            if self.index + 1 >= CHARACTER_IMAGES_ROW_COUNT {
-                self.index = 0;
-            } else {
-                self.index += 1;
-            }
-            for n in self.character_images[self.index].iter() {
-                self.registered_characters.remove(&n.key);
-            }
-            self.character_images[self.index].clear();
-            true
-        } else {
-            false
-        };
+        let result = rs_mngr.rasterize_character(font_name, character, size as f32)?;
 
-        // テクスチャアトラスに描き込み
-        let x = self.offset;
-        let y = self.index as u32 * CHARACTER_HEIGHT;
+        // 配置する場所を確保
+        let width = result.width + PADDING * 2;
+        let height = result.height + PADDING * 2;
+        let (page, shelf, x, y) = self.packer.reserve(key, width, height)?;
+        while self.textures.len() < self.packer.pages.len() {
+            self.add_page(device);
+        }
+
+        // テクスチャアトラスに余白ごと描き込み
+        // NOTE: 余白は透明な白であり、線形補間されても縁が黒ずまない。
+        let mut texture = [255, 255, 255, 0].repeat((width * height) as usize);
+        for row in 0..result.height as usize {
+            let src = 4 * result.width as usize * row;
+            let dst = 4 * (width as usize * (row + PADDING as usize) + PADDING as usize);
+            let length = 4 * result.width as usize;
+            texture[dst..dst + length].copy_from_slice(&result.texture[src..src + length]);
+        }
         queue.write_texture(
             ImageCopyTexture {
-                texture: &self.texture,
+                texture: &self.textures[page],
                 mip_level: 0,
                 origin: Origin3d { x, y, z: 0 },
                 aspect: TextureAspect::All,
             },
-            &result.texture,
+            &texture,
             ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(4 * result.width),
+                bytes_per_row: Some(4 * width),
                 rows_per_image: None,
             },
             Extent3d {
-                width: result.width,
-                height: result.height,
+                width,
+                height,
                 depth_or_array_layers: 1,
             },
         );
 
         // 登録
+        let page_size = self.packer.page_size as f32;
         let character_image = CharacterImage {
-            key: (font_name, character),
+            image_id: PAGE_IMAGE_IDS[page],
+            size,
             uv: Vec4::new(
-                x as f32 / CHARS_TEXTURE_WIDTH as f32,
-                y as f32 / CHARS_TEXTURE_HEIGHT as f32,
-                result.width as f32 / CHARS_TEXTURE_WIDTH as f32,
-                result.height as f32 / CHARS_TEXTURE_HEIGHT as f32,
+                (x + PADDING) as f32 / page_size,
+                (y + PADDING) as f32 / page_size,
+                result.width as f32 / page_size,
+                result.height as f32 / page_size,
             ),
             width: result.width as f32,
             height: result.height as f32,
@@ -184,19 +405,206 @@ impl CharacterImagesTextureAtlas {
             y_offset: result.y_offset,
             advance: result.advance,
         };
-        self.registered_characters.insert(
-            (font_name, character),
-            (self.index, self.character_images[self.index].len()),
+        Ok(self.packer.register(key, character_image, page, shelf))
+    }
+
+    /// ページを追加するメソッド。
+    ///
+    /// NOTE: wgpuはテクスチャを0で初期化するので、クリアしない。
+    fn add_page(&mut self, device: &Device) {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: self.packer.page_size,
+                height: self.packer.page_size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        self.textures.push(texture);
+    }
+
+    /// フレームを進めるメソッド。
+    ///
+    /// 描画のたびに呼ぶ。前のフレームまでに使われた文字画像は追い出されうるようになる。
+    pub fn next_frame(&mut self) {
+        self.packer.next_frame();
+    }
+
+    /// このフレームで追い出されたことのある文字画像を再びロードしたか否かを取得するメソッド。
+    pub fn is_reloaded(&self) -> bool {
+        self.packer.is_reloaded
+    }
+
+    /// 各ページの(画像ID, テクスチャ)を取得するメソッド。
+    pub fn get_pages(&self) -> impl Iterator<Item = (&'static str, &Texture)> {
+        PAGE_IMAGE_IDS.into_iter().zip(&self.textures)
+    }
+
+    /// 登録されている文字画像の数・ページの数・ページの使用率 [0, 1] を取得するメソッド。
+    ///
+    /// 使用率は作成済みのページの面積に対する、文字画像が詰められた面積の割合である。
+    pub fn get_usage(&self) -> (u32, u32, f32) {
+        (
+            self.packer.registered_characters.len() as u32,
+            self.packer.pages.len() as u32,
+            self.packer.get_usage(),
+        )
+    }
+
+    pub fn get(&self, key: &CharacterKey) -> Option<&CharacterImage> {
+        self.packer
+            .registered_characters
+            .get(key)
+            .map(|(n, _, _)| n)
+    }
+}
+
+/// デバイスのテクスチャの大きさの上限からページの大きさを求める関数。
+fn get_page_size(max_texture_dimension: u32) -> u32 {
+    MAX_PAGE_SIZE.min(max_texture_dimension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(character: char) -> CharacterKey {
+        ("font", character, 16)
+    }
+
+    /// 文字画像を確保・登録し、(ページ, X座標, Y座標, 再ロードか否か)を返す関数。
+    fn insert(
+        packer: &mut ShelfPacker,
+        character: char,
+        width: u32,
+        height: u32,
+    ) -> Result<(usize, u32, u32, bool), EError> {
+        let key = key(character);
+        let (page, shelf, x, y) = packer.reserve(key, width, height)?;
+        let image = CharacterImage {
+            image_id: PAGE_IMAGE_IDS[page],
+            size: 16,
+            uv: Vec4::ZERO,
+            width: width as f32,
+            height: height as f32,
+            x_offset: 0.0,
+            y_offset: 0.0,
+            advance: width as f32,
+        };
+        let is_reloaded = packer.register(key, image, page, shelf);
+        Ok((page, x, y, is_reloaded))
+    }
+
+    #[test]
+    fn reuses_shelves_of_similar_height() {
+        let mut packer = ShelfPacker::new(256, 1);
+        assert_eq!(insert(&mut packer, 'a', 10, 20).unwrap(), (0, 0, 0, false));
+        // 高さが1.5倍以内の棚に詰める
+        assert_eq!(insert(&mut packer, 'b', 10, 16).unwrap(), (0, 10, 0, false));
+        assert_eq!(insert(&mut packer, 'c', 10, 14).unwrap(), (0, 20, 0, false));
+        // 低すぎる文字画像には新しい棚を作る
+        assert_eq!(insert(&mut packer, 'd', 10, 12).unwrap(), (0, 0, 20, false));
+        // 高すぎる文字画像にも新しい棚を作る
+        assert_eq!(insert(&mut packer, 'e', 10, 30).unwrap(), (0, 0, 32, false));
+        // 棚の右端に収まらなければ次の棚へ
+        assert_eq!(
+            insert(&mut packer, 'f', 250, 20).unwrap(),
+            (0, 0, 62, false)
         );
-        self.character_images[self.index].push(character_image);
-        self.offset += result.width;
 
-        Ok(cleared)
+        assert_eq!(packer.pages[0].shelves.len(), 4);
+        assert_eq!(packer.registered_characters.len(), 6);
+    }
+
+    #[test]
+    fn evicts_only_shelves_unused_in_this_frame() {
+        // 1ページに高さ32の棚が2つだけ入る
+        let mut packer = ShelfPacker::new(64, 1);
+        insert(&mut packer, 'a', 64, 32).unwrap();
+        packer.next_frame();
+        insert(&mut packer, 'b', 64, 32).unwrap();
+        packer.next_frame();
+
+        // 直近に使われた棚は残し、最も長く使われていない棚を追い出す
+        assert!(packer.touch(&key('b')));
+        assert_eq!(insert(&mut packer, 'c', 64, 32).unwrap(), (0, 0, 0, false));
+        assert!(packer.evicted_characters.contains(&key('a')));
+        assert!(!packer.touch(&key('a')));
+        assert!(packer.touch(&key('b')));
+
+        // このフレームで使われている棚しか無ければ失敗する
+        assert!(insert(&mut packer, 'd', 64, 32).is_err());
+        assert!(packer.touch(&key('b')) && packer.touch(&key('c')));
+    }
+
+    #[test]
+    fn reports_reloads_after_eviction() {
+        let mut packer = ShelfPacker::new(64, 1);
+        insert(&mut packer, 'a', 64, 64).unwrap();
+        packer.next_frame();
+        insert(&mut packer, 'b', 64, 64).unwrap();
+        assert!(!packer.is_reloaded);
+        packer.next_frame();
+
+        assert!(!packer.touch(&key('a')));
+        assert_eq!(insert(&mut packer, 'a', 64, 64).unwrap(), (0, 0, 0, true));
+        assert!(packer.is_reloaded);
+        assert!(!packer.evicted_characters.contains(&key('a')));
+
+        // フレームを進めると戻る
+        packer.next_frame();
+        assert!(!packer.is_reloaded);
+    }
+
+    #[test]
+    fn adds_pages_up_to_limit_and_resets_unused_pages() {
+        // 1ページに高さ16の棚が2つだけ入る
+        let mut packer = ShelfPacker::new(32, MAX_PAGE_COUNT);
+        let chars = ('a'..).take(MAX_PAGE_COUNT * 2).collect::<Vec<_>>();
+        for (i, c) in chars.iter().enumerate() {
+            let y = 16 * (i as u32 % 2);
+            assert_eq!(
+                insert(&mut packer, *c, 32, 16).unwrap(),
+                (i / 2, 0, y, false)
+            );
+        }
+        assert_eq!(packer.pages.len(), MAX_PAGE_COUNT);
+
+        // 棚より高い文字画像は、すべての棚が使われていないページを丸ごと空けて置く
+        packer.next_frame();
+        for c in &chars[3..] {
+            assert!(packer.touch(&key(*c)));
+        }
+        assert_eq!(insert(&mut packer, 'z', 32, 32).unwrap(), (0, 0, 0, false));
+        assert!(packer.evicted_characters.contains(&key('a')));
+        assert!(packer.evicted_characters.contains(&key('b')));
+        // 一部の棚が使われているページは空けない
+        assert!(insert(&mut packer, 'y', 32, 32).is_err());
+        assert!(packer.touch(&key('d')));
+        assert_eq!(packer.pages.len(), MAX_PAGE_COUNT);
+    }
+
+    #[test]
+    fn rejects_too_large_images() {
+        let mut packer = ShelfPacker::new(64, 1);
+        assert!(insert(&mut packer, 'a', 65, 10).is_err());
+        assert!(insert(&mut packer, 'a', 10, 65).is_err());
+        assert!(packer.pages.is_empty());
     }
 
-    pub fn get(&self, font_name: &'static str, character: char) -> Option<&CharacterImage> {
-        self.registered_characters
-            .get(&(font_name, character))
-            .map(|(i, j)| &self.character_images[*i][*j])
+    #[test]
+    fn limits_page_size() {
+        assert_eq!(get_page_size(8192), 2048);
+        assert_eq!(get_page_size(2048), 2048);
+        assert_eq!(get_page_size(1024), 1024);
+        // 最大のページ数でも128MiBに収まる
+        let memory = 4 * MAX_PAGE_COUNT as u64 * get_page_size(u32::MAX) as u64 * 2048;
+        assert_eq!(memory, 128 << 20);
     }
 }
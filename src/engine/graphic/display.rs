@@ -0,0 +1,146 @@
+use super::*;
+
+use std::{fs, num::NonZeroU32, path::Path};
+
+/// 表示設定の設定ファイルのパス。
+pub const CONFIG_PATH: &str = "display.cfg";
+
+/// フルスクリーンの方式。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// ボーダーレスウィンドウ。切り替えが速く、他のウィンドウに移りやすい。
+    Borderless,
+    /// 排他的フルスクリーン。モニタの解像度・リフレッシュレートが最大のビデオモードを用いる。
+    Exclusive,
+}
+
+/// 表示設定。
+///
+/// GraphicManager.set_display_settings()で実行時に適用できる。
+///
+/// 設定ファイルは次のような1行1項目のテキストである(#以降はコメント)：
+/// ```text
+/// vsync = on
+/// frame_rate_cap = 60
+/// fullscreen = off
+/// fullscreen_mode = borderless
+/// monitor = DELL U2720Q
+/// scaling = fit
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplaySettings {
+    /// 垂直同期を取るか否か。
+    pub vsync: bool,
+    /// フレームレートの上限 [fps]。Noneであれば上限なし。
+    pub frame_rate_cap: Option<NonZeroU32>,
+    /// フルスクリーンか否か。
+    pub is_fullscreen: bool,
+    pub fullscreen_mode: FullscreenMode,
+    /// フルスクリーンにするモニタの名前。
+    ///
+    /// Noneの場合・見つからない場合、ウィンドウのあるモニタを用いる。
+    pub monitor: Option<String>,
+    pub scaling_mode: ScalingMode,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            frame_rate_cap: None,
+            is_fullscreen: false,
+            fullscreen_mode: FullscreenMode::Borderless,
+            monitor: None,
+            scaling_mode: ScalingMode::Fit,
+        }
+    }
+}
+
+impl DisplaySettings {
+    /// 設定ファイルから表示設定を読み込むメソッド。
+    ///
+    /// 設定ファイルに書かれていない項目は変更されない。
+    /// エラーの場合、何も変更されない。
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), EError> {
+        let text = fs::read_to_string(path)?;
+
+        let mut settings = self.clone();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: '=' is expected.", i + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = || format!("line {}: invalid value '{value}' for '{key}'.", i + 1);
+            match key {
+                "vsync" => settings.vsync = parse_switch(value).ok_or_else(invalid)?,
+                "frame_rate_cap" => {
+                    let cap = value.parse::<u32>().map_err(|_| invalid())?;
+                    settings.frame_rate_cap = NonZeroU32::new(cap);
+                }
+                "fullscreen" => settings.is_fullscreen = parse_switch(value).ok_or_else(invalid)?,
+                "fullscreen_mode" => {
+                    settings.fullscreen_mode = match value {
+                        "borderless" => FullscreenMode::Borderless,
+                        "exclusive" => FullscreenMode::Exclusive,
+                        _ => return Err(invalid().into()),
+                    }
+                }
+                "monitor" => settings.monitor = (!value.is_empty()).then(|| value.to_string()),
+                "scaling" => {
+                    settings.scaling_mode = match value {
+                        "fit" => ScalingMode::Fit,
+                        "integer" => ScalingMode::Integer,
+                        _ => return Err(invalid().into()),
+                    }
+                }
+                _ => return Err(format!("line {}: unknown key '{key}'.", i + 1).into()),
+            }
+        }
+
+        *self = settings;
+        Ok(())
+    }
+
+    /// 設定ファイルに表示設定を書き込むメソッド。
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), EError> {
+        let switch = |n: bool| if n { "on" } else { "off" };
+        let mut text = String::new();
+        text += &format!("vsync = {}\n", switch(self.vsync));
+        text += "# 0は上限なし\n";
+        text += &format!(
+            "frame_rate_cap = {}\n",
+            self.frame_rate_cap.map_or(0, NonZeroU32::get)
+        );
+        text += &format!("fullscreen = {}\n", switch(self.is_fullscreen));
+        text += &format!(
+            "fullscreen_mode = {}\n",
+            match self.fullscreen_mode {
+                FullscreenMode::Borderless => "borderless",
+                FullscreenMode::Exclusive => "exclusive",
+            }
+        );
+        text += "# 空であればウィンドウのあるモニタ\n";
+        text += &format!("monitor = {}\n", self.monitor.as_deref().unwrap_or(""));
+        text += &format!(
+            "scaling = {}\n",
+            match self.scaling_mode {
+                ScalingMode::Fit => "fit",
+                ScalingMode::Integer => "integer",
+            }
+        );
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
@@ -12,7 +12,7 @@ pub struct Vertex {
 }
 
 /// 頂点バッファのレイアウト。
-pub const VERTEX_BUFFER_LAYOUTS: &[VertexBufferLayout] = &[VertexBufferLayout {
+pub const VERTEX_BUFFER_LAYOUT: VertexBufferLayout = VertexBufferLayout {
     array_stride: mem::size_of::<Vertex>() as u64,
     step_mode: VertexStepMode::Vertex,
     attributes: &[
@@ -27,7 +27,7 @@ pub const VERTEX_BUFFER_LAYOUTS: &[VertexBufferLayout] = &[VertexBufferLayout {
             shader_location: 1,
         },
     ],
-}];
+};
 
 /// モデルデータの構造体。
 pub struct Model {
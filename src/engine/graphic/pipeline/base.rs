@@ -12,15 +12,6 @@ struct Camera {
 @binding(0)
 var<uniform> camera: Camera;
 
-struct Instance {
-    world: mat4x4<f32>,
-    uv: vec4<f32>,
-    color: vec4<f32>,
-    param: vec4<f32>,
-}
-@group(0)
-@binding(1)
-var<uniform> instances: array<Instance, 512>;
 
 @group(1)
 @binding(0)
@@ -34,6 +25,15 @@ struct VertexInput {
     @location(0) position: vec4<f32>,
     @location(1) uv: vec2<f32>,
 }
+struct InstanceInput {
+    @location(2) world_0: vec4<f32>,
+    @location(3) world_1: vec4<f32>,
+    @location(4) world_2: vec4<f32>,
+    @location(5) world_3: vec4<f32>,
+    @location(6) uv: vec4<f32>,
+    @location(7) color: vec4<f32>,
+    @location(8) param: vec4<f32>,
+}
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) uv: vec2<f32>,
@@ -42,11 +42,18 @@ struct VertexOutput {
 
 @vertex
 fn vs_main(
-    @builtin(instance_index) instance_index: u32,
     vertex_input: VertexInput,
+    instance_input: InstanceInput,
 ) -> VertexOutput {
     var result: VertexOutput;
 
+    let world: mat4x4<f32> = mat4x4<f32>(
+        instance_input.world_0,
+        instance_input.world_1,
+        instance_input.world_2,
+        instance_input.world_3,
+    );
+
     let identity: mat4x4<f32> = mat4x4<f32>(
         vec4<f32>(1.0, 0.0, 0.0, 0.0),
         vec4<f32>(0.0, 1.0, 0.0, 0.0),
@@ -54,16 +61,16 @@ fn vs_main(
         vec4<f32>(0.0, 0.0, 0.0, 1.0)
     );
     result.position = camera.projection
-        * ((1.0 - instances[instance_index].param.x) * camera.view + instances[instance_index].param.x * identity)
-        * instances[instance_index].world
+        * ((1.0 - instance_input.param.x) * camera.view + instance_input.param.x * identity)
+        * world
         * vertex_input.position;
 
     result.uv = vec2<f32>(
-        instances[instance_index].uv.x + instances[instance_index].uv.z * vertex_input.uv.x,
-        instances[instance_index].uv.y + instances[instance_index].uv.w * vertex_input.uv.y,
+        instance_input.uv.x + instance_input.uv.z * vertex_input.uv.x,
+        instance_input.uv.y + instance_input.uv.w * vertex_input.uv.y,
     );
 
-    result.color = instances[instance_index].color;
+    result.color = instance_input.color;
 
     return result;
 }
@@ -74,7 +81,23 @@ fn fs_main(vertex_output: VertexOutput) -> @location(0) vec4<f32> {
 }
 ";
 
-const MAX_INSTANCE_COUNT: u32 = 512;
+/// インスタンスバッファの初期容量 [インスタンス]。
+const INITIAL_INSTANCE_CAPACITY: u32 = 512;
+
+/// インスタンスバッファのレイアウト。
+const INSTANCE_BUFFER_LAYOUT: VertexBufferLayout = VertexBufferLayout {
+    array_stride: mem::size_of::<Instance>() as u64,
+    step_mode: VertexStepMode::Instance,
+    attributes: &vertex_attr_array![
+        2 => Float32x4,
+        3 => Float32x4,
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+    ],
+};
 
 /// カメラの構造体。
 #[derive(Clone)]
@@ -97,10 +120,12 @@ pub struct Instance {
 /// - 深度テストなし
 /// - アルファブレンディングあり
 /// - 拡大/縮小ともにアンチエイリアシングなし
+/// - インスタンスバッファは頂点バッファとして渡され、容量が足りなければ拡張される
 pub struct BasePipeline {
     render_pipeline: RenderPipeline,
     camera_buffer: Buffer,
     instance_buffer: Buffer,
+    instance_capacity: u32,
     sampler: Sampler,
     bind_group_1_layout: BindGroupLayout,
     bind_group_0: BindGroup,
@@ -123,30 +148,16 @@ impl BasePipeline {
         // group(0)のレイアウトを定義
         let bind_group_0_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: None,
-            entries: &[
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::VERTEX,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: BufferSize::new(mem::size_of::<Camera>() as u64),
-                    },
-                    count: None,
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: BufferSize::new(mem::size_of::<Camera>() as u64),
                 },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::VERTEX,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: BufferSize::new(
-                            mem::size_of::<Instance>() as u64 * MAX_INSTANCE_COUNT as u64,
-                        ),
-                    },
-                    count: None,
-                },
-            ],
+                count: None,
+            }],
         });
 
         // group(1)のレイアウトを定義
@@ -187,7 +198,7 @@ impl BasePipeline {
                 module: &shader_module,
                 entry_point: Some("vs_main"),
                 compilation_options: Default::default(),
-                buffers: model::VERTEX_BUFFER_LAYOUTS,
+                buffers: &[model::VERTEX_BUFFER_LAYOUT, INSTANCE_BUFFER_LAYOUT],
             },
             fragment: Some(FragmentState {
                 module: &shader_module,
@@ -240,20 +251,7 @@ impl BasePipeline {
         });
 
         // インスタンス配列のバッファを作成
-        let instances = (0..MAX_INSTANCE_COUNT)
-            .into_iter()
-            .map(|_| Instance {
-                _world: Mat4::IDENTITY,
-                _uv: Vec4::new(0.0, 0.0, 1.0, 1.0),
-                _color: Vec4::new(1.0, 1.0, 1.0, 1.0),
-                _param: Vec4::ZERO,
-            })
-            .collect::<Vec<Instance>>();
-        let instance_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: slice_to_u8slice(instances.as_slice()),
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-        });
+        let instance_buffer = create_instance_buffer(device, INITIAL_INSTANCE_CAPACITY);
 
         // サンプラを作成
         let sampler = device.create_sampler(&SamplerDescriptor {
@@ -274,22 +272,17 @@ impl BasePipeline {
         let bind_group_0 = device.create_bind_group(&BindGroupDescriptor {
             label: None,
             layout: &bind_group_0_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: camera_buffer.as_entire_binding(),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: instance_buffer.as_entire_binding(),
-                },
-            ],
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
         });
 
         Self {
             render_pipeline,
             camera_buffer,
             instance_buffer,
+            instance_capacity: INITIAL_INSTANCE_CAPACITY,
             sampler,
             bind_group_1_layout,
             bind_group_0,
@@ -333,10 +326,36 @@ impl BasePipeline {
         queue.write_buffer(&self.camera_buffer, 0, anything_to_u8slice(camera));
     }
 
+    /// インスタンスバッファの容量を少なくともcountにするメソッド。
+    ///
+    /// 容量が足りない場合、倍々に拡張した新たなバッファを作成し、旧バッファの内容をコピーする。
+    pub fn reserve_instances(&mut self, device: &Device, queue: &Queue, count: u32) {
+        if count <= self.instance_capacity {
+            return;
+        }
+        let capacity = count.max(self.instance_capacity.saturating_mul(2));
+        let instance_buffer = create_instance_buffer(device, capacity);
+
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+        command_encoder.copy_buffer_to_buffer(
+            &self.instance_buffer,
+            0,
+            &instance_buffer,
+            0,
+            self.instance_buffer.size(),
+        );
+        queue.submit(Some(command_encoder.finish()));
+
+        self.instance_buffer = instance_buffer;
+        self.instance_capacity = capacity;
+    }
+
     /// インスタンスバッファを更新するメソッド。
     ///
     /// WARN: インスタンスバッファは各フレームの描画開始前に更新すべし。
     /// WARN: インスタンスバッファを超過しているか否か、判定しない。
+    ///       予めreserve_instances()で容量を確保すべし。
     pub fn update_instances(&self, queue: &Queue, offset: u32, instances: &[Instance]) {
         queue.write_buffer(
             &self.instance_buffer,
@@ -349,6 +368,7 @@ impl BasePipeline {
     pub fn start(&self, render_pass: &mut RenderPass<'_>) {
         render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_bind_group(0, &self.bind_group_0, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
     }
 
     /// モデルをセットするメソッド。
@@ -377,3 +397,13 @@ impl BasePipeline {
         }
     }
 }
+
+/// capacity個のインスタンスを格納できるインスタンスバッファを作成する関数。
+fn create_instance_buffer(device: &Device, capacity: u32) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: mem::size_of::<Instance>() as u64 * capacity as u64,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
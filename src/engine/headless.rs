@@ -0,0 +1,211 @@
+use super::*;
+
+#[cfg(test)]
+use winit::keyboard::KeyCode;
+
+/// ウィンドウを作らずにクライアントを駆動するランナー。
+///
+/// ディスプレイの無い環境でクライアントのロジックを自動テストするために用いる。
+/// - 描画はオフスクリーンのテクスチャに行われる
+/// - 経過時間は呼び出し側が指定する
+/// - 入力状態は呼び出し側が指定する
+/// - 入力はInputManager.start_recording()で記録でき、run_replay()で再生できる(run_headless()を参照)
+/// - 音声はヌルバックエンドに出力される(`AudioManager.set_backend()`で差し替えられる)
+pub struct HeadlessRunner<T>
+where
+    T: ClientHandler,
+{
+    mngrs: Managers<'static>,
+    client: T,
+    timestep: timestep::Timestep,
+    running: bool,
+    frame: u64,
+}
+
+impl<T> HeadlessRunner<T>
+where
+    T: ClientHandler,
+{
+    /// コンストラクタ。
+    ///
+    /// infoのうちシーンのサイズ・描画のバックエンド・タイムステップの設定のみ用いられる。
+    pub fn new(info: &ApplicationInfo) -> Result<Self, EError> {
+        let gr_mngr = graphic::GraphicManager::new_headless(
+            info.scene_width,
+            info.scene_height,
+            &info.graphic_backends,
+        )?;
+        let mut in_mngr = input::InputManager::new();
+        // NOTE: ウィンドウが無いので、ウィンドウ上の位置はそのままキャンバス座標系の位置となる。
+        let scene_size = glam::Vec2::new(info.scene_width as f32, info.scene_height as f32);
+        in_mngr.set_viewport(glam::Vec2::ZERO, scene_size, scene_size);
+        let rs_mngr = resource::ResourceManager::new()?;
+        let au_mngr = audio::AudioManager::new(Box::new(audio::backend::NullBackend::new()));
+        let mut mngrs = Managers {
+            gr_mngr,
+            in_mngr,
+            rs_mngr,
+            au_mngr,
+            frame_stats: stats::FrameStats::new(),
+        };
+
+        let client = T::new(&mut mngrs);
+
+        let timestep = timestep::Timestep::new(info.timestep_mode, info.max_frame_duration);
+
+        Ok(Self {
+            mngrs,
+            client,
+            timestep,
+            running: true,
+            frame: 0,
+        })
+    }
+
+    /// 直前に描画したフレームを読み出すメソッド。
+    ///
+    /// ゴールデンイメージとの比較に用いる。
+    pub fn capture(&self) -> Result<graphic::capture::CapturedImage, EError> {
+        self.mngrs.gr_mngr.capture()
+    }
+
+    /// ウィンドウが閉じられた場合と同様に、クライアントを終了させるメソッド。
+    ///
+    /// クライアントの終了メソッドが呼ばれ、以降フレームは進まない。
+    /// 既に終了している場合、何もしない。
+    pub fn close(&mut self) {
+        if self.running {
+            self.running = false;
+            self.client.shutdown(&mut self.mngrs);
+        }
+    }
+
+    /// 1フレーム進めるメソッド。
+    ///
+    /// stepsがSomeの場合、経過時間に関わらずそのステップ数だけシミュレーションを進める。
+    fn advance(&mut self, duration: Duration, steps: Option<u32>) -> bool {
+        if !self.running {
+            return false;
+        }
+
+        self.running = match steps {
+            Some(n) => run_steps(
+                &mut self.mngrs,
+                &mut self.client,
+                &self.timestep,
+                duration,
+                n,
+            ),
+            None => run_frame(
+                &mut self.mngrs,
+                &mut self.client,
+                &mut self.timestep,
+                duration,
+            ),
+        };
+        self.frame += 1;
+        if !self.running {
+            self.client.shutdown(&mut self.mngrs);
+        }
+
+        self.running
+    }
+
+    /// 記録された入力を再生してフレームを進めるメソッド。
+    ///
+    /// 各フレームの経過時間には記録されたものを用いる。
+    /// 記録時と同じタイムステップの設定で再生すること。
+    /// 進めたフレーム数を返す。クライアントが終了を望んだ場合、そこで中断する。
+    pub fn run_replay(&mut self, replay: input::replay::InputReplay) -> Result<usize, EError> {
+        let length = replay.get_frames().len();
+        // NOTE: 記録されたフレームは固定タイムステップのステップ単位であるため、
+        //       蓄積された経過時間に関わらず1フレームにちょうど1ステップ進める。
+        let duration = self.timestep.get_step().unwrap_or_default();
+        self.mngrs.in_mngr.start_replay(replay)?;
+        let mut count = 0;
+        for _ in 0..length {
+            count += 1;
+            if !self.advance(duration, Some(1)) {
+                break;
+            }
+        }
+        self.mngrs.in_mngr.stop_replay();
+        Ok(count)
+    }
+}
+
+// NOTE: スクリプトによる駆動は自動テストでのみ用いる。
+#[cfg(test)]
+impl<T> HeadlessRunner<T>
+where
+    T: ClientHandler,
+{
+    /// 1フレーム進めるメソッド。
+    ///
+    /// 固定タイムステップの場合、durationに応じたステップ数だけシミュレーションが進む。
+    /// クライアントが終了を望んだ場合、クライアントの終了メソッドを呼び、以降は何もせずfalseを返す。
+    pub fn step(&mut self, duration: Duration) -> bool {
+        self.advance(duration, None)
+    }
+
+    /// マネージャオブジェクトの集合を取得するメソッド。
+    pub fn managers(&mut self) -> &mut Managers<'static> {
+        &mut self.mngrs
+    }
+
+    /// クライアントを取得するメソッド。
+    pub fn client(&mut self) -> &mut T {
+        &mut self.client
+    }
+
+    /// クライアントが続行を望んでいるか否かを取得するメソッド。
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// これまでに進めたフレーム数を取得するメソッド。
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// キーを押すメソッド。
+    pub fn press(&mut self, key_code: KeyCode) {
+        self.mngrs.in_mngr.press(key_code);
+    }
+
+    /// キーを離すメソッド。
+    pub fn release(&mut self, key_code: KeyCode) {
+        self.mngrs.in_mngr.release(key_code);
+    }
+
+    /// 押されているキーをkey_codesのみにしてから1フレーム進めるメソッド。
+    pub fn step_with_keys(&mut self, duration: Duration, key_codes: &[KeyCode]) -> bool {
+        for n in self.mngrs.in_mngr.pressed_keys() {
+            if !key_codes.contains(&n) {
+                self.release(n);
+            }
+        }
+        for n in key_codes {
+            self.press(*n);
+        }
+        self.step(duration)
+    }
+
+    /// スクリプトに従ってフレームを進めるメソッド。
+    ///
+    /// スクリプトの各要素は(経過時間, 押されているキー)である。
+    /// 進めたフレーム数を返す。クライアントが終了を望んだ場合、そこで中断する。
+    pub fn run_script<'k, I>(&mut self, script: I) -> usize
+    where
+        I: IntoIterator<Item = (Duration, &'k [KeyCode])>,
+    {
+        let mut count = 0;
+        for (duration, key_codes) in script {
+            count += 1;
+            if !self.step_with_keys(duration, key_codes) {
+                break;
+            }
+        }
+        count
+    }
+}
@@ -1,29 +1,464 @@
-use std::collections::HashMap;
+// NOTE: 定義するだけ定義しておいて使わなくても良いので。
+#![allow(dead_code)]
+
+pub mod action;
+mod button;
+pub mod gamepad;
+pub mod key;
+pub mod replay;
+
+use super::*;
+
+use glam::Vec2;
+use std::collections::{HashMap, HashSet};
 use winit::{
-    event::KeyEvent,
+    event::{Ime, KeyEvent, MouseButton, MouseScrollDelta},
     keyboard::{KeyCode, PhysicalKey},
 };
 
-/// キーボードの入力状態を管理するオブジェクト。
+/// オートリピートが始まるまでの既定の時間。
+pub const DEFAULT_REPEAT_DELAY: Duration = Duration::from_millis(400);
+/// オートリピートの既定の間隔。
+pub const DEFAULT_REPEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// ピクセル単位のホイール入力を行単位に換算する際の1行あたりのピクセル数。
+const PIXELS_PER_WHEEL_LINE: f32 = 20.0;
+
+/// キーボード・マウス・ゲームパッドの入力状態を管理するオブジェクト。
 ///
-/// 入力状態は次の通り：
+/// キー・マウスボタン・ゲームパッドのボタンの入力状態は次の通り：
 /// - 0: 押されていない
 /// - n: nフレーム押されている
+///
+/// また、押されている時間も記録しており、メニューのカーソル移動のような時間ベースのオートリピートに用いる。
+///
+/// 加えて、そのフレームに離されたキーを記録する。
+/// 1フレームの間に押されて離されたキーは、そのフレームは押されているものとして扱い、次のフレームで離されたものとする。
+/// つまり、1フレームより短い入力も取りこぼさない。
+///
+/// ゲームパッドの左スティックの傾きは、上下左右の仮想的なボタン(LeftStickUp等)の入力状態にも変換される。
+///
+/// アクションの入力状態は、割り当てられたキー・ボタンの入力状態のうち最大のものである。
+///
+/// テキスト入力が有効な間は、確定された文字列とIMEの未確定文字列(プリエディット)も収集する。
+///
+/// 与えられた入力はフレームごとにファイルへ記録でき、apply_event()で再生できる。
 pub struct InputManager {
-    /// キーの入力状態を管理するマップ。
-    /// 押されているキーのみ存在する。
-    states: HashMap<KeyCode, u32>,
+    keys: button::ButtonStates<KeyCode>,
+    mouse_buttons: button::ButtonStates<MouseButton>,
+    gamepad: Box<dyn gamepad::GamepadBackend>,
+    gamepad_buttons: button::ButtonStates<gamepad::GamepadButton>,
+    /// スティックの生の値。
+    gamepad_axes: HashMap<gamepad::GamepadAxis, f32>,
+    /// スティックのデッドゾーン。
+    stick_deadzone: f32,
+    /// 左スティックの傾きから変換された仮想的なボタン。
+    stick_direction: Option<gamepad::GamepadButton>,
+    /// ウィンドウ上のカーソルの位置 [px]。
+    /// カーソルがウィンドウ外にある場合、None。
+    cursor: Option<Vec2>,
+    /// このフレームのホイールの回転量 [行]。
+    wheel: Vec2,
+    /// シーンが描画されているウィンドウ上の領域の左上の位置 [px]。
+    viewport_offset: Vec2,
+    /// シーンが描画されているウィンドウ上の領域のサイズ [px]。
+    viewport_size: Vec2,
+    /// シーンのサイズ。
+    scene_size: Vec2,
+    action_map: action::ActionMap,
+    /// オートリピートが始まるまでの時間。
+    repeat_delay: Duration,
+    /// オートリピートの間隔。
+    repeat_interval: Duration,
+    /// テキスト入力が有効か否か。
+    is_text_input_active: bool,
+    /// このフレームに確定された文字列。
+    text: String,
+    /// IMEの未確定文字列。
+    preedit: String,
+    /// 未確定文字列中のカーソルの範囲 [byte]。
+    preedit_cursor: Option<(usize, usize)>,
+    /// IMEの変換候補ウィンドウを表示する領域(キャンバス座標系の位置, サイズ)。
+    ime_cursor_area: Option<(Vec2, Vec2)>,
+    /// 入力を記録している場合、Some。
+    recorder: Option<replay::InputRecorder>,
+    /// 入力を再生している場合、Some(再生中の入力, 再生前のバインディング)。
+    replay: Option<(replay::InputReplay, action::ActionMap)>,
 }
 
 impl InputManager {
     pub fn new() -> Self {
         Self {
-            states: HashMap::new(),
+            keys: button::ButtonStates::new(),
+            mouse_buttons: button::ButtonStates::new(),
+            gamepad: Box::new(gamepad::NullGamepad),
+            gamepad_buttons: button::ButtonStates::new(),
+            gamepad_axes: HashMap::new(),
+            stick_deadzone: gamepad::DEFAULT_STICK_DEADZONE,
+            stick_direction: None,
+            cursor: None,
+            wheel: Vec2::ZERO,
+            viewport_offset: Vec2::ZERO,
+            viewport_size: Vec2::ZERO,
+            scene_size: Vec2::ZERO,
+            action_map: action::ActionMap::new(),
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+            repeat_interval: DEFAULT_REPEAT_INTERVAL,
+            is_text_input_active: false,
+            text: String::new(),
+            preedit: String::new(),
+            preedit_cursor: None,
+            ime_cursor_area: None,
+            recorder: None,
+            replay: None,
         }
     }
 
     pub fn get(&self, key_code: &KeyCode) -> u32 {
-        *self.states.get(key_code).unwrap_or(&0)
+        self.keys.frames(key_code)
+    }
+
+    /// キーがまさにこのフレームに押されたか否かを取得するメソッド。
+    pub fn just_pressed(&self, key_code: &KeyCode) -> bool {
+        self.get(key_code) == 1
+    }
+
+    /// キーがまさにこのフレームに離されたか否かを取得するメソッド。
+    pub fn just_released(&self, key_code: &KeyCode) -> bool {
+        self.keys.just_released(key_code)
+    }
+
+    /// キーがframesフレーム以上押され続けているか否かを取得するメソッド。
+    pub fn held_for(&self, key_code: &KeyCode, frames: u32) -> bool {
+        let state = self.get(key_code);
+        state > 0 && state >= frames
+    }
+
+    /// キーがオートリピートによりこのフレームに入力されたか否かを取得するメソッド。
+    ///
+    /// 押されたフレームにtrueとなり、repeat_delayだけ待った後、repeat_intervalごとにtrueとなる。
+    /// 押されている時間に基づくため、フレームレートに依存しない。
+    pub fn repeated(&self, key_code: &KeyCode) -> bool {
+        self.is_repeated(self.keys.get(key_code))
+    }
+
+    fn is_repeated(&self, state: Option<&button::ButtonState>) -> bool {
+        let Some(state) = state else {
+            return false;
+        };
+        state.frames == 1 || self.repeat_count(state.time) > self.repeat_count(state.last_time)
+    }
+
+    /// 押されている時間tまでにオートリピートした回数を求めるメソッド。
+    fn repeat_count(&self, t: Duration) -> u32 {
+        if t < self.repeat_delay {
+            return 0;
+        }
+        if self.repeat_interval.is_zero() {
+            return 1;
+        }
+        1 + ((t - self.repeat_delay).as_nanos() / self.repeat_interval.as_nanos()) as u32
+    }
+
+    pub fn get_repeat_delay(&self) -> Duration {
+        self.repeat_delay
+    }
+    pub fn get_repeat_interval(&self) -> Duration {
+        self.repeat_interval
+    }
+    pub fn set_repeat_delay(&mut self, delay: Duration) {
+        self.repeat_delay = delay;
+    }
+    pub fn set_repeat_interval(&mut self, interval: Duration) {
+        self.repeat_interval = interval;
+    }
+
+    /// ゲームパッドのボタンの入力状態を取得するメソッド。
+    pub fn get_gamepad(&self, button: &gamepad::GamepadButton) -> u32 {
+        self.gamepad_buttons.frames(button)
+    }
+
+    /// ゲームパッドのボタンがまさにこのフレームに押されたか否かを取得するメソッド。
+    pub fn gamepad_just_pressed(&self, button: &gamepad::GamepadButton) -> bool {
+        self.get_gamepad(button) == 1
+    }
+
+    /// ゲームパッドのボタンがまさにこのフレームに離されたか否かを取得するメソッド。
+    pub fn gamepad_just_released(&self, button: &gamepad::GamepadButton) -> bool {
+        self.gamepad_buttons.just_released(button)
+    }
+
+    /// ゲームパッドのボタンがframesフレーム以上押され続けているか否かを取得するメソッド。
+    pub fn gamepad_held_for(&self, button: &gamepad::GamepadButton, frames: u32) -> bool {
+        let state = self.get_gamepad(button);
+        state > 0 && state >= frames
+    }
+
+    /// ゲームパッドのボタンがオートリピートによりこのフレームに入力されたか否かを取得するメソッド。
+    pub fn gamepad_repeated(&self, button: &gamepad::GamepadButton) -> bool {
+        self.is_repeated(self.gamepad_buttons.get(button))
+    }
+
+    /// スティックの傾きを取得するメソッド。
+    ///
+    /// デッドゾーンが適用されており、大きさは[0, 1]である。Yは上が正。
+    pub fn get_stick(&self, stick: gamepad::GamepadStick) -> Vec2 {
+        let (x, y) = match stick {
+            gamepad::GamepadStick::Left => (
+                gamepad::GamepadAxis::LeftStickX,
+                gamepad::GamepadAxis::LeftStickY,
+            ),
+            gamepad::GamepadStick::Right => (
+                gamepad::GamepadAxis::RightStickX,
+                gamepad::GamepadAxis::RightStickY,
+            ),
+        };
+        let raw = Vec2::new(
+            *self.gamepad_axes.get(&x).unwrap_or(&0.0),
+            *self.gamepad_axes.get(&y).unwrap_or(&0.0),
+        );
+        gamepad::apply_deadzone(raw, self.stick_deadzone)
+    }
+
+    pub fn get_stick_deadzone(&self) -> f32 {
+        self.stick_deadzone
+    }
+    pub fn set_stick_deadzone(&mut self, deadzone: f32) {
+        self.stick_deadzone = deadzone.clamp(0.0, 1.0);
+    }
+
+    /// ゲームパッドのバックエンドを差し替えるメソッド。
+    ///
+    /// テストでは仮想的なゲームパッド(gamepad::VirtualGamepad)を用いる。
+    pub fn set_gamepad(&mut self, backend: Box<dyn gamepad::GamepadBackend>) {
+        self.gamepad = backend;
+    }
+
+    /// ゲームパッドのイベントを取り込むメソッド。
+    ///
+    /// 毎フレーム、クライアントの更新前に呼ばれる。
+    /// 入力を再生している間は、実際のゲームパッドのイベントは捨てられる。
+    pub fn update_gamepad(&mut self) {
+        for event in self.gamepad.poll() {
+            if self.replay.is_none() {
+                self.input_gamepad(event);
+            }
+        }
+
+        // 左スティックの傾きを仮想的なボタンに反映
+        let direction = gamepad::stick_to_direction(self.get_stick(gamepad::GamepadStick::Left));
+        if direction != self.stick_direction {
+            if let Some(n) = self.stick_direction {
+                self.gamepad_buttons.release(n);
+            }
+            if let Some(n) = direction {
+                self.gamepad_buttons.press(n);
+            }
+            self.stick_direction = direction;
+        }
+    }
+
+    /// ゲームパッドのイベントを反映するメソッド。
+    pub fn input_gamepad(&mut self, event: gamepad::GamepadEvent) {
+        self.record(replay::InputEvent::Gamepad(event));
+        match event {
+            gamepad::GamepadEvent::Pressed(n) => self.gamepad_buttons.press(n),
+            gamepad::GamepadEvent::Released(n) => self.gamepad_buttons.release(n),
+            gamepad::GamepadEvent::AxisChanged(n, value) => {
+                self.gamepad_axes.insert(n, value.clamp(-1.0, 1.0));
+            }
+            gamepad::GamepadEvent::Disconnected => {
+                for n in self.gamepad_buttons.pressed() {
+                    self.gamepad_buttons.release(n);
+                }
+                self.gamepad_axes.clear();
+            }
+        }
+    }
+
+    fn get_binding_state(&self, binding: &action::Binding) -> Option<&button::ButtonState> {
+        match binding {
+            action::Binding::Key(n) => self.keys.get(n),
+            action::Binding::Gamepad(n) => self.gamepad_buttons.get(n),
+        }
+    }
+
+    fn binding_just_released(&self, binding: &action::Binding) -> bool {
+        match binding {
+            action::Binding::Key(n) => self.keys.just_released(n),
+            action::Binding::Gamepad(n) => self.gamepad_buttons.just_released(n),
+        }
+    }
+
+    /// アクションの入力状態を取得するメソッド。
+    pub fn get_action(&self, action: action::Action) -> u32 {
+        self.action_map
+            .get_bindings(action)
+            .iter()
+            .filter_map(|n| self.get_binding_state(n))
+            .map(|n| n.frames)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// アクションがまさにこのフレームに押されたか否かを取得するメソッド。
+    pub fn action_just_pressed(&self, action: action::Action) -> bool {
+        self.get_action(action) == 1
+    }
+
+    /// アクションがまさにこのフレームに離されたか否かを取得するメソッド。
+    ///
+    /// 割り当てられた入力のいずれかがこのフレームに離され、かつ他のいずれも押されていない場合にtrueとなる。
+    pub fn action_just_released(&self, action: action::Action) -> bool {
+        let bindings = self.action_map.get_bindings(action);
+        self.get_action(action) == 0 && bindings.iter().any(|n| self.binding_just_released(n))
+    }
+
+    /// アクションがオートリピートによりこのフレームに入力されたか否かを取得するメソッド。
+    ///
+    /// 割り当てられた入力のうち最も長く押されているものに基づく。
+    pub fn action_repeated(&self, action: action::Action) -> bool {
+        let state = self
+            .action_map
+            .get_bindings(action)
+            .iter()
+            .filter_map(|n| self.get_binding_state(n))
+            .max_by_key(|n| n.frames);
+        self.is_repeated(state)
+    }
+
+    /// アクションがframesフレーム以上押され続けているか否かを取得するメソッド。
+    pub fn action_held_for(&self, action: action::Action, frames: u32) -> bool {
+        let state = self.get_action(action);
+        state > 0 && state >= frames
+    }
+
+    pub fn get_action_map(&self) -> &action::ActionMap {
+        &self.action_map
+    }
+
+    /// アクションとキーの対応を取得するメソッド。
+    ///
+    /// 実行時にキーを割り当て直す場合に用いる。
+    pub fn get_action_map_mut(&mut self) -> &mut action::ActionMap {
+        &mut self.action_map
+    }
+
+    pub fn get_mouse(&self, button: &MouseButton) -> u32 {
+        self.mouse_buttons.frames(button)
+    }
+
+    /// マウスボタンがまさにこのフレームに押されたか否かを取得するメソッド。
+    pub fn mouse_just_pressed(&self, button: &MouseButton) -> bool {
+        self.get_mouse(button) == 1
+    }
+
+    /// マウスボタンがまさにこのフレームに離されたか否かを取得するメソッド。
+    pub fn mouse_just_released(&self, button: &MouseButton) -> bool {
+        self.mouse_buttons.just_released(button)
+    }
+
+    /// このフレームのホイールの回転量 [行] を取得するメソッド。
+    ///
+    /// yは上方向(奥方向)への回転が正。
+    pub fn get_wheel(&self) -> Vec2 {
+        self.wheel
+    }
+
+    /// カーソルの位置をキャンバス座標系で取得するメソッド。
+    ///
+    /// カーソルがシーンの描画領域外にある場合、Noneを返す。
+    pub fn get_cursor_canvas(&self) -> Option<Vec2> {
+        let cursor = self.cursor?;
+        if self.viewport_size.x <= 0.0 || self.viewport_size.y <= 0.0 {
+            return None;
+        }
+        let pos = (cursor - self.viewport_offset) / self.viewport_size * self.scene_size;
+        let is_inside =
+            pos.x >= 0.0 && pos.y >= 0.0 && pos.x < self.scene_size.x && pos.y < self.scene_size.y;
+        is_inside.then_some(pos)
+    }
+
+    /// カーソルの位置をワールド座標系で取得するメソッド。
+    ///
+    /// カーソルがシーンの描画領域外にある場合、Noneを返す。
+    /// WARN: カメラの位置は考慮されない。
+    pub fn get_cursor_world(&self) -> Option<Vec2> {
+        let pos = self.get_cursor_canvas()?;
+        Some(Vec2::new(
+            pos.x - self.scene_size.x / 2.0,
+            self.scene_size.y / 2.0 - pos.y,
+        ))
+    }
+
+    /// テキスト入力を開始するメソッド。
+    ///
+    /// ウィンドウのIMEが有効になる。
+    /// WARN: テキスト入力中もキー・アクションの入力状態は更新される。
+    pub fn start_text_input(&mut self) {
+        self.is_text_input_active = true;
+    }
+
+    /// テキスト入力を終了するメソッド。
+    ///
+    /// ウィンドウのIMEが無効になり、未確定文字列は破棄される。
+    pub fn stop_text_input(&mut self) {
+        self.is_text_input_active = false;
+        self.text.clear();
+        self.preedit.clear();
+        self.preedit_cursor = None;
+    }
+
+    pub fn is_text_input_active(&self) -> bool {
+        self.is_text_input_active
+    }
+
+    /// このフレームに確定された文字列を取得するメソッド。
+    ///
+    /// 制御文字は含まれない。
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// IMEの未確定文字列を取得するメソッド。
+    pub fn get_preedit(&self) -> &str {
+        &self.preedit
+    }
+
+    /// IMEの未確定文字列中のカーソルの範囲 [byte] を取得するメソッド。
+    ///
+    /// カーソルを表示すべきでない場合、Noneを返す。
+    pub fn get_preedit_cursor(&self) -> Option<(usize, usize)> {
+        self.preedit_cursor
+    }
+
+    /// IMEの変換候補ウィンドウを表示する領域を設定するメソッド。
+    ///
+    /// posは領域の左上の位置、sizeは領域のサイズであり、いずれもキャンバス座標系である。
+    /// 変換候補ウィンドウは入力中の文字列を隠さないように配置される。
+    pub fn set_ime_cursor_area(&mut self, pos: Vec2, size: Vec2) {
+        self.ime_cursor_area = Some((pos, size));
+    }
+
+    /// IMEの変換候補ウィンドウを表示する領域をウィンドウ上の位置・サイズ [px] で取得するメソッド。
+    pub fn get_ime_cursor_area(&self) -> Option<(Vec2, Vec2)> {
+        if self.scene_size.x <= 0.0 || self.scene_size.y <= 0.0 {
+            return None;
+        }
+        let (pos, size) = self.ime_cursor_area?;
+        let scale = self.viewport_size / self.scene_size;
+        Some((self.viewport_offset + pos * scale, size * scale))
+    }
+
+    /// シーンが描画されているウィンドウ上の領域を設定するメソッド。
+    ///
+    /// カーソルの位置をシーン上の座標に変換するために用いる。
+    /// offset・sizeはウィンドウ上の位置・サイズ [px]、scene_sizeはシーンのサイズである。
+    pub fn set_viewport(&mut self, offset: Vec2, size: Vec2, scene_size: Vec2) {
+        self.record(replay::InputEvent::Viewport(offset, size, scene_size));
+        self.viewport_offset = offset;
+        self.viewport_size = size;
+        self.scene_size = scene_size;
     }
 
     pub fn on_key_event_happened(&mut self, event: KeyEvent) {
@@ -32,17 +467,351 @@ impl InputManager {
             _ => return,
         };
         if event.state.is_pressed() {
-            if !self.states.contains_key(&key_code) {
-                self.states.insert(key_code, 1);
+            self.press(key_code);
+            if let Some(text) = &event.text {
+                self.input_text(text);
             }
         } else {
-            self.states.remove(&key_code);
+            self.release(key_code);
         }
     }
 
-    pub fn go_next(&mut self) {
-        for n in self.states.values_mut() {
-            *n += 1;
+    pub fn on_ime_happened(&mut self, ime: Ime) {
+        if !self.is_text_input_active {
+            return;
+        }
+        match ime {
+            Ime::Preedit(text, cursor) => self.input_preedit(text, cursor),
+            Ime::Commit(text) => self.input_text(&text),
+            Ime::Enabled => (),
+            Ime::Disabled => self.input_preedit(String::new(), None),
         }
     }
+
+    pub fn on_mouse_wheel_happened(&mut self, delta: MouseScrollDelta) {
+        let delta = match delta {
+            MouseScrollDelta::LineDelta(x, y) => Vec2::new(x, y),
+            MouseScrollDelta::PixelDelta(n) => {
+                Vec2::new(n.x as f32, n.y as f32) / PIXELS_PER_WHEEL_LINE
+            }
+        };
+        self.scroll(delta);
+    }
+
+    /// 押されているすべてのキーを取得するメソッド。
+    ///
+    /// 次のフレームで離されるキーは含まない。
+    pub fn pressed_keys(&self) -> Vec<KeyCode> {
+        self.keys.pressed()
+    }
+
+    /// キーを押すメソッド。
+    ///
+    /// 既に押されている場合、無視される。
+    pub fn press(&mut self, key_code: KeyCode) {
+        self.record(replay::InputEvent::Press(key_code));
+        self.keys.press(key_code);
+    }
+
+    /// キーを離すメソッド。
+    ///
+    /// まさにこのフレームに押されたキーの場合、次のフレームで離される。
+    pub fn release(&mut self, key_code: KeyCode) {
+        self.record(replay::InputEvent::Release(key_code));
+        self.keys.release(key_code);
+    }
+
+    /// マウスボタンを押すメソッド。
+    pub fn press_mouse(&mut self, button: MouseButton) {
+        self.record(replay::InputEvent::PressMouse(button));
+        self.mouse_buttons.press(button);
+    }
+
+    /// マウスボタンを離すメソッド。
+    pub fn release_mouse(&mut self, button: MouseButton) {
+        self.record(replay::InputEvent::ReleaseMouse(button));
+        self.mouse_buttons.release(button);
+    }
+
+    /// カーソルを移動するメソッド。
+    ///
+    /// posはウィンドウ上の位置 [px] であり、カーソルがウィンドウ外に出た場合はNoneである。
+    pub fn move_cursor(&mut self, pos: Option<Vec2>) {
+        self.record(replay::InputEvent::MoveCursor(pos));
+        self.cursor = pos;
+    }
+
+    /// 文字列を入力するメソッド。
+    ///
+    /// テキスト入力が無効の場合、無視される。
+    /// 制御文字は取り除かれる。
+    pub fn input_text(&mut self, text: &str) {
+        self.record(replay::InputEvent::Text(text.to_string()));
+        if self.is_text_input_active {
+            self.text.extend(text.chars().filter(|c| !c.is_control()));
+        }
+    }
+
+    /// IMEの未確定文字列を設定するメソッド。
+    ///
+    /// テキスト入力が無効の場合、無視される。
+    pub fn input_preedit(&mut self, text: String, cursor: Option<(usize, usize)>) {
+        self.record(replay::InputEvent::Preedit(text.clone(), cursor));
+        if self.is_text_input_active {
+            self.preedit = text;
+            self.preedit_cursor = cursor;
+        }
+    }
+
+    /// ホイールを回転させるメソッド。
+    pub fn scroll(&mut self, delta: Vec2) {
+        self.record(replay::InputEvent::Scroll(delta));
+        self.wheel += delta;
+    }
+
+    /// 記録された入力を反映するメソッド。
+    ///
+    /// 再生中も記録していれば、再び記録される。
+    pub fn apply_event(&mut self, event: replay::InputEvent) {
+        match event {
+            replay::InputEvent::Press(n) => self.press(n),
+            replay::InputEvent::Release(n) => self.release(n),
+            replay::InputEvent::PressMouse(n) => self.press_mouse(n),
+            replay::InputEvent::ReleaseMouse(n) => self.release_mouse(n),
+            replay::InputEvent::MoveCursor(n) => self.move_cursor(n),
+            replay::InputEvent::Scroll(n) => self.scroll(n),
+            replay::InputEvent::Text(n) => self.input_text(&n),
+            replay::InputEvent::Preedit(text, cursor) => self.input_preedit(text, cursor),
+            replay::InputEvent::Gamepad(n) => self.input_gamepad(n),
+            replay::InputEvent::Viewport(offset, size, scene_size) => {
+                self.set_viewport(offset, size, scene_size)
+            }
+        }
+    }
+
+    /// 入力の記録を開始するメソッド。
+    ///
+    /// 以降、go_next()が呼ばれるごとに、そのフレームの経過時間と入力がファイルに書き出される。
+    /// 既に記録している場合、以前の記録は終了する。
+    pub fn start_recording<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<(), EError> {
+        let bindings = self.action_map.to_text();
+        self.recorder = Some(replay::InputRecorder::create(path, &bindings)?);
+        Ok(())
+    }
+
+    /// 入力の記録を終了するメソッド。
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// 入力の再生を開始するメソッド。
+    ///
+    /// 再生が終わるまで、バインディングは記録開始時のものとなる。
+    pub fn start_replay(&mut self, replay: replay::InputReplay) -> Result<(), EError> {
+        let mut action_map = action::ActionMap::new();
+        action_map.parse(replay.get_bindings())?;
+        self.stop_replay();
+        let live_action_map = std::mem::replace(&mut self.action_map, action_map);
+        self.replay = Some((replay, live_action_map));
+        Ok(())
+    }
+
+    /// 入力の再生を終了するメソッド。
+    ///
+    /// バインディングは再生前のものに戻る。
+    pub fn stop_replay(&mut self) {
+        if let Some((_, live_action_map)) = self.replay.take() {
+            self.action_map = live_action_map;
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// 再生中の入力を1フレーム分反映するメソッド。
+    ///
+    /// 毎フレーム、クライアントの更新前に呼ばれ、記録されたフレームの経過時間を返す。
+    /// 呼び出し側は実際の経過時間の代わりにこれを用いること。
+    /// 再生していない・すべてのフレームを再生し終えた場合、再生を終了してNoneを返す。
+    pub fn replay_frame(&mut self) -> Option<Duration> {
+        let frame = self
+            .replay
+            .as_mut()
+            .and_then(|(n, _)| n.next_frame())
+            .cloned();
+        let Some(frame) = frame else {
+            self.stop_replay();
+            return None;
+        };
+        for event in frame.events {
+            self.apply_event(event);
+        }
+        Some(frame.duration)
+    }
+
+    fn record(&mut self, event: replay::InputEvent) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.push(event);
+        }
+    }
+
+    /// 次のフレームに進めるメソッド。
+    ///
+    /// durationは押されている時間に加算される。
+    pub fn go_next(&mut self, duration: Duration) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.end_frame(duration) {
+                eprintln!("failed to record inputs: {e}");
+                self.recorder = None;
+            }
+        }
+        self.keys.go_next(duration);
+        self.mouse_buttons.go_next(duration);
+        self.gamepad_buttons.go_next(duration);
+        self.wheel = Vec2::ZERO;
+        self.text.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// stepごとにフレームを進めながらキーを押し続け、オートリピートした時刻を返す関数。
+    fn collect_repeats(step: Duration, until: Duration) -> Vec<Duration> {
+        let mut in_mngr = InputManager::new();
+        in_mngr.press(KeyCode::KeyZ);
+        let mut times = Vec::new();
+        let mut elapsed = Duration::ZERO;
+        while elapsed <= until {
+            if in_mngr.repeated(&KeyCode::KeyZ) {
+                times.push(elapsed);
+            }
+            in_mngr.go_next(step);
+            elapsed += step;
+        }
+        times
+    }
+
+    #[test]
+    fn repeats_independently_of_frame_rate() {
+        let until = Duration::from_secs(1);
+        let at_60 = collect_repeats(Duration::from_secs(1) / 60, until);
+        let at_144 = collect_repeats(Duration::from_secs(1) / 144, until);
+
+        // 押した直後・400ms後から100msごと
+        for (secs, count) in [(0.0, 1), (0.39, 1), (0.45, 2), (0.75, 5), (0.95, 7)] {
+            let t = Duration::from_secs_f32(secs);
+            let count_60 = at_60.iter().filter(|n| **n <= t).count();
+            let count_144 = at_144.iter().filter(|n| **n <= t).count();
+            assert_eq!((count_60, count_144), (count, count), "at {secs}s");
+        }
+
+        // 各リピートは間隔の境界から1フレーム以内に起こる
+        for (times, step) in [(&at_60, 1.0 / 60.0), (&at_144, 1.0 / 144.0)] {
+            for (i, n) in times.iter().enumerate().skip(1) {
+                let expected = 0.4 + 0.1 * (i - 1) as f32;
+                let t = n.as_secs_f32();
+                assert!(t >= expected - 1e-4 && t < expected + step + 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn repeats_once_per_frame_at_low_frame_rates() {
+        // 1フレームが間隔より長くても、そのフレームに1回だけtrueとなる
+        let times = collect_repeats(Duration::from_millis(250), Duration::from_secs(1));
+        let millis = times.iter().map(|n| n.as_millis()).collect::<Vec<_>>();
+        assert_eq!(millis, [0, 500, 750, 1000]);
+    }
+
+    /// ウィンドウの大きさwindowにシーンを収めた描画領域を設定したInputManagerを作成する関数。
+    fn create_with_viewport(window: Vec2, mode: graphic::ScalingMode) -> InputManager {
+        let viewport = graphic::Viewport::fit(window.x as u32, window.y as u32, 1280, 720, mode);
+        let mut in_mngr = InputManager::new();
+        in_mngr.set_viewport(
+            Vec2::new(viewport.x, viewport.y),
+            Vec2::new(viewport.width, viewport.height),
+            Vec2::new(1280.0, 720.0),
+        );
+        in_mngr
+    }
+
+    fn cursor_canvas(in_mngr: &mut InputManager, pos: Vec2) -> Option<Vec2> {
+        in_mngr.move_cursor(Some(pos));
+        in_mngr.get_cursor_canvas()
+    }
+
+    #[test]
+    fn maps_cursor_through_letterbox() {
+        // 1920x1200のウィンドウでは上下に60pxの帯ができ、1.5倍に拡大される
+        let mut in_mngr =
+            create_with_viewport(Vec2::new(1920.0, 1200.0), graphic::ScalingMode::Fit);
+        assert_eq!(in_mngr.get_cursor_canvas(), None);
+        assert_eq!(
+            cursor_canvas(&mut in_mngr, Vec2::new(960.0, 600.0)),
+            Some(Vec2::new(640.0, 360.0))
+        );
+        assert_eq!(in_mngr.get_cursor_world(), Some(Vec2::ZERO));
+        assert_eq!(
+            cursor_canvas(&mut in_mngr, Vec2::new(0.0, 60.0)),
+            Some(Vec2::ZERO)
+        );
+        assert_eq!(in_mngr.get_cursor_world(), Some(Vec2::new(-640.0, 360.0)));
+        assert_eq!(
+            cursor_canvas(&mut in_mngr, Vec2::new(1500.0, 1110.0)),
+            Some(Vec2::new(1000.0, 700.0))
+        );
+
+        // 帯の上・描画領域の右端と下端は領域外
+        for pos in [
+            Vec2::new(960.0, 30.0),
+            Vec2::new(960.0, 59.0),
+            Vec2::new(960.0, 1140.0),
+            Vec2::new(960.0, 1170.0),
+            Vec2::new(1920.0, 600.0),
+        ] {
+            assert_eq!(cursor_canvas(&mut in_mngr, pos), None, "{pos}");
+            assert_eq!(in_mngr.get_cursor_world(), None);
+        }
+
+        in_mngr.move_cursor(None);
+        assert_eq!(in_mngr.get_cursor_canvas(), None);
+    }
+
+    #[test]
+    fn maps_cursor_through_pillarbox() {
+        // 2560x1080のウィンドウでは左右に320pxの帯ができる
+        let mut in_mngr =
+            create_with_viewport(Vec2::new(2560.0, 1080.0), graphic::ScalingMode::Fit);
+        assert_eq!(cursor_canvas(&mut in_mngr, Vec2::new(100.0, 540.0)), None);
+        assert_eq!(cursor_canvas(&mut in_mngr, Vec2::new(2300.0, 540.0)), None);
+        assert_eq!(
+            cursor_canvas(&mut in_mngr, Vec2::new(1280.0, 540.0)),
+            Some(Vec2::new(640.0, 360.0))
+        );
+        assert_eq!(
+            cursor_canvas(&mut in_mngr, Vec2::new(320.0, 0.0)),
+            Some(Vec2::ZERO)
+        );
+
+        // 整数倍の拡大では余白が四方にできる
+        let mut in_mngr =
+            create_with_viewport(Vec2::new(1600.0, 900.0), graphic::ScalingMode::Integer);
+        assert_eq!(cursor_canvas(&mut in_mngr, Vec2::new(100.0, 450.0)), None);
+        assert_eq!(cursor_canvas(&mut in_mngr, Vec2::new(800.0, 50.0)), None);
+        assert_eq!(
+            cursor_canvas(&mut in_mngr, Vec2::new(160.0, 90.0)),
+            Some(Vec2::ZERO)
+        );
+        assert_eq!(
+            cursor_canvas(&mut in_mngr, Vec2::new(800.0, 450.0)),
+            Some(Vec2::new(640.0, 360.0))
+        );
+    }
 }
@@ -0,0 +1,323 @@
+use super::*;
+
+use std::{fs, path::Path};
+
+/// キー割り当ての設定ファイルのパス。
+pub const CONFIG_PATH: &str = "input.cfg";
+
+/// 論理的な入力(アクション)。
+///
+/// クライアントは物理的なキーではなくアクションで入力を扱う。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Confirm,
+    Cancel,
+    Menu,
+    Up,
+    Down,
+    Left,
+    Right,
+    /// メッセージのオートモードの切り替え。
+    Auto,
+    /// メッセージのスキップモードの切り替え。
+    Skip,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Confirm,
+        Action::Cancel,
+        Action::Menu,
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Auto,
+        Action::Skip,
+    ];
+
+    /// 設定ファイル上の名前を取得するメソッド。
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Confirm => "Confirm",
+            Action::Cancel => "Cancel",
+            Action::Menu => "Menu",
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Auto => "Auto",
+            Action::Skip => "Skip",
+        }
+    }
+
+    /// 設定ファイル上の名前からアクションを取得するメソッド。
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|n| n.name() == name)
+    }
+}
+
+/// アクションに割り当てられる物理的な入力。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Gamepad(gamepad::GamepadButton),
+}
+
+impl Binding {
+    /// 設定ファイル上の名前を取得するメソッド。
+    ///
+    /// キーはwinitのKeyCodeのバリアント名、ゲームパッドのボタンはPadを前置したバリアント名である。
+    /// 設定ファイルに書けない場合、Noneを返す。
+    pub fn name(&self) -> Option<String> {
+        match self {
+            Binding::Key(n) if key::ALL_KEYS.contains(n) => Some(key::get_name(*n)),
+            Binding::Key(_) => None,
+            Binding::Gamepad(n) => Some(format!("Pad{n:?}")),
+        }
+    }
+
+    /// 設定ファイル上の名前から入力を取得するメソッド。
+    pub fn from_name(name: &str) -> Option<Self> {
+        let keys = key::ALL_KEYS.into_iter().map(Binding::Key);
+        let buttons = gamepad::GamepadButton::ALL
+            .into_iter()
+            .map(Binding::Gamepad);
+        keys.chain(buttons)
+            .find(|n| n.name().as_deref() == Some(name))
+    }
+}
+
+impl From<KeyCode> for Binding {
+    fn from(key_code: KeyCode) -> Self {
+        Binding::Key(key_code)
+    }
+}
+
+impl From<gamepad::GamepadButton> for Binding {
+    fn from(button: gamepad::GamepadButton) -> Self {
+        Binding::Gamepad(button)
+    }
+}
+
+/// アクションと入力の対応(バインディング)を管理するオブジェクト。
+///
+/// 1つのアクションに複数のキー・ゲームパッドのボタンを割り当てられ、1つの入力を複数のアクションに割り当てることもできる。
+///
+/// 設定ファイルは次のような1行1アクションのテキストである(#以降はコメント)：
+/// ```text
+/// Confirm = KeyZ Enter Space PadSouth
+/// Cancel = KeyX Escape PadEast
+/// ```
+pub struct ActionMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+}
+
+impl ActionMap {
+    /// 既定のバインディングでActionMapを作成するメソッド。
+    pub fn new() -> Self {
+        let mut action_map = Self {
+            bindings: HashMap::new(),
+        };
+        action_map.reset();
+        action_map
+    }
+
+    /// 既定のバインディングに戻すメソッド。
+    pub fn reset(&mut self) {
+        use gamepad::GamepadButton;
+
+        self.bindings.clear();
+        self.bind(Action::Confirm, KeyCode::KeyZ);
+        self.bind(Action::Confirm, KeyCode::Enter);
+        self.bind(Action::Confirm, KeyCode::Space);
+        self.bind(Action::Confirm, GamepadButton::South);
+        self.bind(Action::Cancel, KeyCode::KeyX);
+        self.bind(Action::Cancel, KeyCode::Escape);
+        self.bind(Action::Cancel, GamepadButton::East);
+        self.bind(Action::Menu, KeyCode::KeyC);
+        self.bind(Action::Menu, GamepadButton::North);
+        self.bind(Action::Menu, GamepadButton::Start);
+        self.bind(Action::Up, KeyCode::ArrowUp);
+        self.bind(Action::Up, GamepadButton::DPadUp);
+        self.bind(Action::Up, GamepadButton::LeftStickUp);
+        self.bind(Action::Down, KeyCode::ArrowDown);
+        self.bind(Action::Down, GamepadButton::DPadDown);
+        self.bind(Action::Down, GamepadButton::LeftStickDown);
+        self.bind(Action::Left, KeyCode::ArrowLeft);
+        self.bind(Action::Left, GamepadButton::DPadLeft);
+        self.bind(Action::Left, GamepadButton::LeftStickLeft);
+        self.bind(Action::Right, KeyCode::ArrowRight);
+        self.bind(Action::Right, GamepadButton::DPadRight);
+        self.bind(Action::Right, GamepadButton::LeftStickRight);
+        self.bind(Action::Auto, KeyCode::KeyA);
+        self.bind(Action::Auto, GamepadButton::LeftShoulder);
+        self.bind(Action::Skip, KeyCode::KeyS);
+        self.bind(Action::Skip, GamepadButton::RightShoulder);
+    }
+
+    /// アクションに入力を割り当てるメソッド。
+    ///
+    /// 既に割り当てられている場合、無視される。
+    pub fn bind<B: Into<Binding>>(&mut self, action: Action, binding: B) {
+        let binding = binding.into();
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// アクションから入力の割り当てを外すメソッド。
+    pub fn unbind<B: Into<Binding>>(&mut self, action: Action, binding: B) {
+        let binding = binding.into();
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|n| *n != binding);
+        }
+    }
+
+    /// アクションのすべての入力の割り当てを外すメソッド。
+    pub fn clear(&mut self, action: Action) {
+        self.bindings.remove(&action);
+    }
+
+    /// アクションに割り当てられている入力を取得するメソッド。
+    pub fn get_bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |n| n.as_slice())
+    }
+
+    /// 設定ファイルからバインディングを読み込むメソッド。
+    ///
+    /// 設定ファイルに書かれたアクションのバインディングのみ置き換えられる。
+    /// WARN: 不正な行がある場合、何も置き換えずにエラーを返す。
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), EError> {
+        self.parse(&fs::read_to_string(path)?)
+    }
+
+    /// 設定ファイルの内容からバインディングを読み込むメソッド。
+    ///
+    /// 書式はload()と同じである。
+    pub fn parse(&mut self, text: &str) -> Result<(), EError> {
+        let mut bindings = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, inputs) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: '=' is expected.", i + 1))?;
+            let action = Action::from_name(name.trim())
+                .ok_or_else(|| format!("line {}: unknown action '{}'.", i + 1, name.trim()))?;
+            let inputs = inputs
+                .split_whitespace()
+                .map(|n| {
+                    Binding::from_name(n)
+                        .ok_or_else(|| format!("line {}: unknown input '{n}'.", i + 1))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            bindings.push((action, inputs));
+        }
+
+        for (action, inputs) in bindings {
+            self.clear(action);
+            for binding in inputs {
+                self.bind(action, binding);
+            }
+        }
+        Ok(())
+    }
+
+    /// 設定ファイルにバインディングを書き込むメソッド。
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), EError> {
+        fs::write(path, self.to_text())?;
+        Ok(())
+    }
+
+    /// バインディングを設定ファイルの内容に変換するメソッド。
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for action in Action::ALL {
+            let names = self
+                .get_bindings(action)
+                .iter()
+                .filter_map(|n| n.name())
+                .collect::<Vec<_>>();
+            text += &format!("{} = {}\n", action.name(), names.join(" "));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_all_keys() {
+        // 設定ファイルを経由しても、すべてのキー・ボタンの割り当てが保たれる
+        let mut action_map = ActionMap::new();
+        for n in key::ALL_KEYS {
+            action_map.bind(Action::Menu, n);
+        }
+        for n in gamepad::GamepadButton::ALL {
+            action_map.bind(Action::Cancel, n);
+        }
+        let text = action_map.to_text();
+
+        let mut parsed = ActionMap::new();
+        parsed.parse(&text).unwrap();
+        for action in Action::ALL {
+            assert_eq!(parsed.get_bindings(action), action_map.get_bindings(action));
+        }
+        assert!(text.contains(" NumpadMultiply ") && text.contains(" F35"));
+    }
+
+    #[test]
+    fn parses_only_written_actions() {
+        let mut action_map = ActionMap::new();
+        action_map
+            .parse("# コメント\n\nConfirm = KeyA PadWest # 行末のコメント\nCancel =\n")
+            .unwrap();
+        assert_eq!(
+            action_map.get_bindings(Action::Confirm),
+            [
+                Binding::Key(KeyCode::KeyA),
+                Binding::Gamepad(gamepad::GamepadButton::West)
+            ]
+        );
+        assert!(action_map.get_bindings(Action::Cancel).is_empty());
+        assert_eq!(
+            action_map.get_bindings(Action::Menu),
+            ActionMap::new().get_bindings(Action::Menu)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_lines_atomically() {
+        for text in [
+            "Confirm = KeyA\nJump = Space\n",
+            "Confirm = KeyA\nCancel = Key1\n",
+            "Confirm = KeyA\nCancel\n",
+        ] {
+            let mut action_map = ActionMap::new();
+            assert!(action_map.parse(text).is_err());
+            assert_eq!(
+                action_map.get_bindings(Action::Confirm),
+                ActionMap::new().get_bindings(Action::Confirm)
+            );
+        }
+    }
+
+    #[test]
+    fn binds_and_unbinds() {
+        let mut action_map = ActionMap::new();
+        action_map.clear(Action::Up);
+        action_map.bind(Action::Up, KeyCode::KeyW);
+        action_map.bind(Action::Up, KeyCode::KeyW);
+        assert_eq!(action_map.get_bindings(Action::Up), [KeyCode::KeyW.into()]);
+        action_map.unbind(Action::Up, KeyCode::KeyW);
+        assert!(action_map.get_bindings(Action::Up).is_empty());
+        action_map.reset();
+        assert!(!action_map.get_bindings(Action::Up).is_empty());
+    }
+}
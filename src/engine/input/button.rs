@@ -0,0 +1,166 @@
+use super::*;
+
+use std::hash::Hash;
+
+/// 押されているボタン1つ分の入力状態。
+pub struct ButtonState {
+    /// 押されているフレーム数。
+    pub frames: u32,
+    /// 押されている時間。
+    pub time: Duration,
+    /// 前フレームにおけるtime。
+    pub last_time: Duration,
+}
+
+/// キー・マウスボタン等のボタンの入力状態の集合。
+///
+/// 1フレームの間に押されて離されたボタンは、そのフレームは押されているものとして扱い、次のフレームで離されたものとする。
+pub struct ButtonStates<T> {
+    /// 押されているボタンのみ存在する。
+    states: HashMap<T, ButtonState>,
+    /// このフレームに離されたボタンの集合。
+    released: HashSet<T>,
+    /// 押されたフレームのうちに離されたため、次のフレームで離すボタンの集合。
+    pending_releases: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> ButtonStates<T> {
+    pub fn new() -> Self {
+        Self {
+            states: HashMap::new(),
+            released: HashSet::new(),
+            pending_releases: HashSet::new(),
+        }
+    }
+
+    pub fn get(&self, button: &T) -> Option<&ButtonState> {
+        self.states.get(button)
+    }
+
+    /// 押されているフレーム数を取得するメソッド。
+    ///
+    /// 押されていない場合、0を返す。
+    pub fn frames(&self, button: &T) -> u32 {
+        self.states.get(button).map_or(0, |n| n.frames)
+    }
+
+    pub fn just_released(&self, button: &T) -> bool {
+        self.released.contains(button)
+    }
+
+    /// 押されているすべてのボタンを取得するメソッド。
+    ///
+    /// 次のフレームで離されるボタンは含まない。
+    pub fn pressed(&self) -> Vec<T> {
+        self.states
+            .keys()
+            .filter(|n| !self.pending_releases.contains(n))
+            .copied()
+            .collect()
+    }
+
+    /// ボタンを押すメソッド。
+    ///
+    /// 既に押されている場合、無視される。
+    pub fn press(&mut self, button: T) {
+        self.pending_releases.remove(&button);
+        self.states.entry(button).or_insert(ButtonState {
+            frames: 1,
+            time: Duration::ZERO,
+            last_time: Duration::ZERO,
+        });
+    }
+
+    /// ボタンを離すメソッド。
+    ///
+    /// まさにこのフレームに押されたボタンの場合、次のフレームで離される。
+    pub fn release(&mut self, button: T) {
+        match self.frames(&button) {
+            0 => (),
+            1 => {
+                self.pending_releases.insert(button);
+            }
+            _ => {
+                self.states.remove(&button);
+                self.released.insert(button);
+            }
+        }
+    }
+
+    /// 次のフレームに進めるメソッド。
+    pub fn go_next(&mut self, duration: Duration) {
+        self.released.clear();
+        for n in self.pending_releases.drain() {
+            self.states.remove(&n);
+            self.released.insert(n);
+        }
+        for n in self.states.values_mut() {
+            n.frames += 1;
+            n.last_time = n.time;
+            n.time += duration;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    #[test]
+    fn counts_frames_and_time() {
+        let mut states = ButtonStates::new();
+        states.press('a');
+        assert_eq!(states.frames(&'a'), 1);
+        states.go_next(FRAME);
+        states.press('a');
+        states.go_next(FRAME);
+        let state = states.get(&'a').unwrap();
+        assert_eq!(state.frames, 3);
+        assert_eq!(state.time, FRAME * 2);
+        assert_eq!(state.last_time, FRAME);
+        assert_eq!(states.pressed(), ['a']);
+
+        states.release('a');
+        assert_eq!(states.frames(&'a'), 0);
+        assert!(states.just_released(&'a'));
+        states.go_next(FRAME);
+        assert!(!states.just_released(&'a'));
+    }
+
+    #[test]
+    fn keeps_taps_for_one_frame() {
+        // 同じフレームに押されて離されたボタンは、そのフレームは押されている
+        let mut states = ButtonStates::new();
+        states.press('a');
+        states.release('a');
+        assert_eq!(states.frames(&'a'), 1);
+        assert!(!states.just_released(&'a'));
+        assert!(states.pressed().is_empty());
+
+        states.go_next(FRAME);
+        assert_eq!(states.frames(&'a'), 0);
+        assert!(states.just_released(&'a'));
+    }
+
+    #[test]
+    fn cancels_pending_release_when_pressed_again() {
+        let mut states = ButtonStates::new();
+        states.press('a');
+        states.release('a');
+        states.press('a');
+        states.go_next(FRAME);
+        assert_eq!(states.frames(&'a'), 2);
+        assert!(!states.just_released(&'a'));
+    }
+
+    #[test]
+    fn ignores_releasing_unpressed_button() {
+        let mut states = ButtonStates::<char>::new();
+        states.release('a');
+        assert!(!states.just_released(&'a'));
+        states.go_next(FRAME);
+        assert!(!states.just_released(&'a'));
+    }
+}
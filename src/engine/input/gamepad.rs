@@ -0,0 +1,457 @@
+use super::*;
+
+use std::sync::{Arc, Mutex};
+
+/// 既定のスティックのデッドゾーン。
+pub const DEFAULT_STICK_DEADZONE: f32 = 0.2;
+/// スティックを方向入力とみなす傾きの大きさ(デッドゾーン適用後)。
+pub const STICK_DIRECTION_THRESHOLD: f32 = 0.5;
+
+/// ゲームパッドのボタン。
+///
+/// 配置はXbox系のコントローラの位置で表す(例：Southは下のボタン)。
+/// LeftStickUp等はスティックの傾きから変換された仮想的なボタンである。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    LeftStickUp,
+    LeftStickDown,
+    LeftStickLeft,
+    LeftStickRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 20] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::LeftThumb,
+        GamepadButton::RightThumb,
+        GamepadButton::LeftStickUp,
+        GamepadButton::LeftStickDown,
+        GamepadButton::LeftStickLeft,
+        GamepadButton::LeftStickRight,
+    ];
+}
+
+/// ゲームパッドのスティックの軸。
+///
+/// 値は[-1.0, 1.0]であり、Xは右、Yは上が正。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; 4] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+    ];
+}
+
+/// ゲームパッドのスティック。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadStick {
+    Left,
+    Right,
+}
+
+/// バックエンドから受け取るゲームパッドのイベント。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadEvent {
+    Pressed(GamepadButton),
+    Released(GamepadButton),
+    AxisChanged(GamepadAxis, f32),
+    /// 切断された。すべてのボタンが離され、スティックは中央に戻る。
+    Disconnected,
+}
+
+/// ゲームパッドの入力元を抽象化するトレイト。
+///
+/// 複数のゲームパッドが接続されている場合、それらは1つのゲームパッドとして扱われる。
+pub trait GamepadBackend {
+    /// 前回呼ばれてから発生したイベントを取り出すメソッド。
+    ///
+    /// 毎フレーム、クライアントの更新前に呼ばれる。
+    fn poll(&mut self) -> Vec<GamepadEvent>;
+}
+
+/// 何も入力しないバックエンド。
+pub struct NullGamepad;
+
+impl GamepadBackend for NullGamepad {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        Vec::new()
+    }
+}
+
+/// スクリプトから操作する仮想的なゲームパッド。
+///
+/// コントローラの無い環境でのテストに用いる。
+/// 複製はイベントキューを共有するため、InputManagerに渡した後も複製から操作できる。
+#[derive(Clone)]
+pub struct VirtualGamepad {
+    events: Arc<Mutex<Vec<GamepadEvent>>>,
+}
+
+impl VirtualGamepad {
+    pub fn new() -> Self {
+        Self {
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn press(&self, button: GamepadButton) {
+        self.push(GamepadEvent::Pressed(button));
+    }
+
+    pub fn release(&self, button: GamepadButton) {
+        self.push(GamepadEvent::Released(button));
+    }
+
+    /// スティックの軸を傾けるメソッド。
+    pub fn set_axis(&self, axis: GamepadAxis, value: f32) {
+        self.push(GamepadEvent::AxisChanged(axis, value));
+    }
+
+    pub fn disconnect(&self) {
+        self.push(GamepadEvent::Disconnected);
+    }
+
+    fn push(&self, event: GamepadEvent) {
+        self.events
+            .lock()
+            .unwrap_or_else(|n| n.into_inner())
+            .push(event);
+    }
+}
+
+impl GamepadBackend for VirtualGamepad {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap_or_else(|n| n.into_inner()))
+    }
+}
+
+/// gilrsを用いて実際のゲームパッドから入力するバックエンド。
+#[cfg(feature = "gilrs")]
+pub struct GilrsGamepad {
+    gilrs: gilrs::Gilrs,
+}
+
+#[cfg(feature = "gilrs")]
+impl GilrsGamepad {
+    pub fn new() -> Result<Self, EError> {
+        let gilrs = gilrs::Gilrs::new().map_err(|e| e.to_string())?;
+        Ok(Self { gilrs })
+    }
+
+    fn convert_button(button: gilrs::Button) -> Option<GamepadButton> {
+        use gilrs::Button;
+
+        let button = match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::West => GamepadButton::West,
+            Button::North => GamepadButton::North,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            Button::LeftTrigger => GamepadButton::LeftShoulder,
+            Button::RightTrigger => GamepadButton::RightShoulder,
+            Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+            Button::RightTrigger2 => GamepadButton::RightTrigger,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::LeftThumb => GamepadButton::LeftThumb,
+            Button::RightThumb => GamepadButton::RightThumb,
+            _ => return None,
+        };
+        Some(button)
+    }
+
+    fn convert_axis(axis: gilrs::Axis) -> Option<GamepadAxis> {
+        use gilrs::Axis;
+
+        let axis = match axis {
+            Axis::LeftStickX => GamepadAxis::LeftStickX,
+            Axis::LeftStickY => GamepadAxis::LeftStickY,
+            Axis::RightStickX => GamepadAxis::RightStickX,
+            Axis::RightStickY => GamepadAxis::RightStickY,
+            _ => return None,
+        };
+        Some(axis)
+    }
+}
+
+#[cfg(feature = "gilrs")]
+impl GamepadBackend for GilrsGamepad {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        use gilrs::EventType;
+
+        let mut events = Vec::new();
+        while let Some(event) = self.gilrs.next_event() {
+            let event = match event.event {
+                EventType::ButtonPressed(n, _) => {
+                    Self::convert_button(n).map(GamepadEvent::Pressed)
+                }
+                EventType::ButtonReleased(n, _) => {
+                    Self::convert_button(n).map(GamepadEvent::Released)
+                }
+                EventType::AxisChanged(n, value, _) => {
+                    Self::convert_axis(n).map(|n| GamepadEvent::AxisChanged(n, value))
+                }
+                EventType::Disconnected => Some(GamepadEvent::Disconnected),
+                _ => None,
+            };
+            events.extend(event);
+        }
+        events
+    }
+}
+
+/// 既定のバックエンドを作成する関数。
+///
+/// ゲームパッドを扱えない環境では、ヌルバックエンドを返す。
+pub fn create_default_backend() -> Box<dyn GamepadBackend> {
+    #[cfg(feature = "gilrs")]
+    match GilrsGamepad::new() {
+        Ok(n) => return Box::new(n),
+        Err(e) => eprintln!("failed to initialize gamepads: {e}"),
+    }
+    Box::new(NullGamepad)
+}
+
+/// スティックの値にデッドゾーンを適用する関数。
+///
+/// 傾きの大きさがdeadzone未満であれば0とし、それ以上であれば[0, 1]に再マップする。
+pub fn apply_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+    let length = stick.length();
+    if length < deadzone || length <= 0.0 {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - deadzone) / (1.0 - deadzone).max(f32::EPSILON)).min(1.0);
+    stick * (scaled / length)
+}
+
+/// スティックの値を上下左右の仮想的なボタンに変換する関数。
+///
+/// 斜めの入力は傾きの大きい軸の方向とする。
+pub fn stick_to_direction(stick: Vec2) -> Option<GamepadButton> {
+    if stick.length() < STICK_DIRECTION_THRESHOLD {
+        return None;
+    }
+    let button = if stick.x.abs() > stick.y.abs() {
+        if stick.x > 0.0 {
+            GamepadButton::LeftStickRight
+        } else {
+            GamepadButton::LeftStickLeft
+        }
+    } else if stick.y > 0.0 {
+        GamepadButton::LeftStickUp
+    } else {
+        GamepadButton::LeftStickDown
+    };
+    Some(button)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use action::Action;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    fn create_input_manager() -> (InputManager, VirtualGamepad) {
+        let pad = VirtualGamepad::new();
+        let mut in_mngr = InputManager::new();
+        in_mngr.set_gamepad(Box::new(pad.clone()));
+        (in_mngr, pad)
+    }
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!((a - b).length() < 1e-5, "{a} != {b}");
+    }
+
+    #[test]
+    fn follows_presses_and_releases() {
+        let (mut in_mngr, pad) = create_input_manager();
+        pad.press(GamepadButton::South);
+        in_mngr.update_gamepad();
+        assert!(in_mngr.gamepad_just_pressed(&GamepadButton::South));
+        assert!(in_mngr.action_just_pressed(Action::Confirm));
+
+        in_mngr.go_next(FRAME);
+        in_mngr.update_gamepad();
+        assert_eq!(in_mngr.get_gamepad(&GamepadButton::South), 2);
+        assert!(in_mngr.gamepad_held_for(&GamepadButton::South, 2));
+
+        pad.release(GamepadButton::South);
+        in_mngr.update_gamepad();
+        assert_eq!(in_mngr.get_gamepad(&GamepadButton::South), 0);
+        assert!(in_mngr.gamepad_just_released(&GamepadButton::South));
+        assert!(in_mngr.action_just_released(Action::Confirm));
+
+        in_mngr.go_next(FRAME);
+        in_mngr.update_gamepad();
+        assert!(!in_mngr.gamepad_just_released(&GamepadButton::South));
+    }
+
+    #[test]
+    fn keeps_taps_for_one_frame() {
+        // 1フレームの間に押されて離されたボタンも取りこぼさない
+        let (mut in_mngr, pad) = create_input_manager();
+        pad.press(GamepadButton::East);
+        pad.release(GamepadButton::East);
+        in_mngr.update_gamepad();
+        assert!(in_mngr.gamepad_just_pressed(&GamepadButton::East));
+
+        in_mngr.go_next(FRAME);
+        in_mngr.update_gamepad();
+        assert_eq!(in_mngr.get_gamepad(&GamepadButton::East), 0);
+        assert!(in_mngr.gamepad_just_released(&GamepadButton::East));
+    }
+
+    #[test]
+    fn rescales_sticks_outside_deadzone() {
+        let (mut in_mngr, pad) = create_input_manager();
+        pad.set_axis(GamepadAxis::LeftStickX, 0.1);
+        pad.set_axis(GamepadAxis::RightStickY, -0.6);
+        in_mngr.update_gamepad();
+        assert_eq!(in_mngr.get_stick(GamepadStick::Left), Vec2::ZERO);
+        // (0.6 - 0.2) / (1 - 0.2) = 0.5
+        assert_near(in_mngr.get_stick(GamepadStick::Right), Vec2::new(0.0, -0.5));
+
+        // 範囲外の値は[-1, 1]に収められる
+        pad.set_axis(GamepadAxis::RightStickY, 1.5);
+        in_mngr.update_gamepad();
+        assert_near(in_mngr.get_stick(GamepadStick::Right), Vec2::new(0.0, 1.0));
+
+        in_mngr.set_stick_deadzone(0.0);
+        assert_near(in_mngr.get_stick(GamepadStick::Left), Vec2::new(0.1, 0.0));
+    }
+
+    #[test]
+    fn applies_deadzone_radially() {
+        assert_eq!(apply_deadzone(Vec2::new(0.1, 0.1), 0.2), Vec2::ZERO);
+        assert_eq!(apply_deadzone(Vec2::ZERO, 0.0), Vec2::ZERO);
+        assert_near(
+            apply_deadzone(Vec2::new(0.6, 0.8), 0.2),
+            Vec2::new(0.6, 0.8),
+        );
+        assert_near(
+            apply_deadzone(Vec2::new(0.3, 0.4), 0.2),
+            Vec2::new(0.225, 0.3),
+        );
+        assert_near(
+            apply_deadzone(Vec2::new(1.0, 1.0), 0.2),
+            Vec2::new(1.0, 1.0).normalize(),
+        );
+    }
+
+    #[test]
+    fn converts_sticks_to_directions() {
+        assert_eq!(stick_to_direction(Vec2::new(0.3, 0.3)), None);
+        assert_eq!(
+            stick_to_direction(Vec2::new(0.0, 0.5)),
+            Some(GamepadButton::LeftStickUp)
+        );
+        assert_eq!(
+            stick_to_direction(Vec2::new(0.4, -0.6)),
+            Some(GamepadButton::LeftStickDown)
+        );
+        assert_eq!(
+            stick_to_direction(Vec2::new(-0.7, 0.6)),
+            Some(GamepadButton::LeftStickLeft)
+        );
+        assert_eq!(
+            stick_to_direction(Vec2::new(0.9, 0.0)),
+            Some(GamepadButton::LeftStickRight)
+        );
+    }
+
+    #[test]
+    fn fires_actions_from_left_stick() {
+        let (mut in_mngr, pad) = create_input_manager();
+        pad.set_axis(GamepadAxis::LeftStickY, 0.9);
+        in_mngr.update_gamepad();
+        assert_eq!(in_mngr.get_gamepad(&GamepadButton::LeftStickUp), 1);
+        assert_eq!(in_mngr.get_action(Action::Up), 1);
+
+        in_mngr.go_next(FRAME);
+        in_mngr.update_gamepad();
+        assert_eq!(in_mngr.get_action(Action::Up), 2);
+
+        // 傾きの向きが変わると、前の方向は離される
+        pad.set_axis(GamepadAxis::LeftStickX, 1.0);
+        pad.set_axis(GamepadAxis::LeftStickY, 0.0);
+        in_mngr.go_next(FRAME);
+        in_mngr.update_gamepad();
+        assert!(in_mngr.action_just_released(Action::Up));
+        assert!(in_mngr.action_just_pressed(Action::Right));
+
+        // デッドゾーン内に戻すと離される
+        pad.set_axis(GamepadAxis::LeftStickX, 0.1);
+        in_mngr.go_next(FRAME);
+        in_mngr.update_gamepad();
+        assert_eq!(in_mngr.get_action(Action::Right), 0);
+        assert!(in_mngr.action_just_released(Action::Right));
+    }
+
+    #[test]
+    fn releases_everything_on_disconnect() {
+        let (mut in_mngr, pad) = create_input_manager();
+        pad.press(GamepadButton::South);
+        pad.press(GamepadButton::Start);
+        pad.set_axis(GamepadAxis::LeftStickX, -1.0);
+        pad.set_axis(GamepadAxis::RightStickX, 1.0);
+        in_mngr.update_gamepad();
+        in_mngr.go_next(FRAME);
+        in_mngr.update_gamepad();
+        assert_eq!(in_mngr.get_action(Action::Left), 2);
+
+        pad.disconnect();
+        in_mngr.update_gamepad();
+        for n in [
+            GamepadButton::South,
+            GamepadButton::Start,
+            GamepadButton::LeftStickLeft,
+        ] {
+            assert_eq!(in_mngr.get_gamepad(&n), 0, "{n:?}");
+            assert!(in_mngr.gamepad_just_released(&n), "{n:?}");
+        }
+        assert_eq!(in_mngr.get_stick(GamepadStick::Left), Vec2::ZERO);
+        assert_eq!(in_mngr.get_stick(GamepadStick::Right), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,534 @@
+use super::*;
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// 記録ファイルの先頭に置かれるマジックナンバー。
+pub const MAGIC: &[u8; 4] = b"RPGR";
+/// 記録ファイルのフォーマットのバージョン。
+pub const VERSION: u32 = 1;
+/// 記録ファイル中の文字列の長さの上限 [byte]。
+///
+/// 壊れたファイルの長さを信用して巨大なバッファを確保しないようにする。
+const MAX_STRING_LENGTH: u32 = 1 << 20;
+
+/// 入力の記録・再生の設定。
+pub enum ReplayMode {
+    /// 入力をファイルに記録する。
+    Record(PathBuf),
+    /// ファイルに記録された入力を再生する。
+    Replay(PathBuf),
+}
+
+/// InputManagerに与えられた1つの入力。
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    Press(KeyCode),
+    Release(KeyCode),
+    PressMouse(MouseButton),
+    ReleaseMouse(MouseButton),
+    /// ウィンドウ上のカーソルの位置 [px]。
+    MoveCursor(Option<Vec2>),
+    /// ホイールの回転量 [行]。
+    Scroll(Vec2),
+    Text(String),
+    /// IMEの未確定文字列と、その中のカーソルの範囲 [byte]。
+    Preedit(String, Option<(usize, usize)>),
+    Gamepad(gamepad::GamepadEvent),
+    /// ビューポート(ウィンドウ上の位置, ウィンドウ上のサイズ, シーンのサイズ)。
+    Viewport(Vec2, Vec2, Vec2),
+}
+
+/// 1フレーム分の入力。
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// フレームの経過時間。
+    pub duration: Duration,
+    /// フレームの間に与えられた入力(与えられた順)。
+    pub events: Vec<InputEvent>,
+}
+
+/// 入力をフレームごとにファイルへ書き出すオブジェクト。
+///
+/// 同じ入力でもバインディングが異なればアクションの入力状態が変わるため、記録開始時のバインディングも書き出す。
+/// クラッシュしても直前のフレームまでは残るよう、フレームごとにフラッシュする。
+///
+/// フォーマット(すべてリトルエンディアン)：
+/// - ヘッダ：マジックナンバー, バージョン(u32), 記録開始時のバインディング(文字列)
+/// - フレーム：経過時間 [ns] (u64), 入力数(u32), 各入力
+///
+/// 各入力は種類(u8)に続けて内容を書く。
+/// キー・ボタン等の名前は長さ(u8)に続けてUTF-8で、文字列は長さ(u32)に続けてUTF-8で書く。
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+    events: Vec<InputEvent>,
+}
+
+impl InputRecorder {
+    /// コンストラクタ。
+    ///
+    /// bindingsはActionMap.to_text()で得られるバインディングである。
+    pub fn create<P: AsRef<Path>>(path: P, bindings: &str) -> Result<Self, EError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_string(&mut writer, bindings)?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            events: Vec::new(),
+        })
+    }
+
+    /// このフレームの入力を追加するメソッド。
+    pub fn push(&mut self, event: InputEvent) {
+        self.events.push(event);
+    }
+
+    /// このフレームの入力をファイルに書き出すメソッド。
+    pub fn end_frame(&mut self, duration: Duration) -> Result<(), EError> {
+        let w = &mut self.writer;
+        w.write_all(&(duration.as_nanos() as u64).to_le_bytes())?;
+        w.write_all(&(self.events.len() as u32).to_le_bytes())?;
+        for n in self.events.drain(..) {
+            write_event(w, &n)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// ファイルに記録された入力を1フレームずつ取り出すオブジェクト。
+pub struct InputReplay {
+    /// 記録開始時のバインディング。
+    bindings: String,
+    frames: Vec<Frame>,
+    index: usize,
+}
+
+impl InputReplay {
+    pub fn new(bindings: String, frames: Vec<Frame>) -> Self {
+        Self {
+            bindings,
+            frames,
+            index: 0,
+        }
+    }
+
+    /// 記録ファイルを読み込むメソッド。
+    ///
+    /// フレームの境界でファイルが終わっていれば、そこまでを読み込む。
+    /// NOTE: フレームごとにフラッシュしているため、記録中にクラッシュしてもファイルはフレームの境界で終わる。
+    /// WARN: フレームの途中で終わっている・不正な入力がある場合、エラーを返す。
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, EError> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err("invalid replay magic number.".into());
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(format!("unsupported replay version {version}.").into());
+        }
+        let bindings = read_string(&mut reader)?;
+
+        let mut frames = Vec::new();
+        while let Some(frame) = read_frame(&mut reader)
+            .map_err(|e| format!("failed to read the replay frame {}: {e}", frames.len()))?
+        {
+            frames.push(frame);
+        }
+        Ok(Self::new(bindings, frames))
+    }
+
+    pub fn get_bindings(&self) -> &str {
+        &self.bindings
+    }
+
+    pub fn get_frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// 次のフレームを取り出すメソッド。
+    ///
+    /// すべてのフレームを取り出した場合、Noneを返す。
+    pub fn next_frame(&mut self) -> Option<&Frame> {
+        let frame = self.frames.get(self.index)?;
+        self.index += 1;
+        Some(frame)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.index >= self.frames.len()
+    }
+}
+
+fn write_event(w: &mut impl Write, event: &InputEvent) -> Result<(), EError> {
+    match event {
+        InputEvent::Press(n) => {
+            w.write_all(&[0])?;
+            write_name(w, &key::get_name(*n))?;
+        }
+        InputEvent::Release(n) => {
+            w.write_all(&[1])?;
+            write_name(w, &key::get_name(*n))?;
+        }
+        InputEvent::PressMouse(n) => {
+            w.write_all(&[2])?;
+            write_mouse_button(w, n)?;
+        }
+        InputEvent::ReleaseMouse(n) => {
+            w.write_all(&[3])?;
+            write_mouse_button(w, n)?;
+        }
+        InputEvent::MoveCursor(None) => w.write_all(&[4])?,
+        InputEvent::MoveCursor(Some(n)) => {
+            w.write_all(&[5])?;
+            write_vec2(w, *n)?;
+        }
+        InputEvent::Scroll(n) => {
+            w.write_all(&[6])?;
+            write_vec2(w, *n)?;
+        }
+        InputEvent::Text(n) => {
+            w.write_all(&[7])?;
+            write_string(w, n)?;
+        }
+        InputEvent::Preedit(text, cursor) => {
+            w.write_all(&[8])?;
+            write_string(w, text)?;
+            match cursor {
+                Some((start, end)) => {
+                    w.write_all(&[1])?;
+                    w.write_all(&(*start as u32).to_le_bytes())?;
+                    w.write_all(&(*end as u32).to_le_bytes())?;
+                }
+                None => w.write_all(&[0])?,
+            }
+        }
+        InputEvent::Gamepad(gamepad::GamepadEvent::Pressed(n)) => {
+            w.write_all(&[9])?;
+            write_name(w, &format!("{n:?}"))?;
+        }
+        InputEvent::Gamepad(gamepad::GamepadEvent::Released(n)) => {
+            w.write_all(&[10])?;
+            write_name(w, &format!("{n:?}"))?;
+        }
+        InputEvent::Gamepad(gamepad::GamepadEvent::AxisChanged(n, value)) => {
+            w.write_all(&[11])?;
+            write_name(w, &format!("{n:?}"))?;
+            w.write_all(&value.to_le_bytes())?;
+        }
+        InputEvent::Gamepad(gamepad::GamepadEvent::Disconnected) => w.write_all(&[12])?,
+        InputEvent::Viewport(offset, size, scene_size) => {
+            w.write_all(&[13])?;
+            write_vec2(w, *offset)?;
+            write_vec2(w, *size)?;
+            write_vec2(w, *scene_size)?;
+        }
+    }
+    Ok(())
+}
+
+/// フレームを1つ読み込む関数。
+///
+/// フレームの境界でファイルが終わっている場合、Noneを返す。
+fn read_frame(r: &mut impl BufRead) -> Result<Option<Frame>, EError> {
+    if r.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let duration = Duration::from_nanos(read_u64(r)?);
+    let count = read_u32(r)?;
+    let mut events = Vec::new();
+    for _ in 0..count {
+        events.push(read_event(r)?);
+    }
+    Ok(Some(Frame { duration, events }))
+}
+
+fn read_event(r: &mut impl Read) -> Result<InputEvent, EError> {
+    let event = match read_u8(r)? {
+        0 => InputEvent::Press(read_key(r)?),
+        1 => InputEvent::Release(read_key(r)?),
+        2 => InputEvent::PressMouse(read_mouse_button(r)?),
+        3 => InputEvent::ReleaseMouse(read_mouse_button(r)?),
+        4 => InputEvent::MoveCursor(None),
+        5 => InputEvent::MoveCursor(Some(read_vec2(r)?)),
+        6 => InputEvent::Scroll(read_vec2(r)?),
+        7 => InputEvent::Text(read_string(r)?),
+        8 => {
+            let text = read_string(r)?;
+            let cursor = match read_u8(r)? {
+                0 => None,
+                _ => Some((read_u32(r)? as usize, read_u32(r)? as usize)),
+            };
+            InputEvent::Preedit(text, cursor)
+        }
+        9 => InputEvent::Gamepad(gamepad::GamepadEvent::Pressed(read_gamepad_button(r)?)),
+        10 => InputEvent::Gamepad(gamepad::GamepadEvent::Released(read_gamepad_button(r)?)),
+        11 => {
+            let name = read_name(r)?;
+            let axis = gamepad::GamepadAxis::ALL
+                .into_iter()
+                .find(|n| format!("{n:?}") == name)
+                .ok_or(format!("unknown gamepad axis {name}."))?;
+            let value = f32::from_le_bytes(read_array(r)?);
+            InputEvent::Gamepad(gamepad::GamepadEvent::AxisChanged(axis, value))
+        }
+        12 => InputEvent::Gamepad(gamepad::GamepadEvent::Disconnected),
+        13 => InputEvent::Viewport(read_vec2(r)?, read_vec2(r)?, read_vec2(r)?),
+        n => return Err(format!("unknown input event {n}.").into()),
+    };
+    Ok(event)
+}
+
+fn write_mouse_button(w: &mut impl Write, button: &MouseButton) -> Result<(), EError> {
+    let (kind, id) = match button {
+        MouseButton::Left => (0, 0),
+        MouseButton::Right => (1, 0),
+        MouseButton::Middle => (2, 0),
+        MouseButton::Back => (3, 0),
+        MouseButton::Forward => (4, 0),
+        MouseButton::Other(n) => (5, *n),
+    };
+    w.write_all(&[kind])?;
+    w.write_all(&id.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_mouse_button(r: &mut impl Read) -> Result<MouseButton, EError> {
+    let kind = read_u8(r)?;
+    let id = u16::from_le_bytes(read_array(r)?);
+    let button = match kind {
+        0 => MouseButton::Left,
+        1 => MouseButton::Right,
+        2 => MouseButton::Middle,
+        3 => MouseButton::Back,
+        4 => MouseButton::Forward,
+        5 => MouseButton::Other(id),
+        n => return Err(format!("unknown mouse button {n}.").into()),
+    };
+    Ok(button)
+}
+
+fn read_key(r: &mut impl Read) -> Result<KeyCode, EError> {
+    let name = read_name(r)?;
+    key::from_name(&name).ok_or(format!("unknown key {name}.").into())
+}
+
+fn read_gamepad_button(r: &mut impl Read) -> Result<gamepad::GamepadButton, EError> {
+    let name = read_name(r)?;
+    gamepad::GamepadButton::ALL
+        .into_iter()
+        .find(|n| format!("{n:?}") == name)
+        .ok_or(format!("unknown gamepad button {name}.").into())
+}
+
+fn write_name(w: &mut impl Write, name: &str) -> Result<(), EError> {
+    w.write_all(&[name.len() as u8])?;
+    w.write_all(name.as_bytes())?;
+    Ok(())
+}
+
+fn read_name(r: &mut impl Read) -> Result<String, EError> {
+    let length = read_u8(r)?;
+    let mut buf = vec![0; length as usize];
+    r.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn write_string(w: &mut impl Write, s: &str) -> Result<(), EError> {
+    w.write_all(&(s.len() as u32).to_le_bytes())?;
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+fn read_string(r: &mut impl Read) -> Result<String, EError> {
+    let length = read_u32(r)?;
+    if length > MAX_STRING_LENGTH {
+        return Err(format!("a string of {length} bytes is too long.").into());
+    }
+    let mut buf = vec![0; length as usize];
+    r.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn write_vec2(w: &mut impl Write, v: Vec2) -> Result<(), EError> {
+    w.write_all(&v.x.to_le_bytes())?;
+    w.write_all(&v.y.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_vec2(r: &mut impl Read) -> Result<Vec2, EError> {
+    let x = f32::from_le_bytes(read_array(r)?);
+    let y = f32::from_le_bytes(read_array(r)?);
+    Ok(Vec2::new(x, y))
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> Result<[u8; N], EError> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u8(r: &mut impl Read) -> Result<u8, EError> {
+    Ok(read_array::<1>(r)?[0])
+}
+
+fn read_u32(r: &mut impl Read) -> Result<u32, EError> {
+    Ok(u32::from_le_bytes(read_array(r)?))
+}
+
+fn read_u64(r: &mut impl Read) -> Result<u64, EError> {
+    Ok(u64::from_le_bytes(read_array(r)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("prototype-{}-{name}.rec", std::process::id()))
+    }
+
+    /// framesを記録したファイルを作成し、その内容を返す関数。
+    fn record(path: &Path, bindings: &str, frames: &[Frame]) -> Vec<u8> {
+        let mut recorder = InputRecorder::create(path, bindings).unwrap();
+        for frame in frames {
+            frame.events.iter().for_each(|n| recorder.push(n.clone()));
+            recorder.end_frame(frame.duration).unwrap();
+        }
+        drop(recorder);
+        std::fs::read(path).unwrap()
+    }
+
+    fn sample_frames() -> Vec<Frame> {
+        use gamepad::{GamepadAxis, GamepadButton, GamepadEvent};
+
+        vec![
+            Frame {
+                duration: Duration::from_nanos(16_666_667),
+                events: vec![
+                    InputEvent::Press(KeyCode::KeyZ),
+                    InputEvent::Release(KeyCode::F24),
+                    InputEvent::PressMouse(MouseButton::Left),
+                    InputEvent::ReleaseMouse(MouseButton::Other(7)),
+                    InputEvent::MoveCursor(None),
+                    InputEvent::MoveCursor(Some(Vec2::new(1.5, -2.0))),
+                    InputEvent::Scroll(Vec2::new(0.0, 3.0)),
+                ],
+            },
+            Frame {
+                duration: Duration::ZERO,
+                events: Vec::new(),
+            },
+            Frame {
+                duration: Duration::from_millis(33),
+                events: vec![
+                    InputEvent::Text("なまえ".to_string()),
+                    InputEvent::Preedit("へんかん".to_string(), Some((0, 6))),
+                    InputEvent::Preedit(String::new(), None),
+                    InputEvent::Gamepad(GamepadEvent::Pressed(GamepadButton::South)),
+                    InputEvent::Gamepad(GamepadEvent::Released(GamepadButton::Start)),
+                    InputEvent::Gamepad(GamepadEvent::AxisChanged(GamepadAxis::LeftStickY, -0.5)),
+                    InputEvent::Gamepad(GamepadEvent::Disconnected),
+                    InputEvent::Viewport(
+                        Vec2::ZERO,
+                        Vec2::new(640.0, 360.0),
+                        Vec2::new(1280.0, 720.0),
+                    ),
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round-trip");
+        let frames = sample_frames();
+        record(&path, "Confirm = F13\n", &frames);
+        let replay = InputReplay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.get_bindings(), "Confirm = F13\n");
+        assert_eq!(replay.get_frames(), frames);
+    }
+
+    #[test]
+    fn replays_frames_in_order() {
+        let mut replay = InputReplay::new(String::new(), sample_frames());
+        assert_eq!(
+            replay.next_frame().unwrap().duration,
+            Duration::from_nanos(16_666_667)
+        );
+        assert_eq!(replay.next_frame().unwrap().duration, Duration::ZERO);
+        assert!(!replay.is_finished());
+        assert_eq!(
+            replay.next_frame().unwrap().duration,
+            Duration::from_millis(33)
+        );
+        assert!(replay.is_finished());
+        assert!(replay.next_frame().is_none());
+    }
+
+    #[test]
+    fn records_all_bindings() {
+        // 記録開始時のバインディングは、どのキーを割り当てていても失われない
+        let mut action_map = action::ActionMap::new();
+        action_map.bind(action::Action::Confirm, KeyCode::IntlYen);
+        action_map.bind(action::Action::Cancel, KeyCode::MediaPlayPause);
+        let path = temp_path("bindings");
+        record(&path, &action_map.to_text(), &[]);
+        let replay = InputReplay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut parsed = action::ActionMap::new();
+        parsed.parse(replay.get_bindings()).unwrap();
+        for action in action::Action::ALL {
+            assert_eq!(parsed.get_bindings(action), action_map.get_bindings(action));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_frames() {
+        let path = temp_path("truncated");
+        let buf = record(&path, "", &sample_frames());
+        std::fs::write(&path, &buf[..buf.len() - 1]).unwrap();
+        let result = InputReplay::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_corrupted_events() {
+        let path = temp_path("corrupted");
+        let frames = [Frame {
+            duration: Duration::ZERO,
+            events: vec![InputEvent::Scroll(Vec2::ZERO)],
+        }];
+        let mut buf = record(&path, "", &frames);
+        // 入力の種類を未知のものにする
+        let offset = buf.len() - 9;
+        assert_eq!(buf[offset], 6);
+        buf[offset] = 0xff;
+        std::fs::write(&path, &buf).unwrap();
+        let result = InputReplay::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_too_long_strings() {
+        let mut buf = Vec::new();
+        buf.extend((MAX_STRING_LENGTH + 1).to_le_bytes());
+        assert!(read_string(&mut buf.as_slice()).is_err());
+
+        let mut buf = Vec::new();
+        write_string(&mut buf, "あいう").unwrap();
+        assert_eq!(read_string(&mut buf.as_slice()).unwrap(), "あいう");
+    }
+}
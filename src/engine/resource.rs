@@ -1,8 +1,15 @@
+mod archive;
+
 use super::*;
 
 use ab_glyph::*;
 use png::Decoder;
-use std::{collections::HashMap, fs::File, io::Read};
+use std::{collections::HashMap, fs, path::Path};
+
+/// リソースをまとめたアーカイブファイルのパス。
+const ARCHIVE_PATH: &str = "res.dat";
+/// リソースを剝き出して配置するディレクトリのパス。
+const RESOURCE_DIRECTORY: &str = "res";
 
 pub struct CharacterRasterizedResult {
     pub texture: Vec<u8>,
@@ -14,26 +21,53 @@ pub struct CharacterRasterizedResult {
 }
 
 /// 外部リソースを管理するオブジェクト。
-//
-// NOTE: 将来的にリソースをすべて.datファイルにまとめて
-//       常にオープンしておいて適宜シークして各リソースを読み出すために
-//       このオブジェクトを介す。
+///
+/// リソースは.datファイル(アーカイブ)から読み出す。
+/// アーカイブは常にオープンされており、適宜シークして各リソースを読み出す。
+///
+/// デバッグビルドにおいては、アーカイブが存在しない場合・アーカイブにリソースが含まれない場合、
+/// resディレクトリ下から直接読み出す。
 pub struct ResourceManager {
+    archive: Option<archive::Archive>,
     fonts: HashMap<String, FontVec>,
 }
 
 impl ResourceManager {
-    pub fn new() -> Self {
-        Self {
+    /// コンストラクタ。
+    ///
+    /// WARN: リリースビルドにおいてアーカイブをオープンできない場合、エラーを返す。
+    pub fn new() -> Result<Self, EError> {
+        let archive = match archive::Archive::open(ARCHIVE_PATH) {
+            Ok(n) => Some(n),
+            Err(e) if cfg!(debug_assertions) => {
+                // NOTE: アーカイブが無いのはデバッグビルドでは普通なので、存在する場合のみ報告する。
+                if Path::new(ARCHIVE_PATH).exists() {
+                    eprintln!("failed to open '{ARCHIVE_PATH}', reading '{RESOURCE_DIRECTORY}' instead: {e}");
+                }
+                None
+            }
+            Err(e) => return Err(format!("failed to open '{ARCHIVE_PATH}': {e}").into()),
+        };
+        Ok(Self {
+            archive,
             fonts: HashMap::new(),
+        })
+    }
+
+    /// リソースのバイト列を読み出すメソッド。
+    ///
+    /// * name - resディレクトリからの相対パス
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, EError> {
+        match &self.archive {
+            Some(n) if !cfg!(debug_assertions) || n.contains(name) => n.read(name),
+            _ => Ok(fs::read(format!("{RESOURCE_DIRECTORY}/{name}"))?),
         }
     }
 
     pub fn load_png(&self, id: &str) -> Result<(Vec<u8>, u32, u32), EError> {
-        // TODO: .datファイルから読み出す
-        let file = File::open(format!("res/{id}.png"))?;
+        let buf = self.read(&format!("{id}.png"))?;
 
-        let mut reader = Decoder::new(file).read_info()?;
+        let mut reader = Decoder::new(buf.as_slice()).read_info()?;
         let mut texture = vec![0; reader.output_buffer_size()];
         let output_info = reader.next_frame(&mut texture)?;
 
@@ -88,10 +122,7 @@ impl ResourceManager {
         if self.fonts.contains_key(font_name) {
             Ok(())
         } else {
-            // TODO: .datファイルから読み出す。
-            let mut file = File::open(format!("res/{font_name}"))?;
-            let mut buf = Vec::new();
-            file.read_to_end(&mut buf)?;
+            let buf = self.read(font_name)?;
 
             self.fonts
                 .insert(font_name.to_string(), FontVec::try_from_vec(buf)?);
@@ -0,0 +1,324 @@
+// NOTE: パッカー(src/packer.rs)とフォーマットを共有するために#[path]で読み込まれるため、
+//       このモジュールは親モジュールに依存しないこと。
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Component, Path},
+};
+
+type EError = Box<dyn Error>;
+
+/// アーカイブファイルの先頭に置かれるマジックナンバー。
+pub const MAGIC: &[u8; 4] = b"RPGA";
+/// アーカイブファイルのフォーマットのバージョン。
+pub const VERSION: u32 = 1;
+/// ヘッダのサイズ [byte]。
+///
+/// マジックナンバー(4) + バージョン(4) + エントリ数(4) + 目次のオフセット(8)
+pub const HEADER_SIZE: u64 = 20;
+/// 目次の1エントリの最小のサイズ [byte]。
+///
+/// 名前長(2) + オフセット(8) + データ長(8) + チェックサム(8)
+const TOC_ENTRY_MIN_SIZE: u64 = 26;
+
+/// アーカイブ中の1エントリの情報。
+pub struct Entry {
+    /// ファイル先頭からのオフセット [byte]。
+    pub offset: u64,
+    /// データ長 [byte]。
+    pub length: u64,
+    /// データのチェックサム(FNV-1a 64bit)。
+    pub checksum: u64,
+}
+
+/// リソースを1ファイルにまとめたアーカイブを読み出すオブジェクト。
+///
+/// フォーマット(すべてリトルエンディアン)：
+/// - ヘッダ：マジックナンバー, バージョン(u32), エントリ数(u32), 目次のオフセット(u64)
+/// - データ：各エントリのデータを連結したもの
+/// - 目次：各エントリについて名前長(u16), 名前(UTF-8), オフセット(u64), データ長(u64), チェックサム(u64)
+///
+/// ファイルはオープンしたまま保持され、読み出しの度にシークする。
+/// エントリの名前はすべて相対パスであり、`..`等を含むものは読み込み時に拒否する。
+pub struct Archive {
+    file: File,
+    entries: HashMap<String, Entry>,
+}
+
+impl Archive {
+    /// アーカイブファイルをオープンして目次を読み込むメソッド。
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, EError> {
+        let mut file = File::open(path)?;
+
+        // ヘッダ
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err("invalid archive magic number.".into());
+        }
+        let version = read_u32(&mut file)?;
+        if version != VERSION {
+            return Err(format!("unsupported archive version {version}.").into());
+        }
+        let count = read_u32(&mut file)?;
+        let toc_offset = read_u64(&mut file)?;
+
+        // 目次
+        // NOTE: エントリ数は信用できないので、ファイルサイズに収まるかを確かめてから読む。
+        let size = file.metadata()?.len();
+        if toc_offset < HEADER_SIZE
+            || toc_offset > size
+            || (size - toc_offset) / TOC_ENTRY_MIN_SIZE < count as u64
+        {
+            return Err(
+                format!("archive has a broken table of contents ({count} entries).").into(),
+            );
+        }
+        file.seek(SeekFrom::Start(toc_offset))?;
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let name_length = read_u16(&mut file)?;
+            let mut name = vec![0; name_length as usize];
+            file.read_exact(&mut name)?;
+            let name = String::from_utf8(name)?;
+            if !is_valid_name(&name) {
+                return Err(format!("entry '{name}' has an invalid name.").into());
+            }
+            let offset = read_u64(&mut file)?;
+            let length = read_u64(&mut file)?;
+            let checksum = read_u64(&mut file)?;
+            if offset < HEADER_SIZE
+                || !matches!(offset.checked_add(length), Some(n) if n <= toc_offset)
+            {
+                return Err(format!("entry '{name}' is out of range.").into());
+            }
+            if entries.contains_key(&name) {
+                return Err(format!("entry '{name}' is duplicated.").into());
+            }
+            entries.insert(
+                name,
+                Entry {
+                    offset,
+                    length,
+                    checksum,
+                },
+            );
+        }
+        if file.stream_position()? != size {
+            return Err("archive has trailing data.".into());
+        }
+
+        Ok(Self { file, entries })
+    }
+
+    /// エントリが存在するか否かを取得するメソッド。
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// エントリの情報を取得するメソッド。
+    // NOTE: パッカーのみが使う。
+    #[allow(dead_code)]
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries.get(name)
+    }
+
+    /// すべてのエントリの名前を昇順で取得するメソッド。
+    // NOTE: パッカーのみが使う。
+    #[allow(dead_code)]
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self.entries.keys().map(|n| n.as_str()).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// エントリのデータを読み出すメソッド。
+    ///
+    /// データがチェックサムと一致しない場合、エラーを返す。
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, EError> {
+        let Some(entry) = self.entries.get(name) else {
+            return Err(format!("entry '{name}' is not found in the archive.").into());
+        };
+
+        // NOTE: &FileはRead/Seekを実装しているので、不変参照のままシークできる。
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut buf = vec![0; entry.length as usize];
+        file.read_exact(&mut buf)?;
+        if checksum(&buf) != entry.checksum {
+            return Err(format!("entry '{name}' has a checksum mismatch.").into());
+        }
+        Ok(buf)
+    }
+}
+
+/// エントリの名前として使えるか判定する関数。
+///
+/// 空でなく、`..`・`.`・ルート・ドライブ等を含まない相対パスのみ使える。
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && Path::new(name)
+            .components()
+            .all(|n| matches!(n, Component::Normal(_)))
+}
+
+/// アーカイブファイルを書き出す関数。
+///
+/// * entries - (名前, データ)の配列
+// NOTE: パッカーのみが使う。
+#[allow(dead_code)]
+pub fn write<P: AsRef<Path>>(path: P, entries: &[(String, Vec<u8>)]) -> Result<(), EError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    // ヘッダ
+    let toc_offset = HEADER_SIZE + entries.iter().map(|(_, n)| n.len() as u64).sum::<u64>();
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&u32::try_from(entries.len())?.to_le_bytes())?;
+    writer.write_all(&toc_offset.to_le_bytes())?;
+
+    // データ
+    for (_, data) in entries {
+        writer.write_all(data)?;
+    }
+
+    // 目次
+    let mut offset = HEADER_SIZE;
+    for (name, data) in entries {
+        writer.write_all(&u16::try_from(name.len())?.to_le_bytes())?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&(data.len() as u64).to_le_bytes())?;
+        writer.write_all(&checksum(data).to_le_bytes())?;
+        offset += data.len() as u64;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// データのチェックサム(FNV-1a 64bit)を計算する関数。
+pub fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |h, n| {
+        (h ^ *n as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16, EError> {
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, EError> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, EError> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs, path::PathBuf, process};
+
+    /// テストごとに異なる一時ファイルのパスを作る関数。
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("archive_test_{}_{name}.dat", process::id()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round_trip");
+        let entries = vec![
+            ("a.png".to_string(), vec![1, 2, 3]),
+            ("dir/b.wav".to_string(), vec![]),
+            ("c.otf".to_string(), (0..=255).collect()),
+        ];
+        write(&path, &entries).unwrap();
+
+        let archive = Archive::open(&path).unwrap();
+        assert_eq!(archive.names(), vec!["a.png", "c.otf", "dir/b.wav"]);
+        for (name, data) in &entries {
+            assert_eq!(&archive.read(name).unwrap(), data);
+            assert_eq!(archive.get(name).unwrap().checksum, checksum(data));
+        }
+        assert!(archive.read("missing.png").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_broken_count() {
+        let path = temp_path("broken_count");
+        write(&path, &[("a.png".to_string(), vec![1])]).unwrap();
+        let mut buf = fs::read(&path).unwrap();
+        buf[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, buf).unwrap();
+
+        assert!(Archive::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_corrupted_data() {
+        let path = temp_path("corrupted_data");
+        write(&path, &[("a.png".to_string(), vec![1, 2, 3])]).unwrap();
+        let mut buf = fs::read(&path).unwrap();
+        buf[HEADER_SIZE as usize] = 0xff;
+        fs::write(&path, buf).unwrap();
+
+        let archive = Archive::open(&path).unwrap();
+        assert!(archive.read("a.png").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_escaping_names() {
+        // NOTE: 同じ長さの名前を書き込んでから目次を書き換える。
+        for name in ["../a.png", "/etc/a.png", "a/../../b"] {
+            let path = temp_path("escaping_names");
+            let placeholder = "x".repeat(name.len());
+            write(&path, &[(placeholder.clone(), vec![1])]).unwrap();
+            let mut buf = fs::read(&path).unwrap();
+            let i = buf.len() - 24 - name.len();
+            buf[i..i + name.len()].copy_from_slice(name.as_bytes());
+            fs::write(&path, buf).unwrap();
+
+            assert!(Archive::open(&path).is_err(), "{name}");
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(is_valid_name("a.png"));
+        assert!(is_valid_name("dir/b.wav"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("."));
+        assert!(!is_valid_name("../a.png"));
+        assert!(!is_valid_name("dir/../../a.png"));
+        assert!(!is_valid_name("/a.png"));
+    }
+
+    #[test]
+    fn rejects_duplicated_names() {
+        let path = temp_path("duplicated_names");
+        let entries = [
+            ("a.png".to_string(), vec![1]),
+            ("a.png".to_string(), vec![2]),
+        ];
+        write(&path, &entries).unwrap();
+
+        assert!(Archive::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+// NOTE: 計測用のユーティリティなので、使われなくても良い。
+#![allow(dead_code)]
+
+use super::*;
+
+use std::collections::VecDeque;
+
+/// フレーム時間の履歴の長さ。
+pub const FRAME_TIME_HISTORY_LENGTH: usize = 120;
+
+/// フレームの統計情報。
+///
+/// エンジンが毎フレーム記録し、Managers.frame_statsから読み出せる。
+pub struct FrameStats {
+    /// 直近のフレーム時間。古い順に並ぶ。
+    frame_times: VecDeque<Duration>,
+    /// 直前のフレームの描画の統計情報。
+    render_stats: graphic::RenderStats,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self {
+            frame_times: VecDeque::with_capacity(FRAME_TIME_HISTORY_LENGTH),
+            render_stats: graphic::RenderStats::default(),
+        }
+    }
+
+    /// 1フレームの計測結果を記録するメソッド。
+    ///
+    /// frame_timeは上限で切り詰める前の実際の経過時間である。
+    pub fn push(&mut self, frame_time: Duration, render_stats: graphic::RenderStats) {
+        if self.frame_times.len() >= FRAME_TIME_HISTORY_LENGTH {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+        self.render_stats = render_stats;
+    }
+
+    /// 直近のフレーム時間の履歴を取得するメソッド。古い順に並ぶ。
+    pub fn get_frame_times(&self) -> &VecDeque<Duration> {
+        &self.frame_times
+    }
+
+    /// 直前のフレーム時間を取得するメソッド。
+    pub fn get_last_frame_time(&self) -> Duration {
+        self.frame_times.back().copied().unwrap_or_default()
+    }
+
+    /// 履歴中のフレーム時間の平均を取得するメソッド。
+    pub fn get_average_frame_time(&self) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32
+    }
+
+    /// 履歴中のフレーム時間の最大値を取得するメソッド。
+    pub fn get_max_frame_time(&self) -> Duration {
+        self.frame_times.iter().max().copied().unwrap_or_default()
+    }
+
+    /// 履歴中の平均のフレームレート [fps] を取得するメソッド。
+    pub fn get_fps(&self) -> f32 {
+        let average = self.get_average_frame_time();
+        if average.is_zero() {
+            0.0
+        } else {
+            1.0 / average.as_secs_f32()
+        }
+    }
+
+    /// 直前のフレームの描画の統計情報を取得するメソッド。
+    pub fn get_render_stats(&self) -> &graphic::RenderStats {
+        &self.render_stats
+    }
+}
@@ -0,0 +1,138 @@
+use super::*;
+
+/// 1フレームの経過時間の既定の上限。
+///
+/// デバッガで止めた・ウィンドウをドラッグした等でフレームが極端に長くなった場合に、
+/// シミュレーションが一度に大きく進まないようにする。
+pub const DEFAULT_MAX_FRAME_DURATION: Duration = Duration::from_millis(250);
+
+/// シミュレーションの進め方。
+///
+/// 既定は可変タイムステップであり、固定タイムステップはクライアントが選んだ場合のみ用いる。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestepMode {
+    /// 毎フレーム、経過時間をそのままClientHandler.update()に渡す。
+    #[default]
+    Variable,
+    /// 一定の時間ごとにClientHandler.fixed_update()を呼び、毎フレームClientHandler.render()を呼ぶ。
+    ///
+    /// シミュレーションの結果がフレームレートに依存しない。
+    Fixed(Duration),
+}
+
+/// フレームの経過時間をシミュレーションのステップに変換するオブジェクト。
+pub struct Timestep {
+    mode: TimestepMode,
+    max_frame_duration: Duration,
+    /// まだシミュレーションに使われていない経過時間。
+    accumulator: Duration,
+}
+
+impl Timestep {
+    /// コンストラクタ。
+    ///
+    /// 固定タイムステップの間隔が0の場合、可変タイムステップとなる。
+    pub fn new(mode: TimestepMode, max_frame_duration: Duration) -> Self {
+        let mode = match mode {
+            TimestepMode::Fixed(n) if n.is_zero() => TimestepMode::Variable,
+            n => n,
+        };
+        Self {
+            mode,
+            max_frame_duration,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// 固定タイムステップの間隔を取得するメソッド。
+    ///
+    /// 可変タイムステップの場合、Noneを返す。
+    pub fn get_step(&self) -> Option<Duration> {
+        match self.mode {
+            TimestepMode::Variable => None,
+            TimestepMode::Fixed(n) => Some(n),
+        }
+    }
+
+    /// 経過時間を上限で切り詰めるメソッド。
+    pub fn clamp(&self, elapsed: Duration) -> Duration {
+        elapsed.min(self.max_frame_duration)
+    }
+
+    /// 経過時間を蓄積し、このフレームに進めるべきステップ数を返すメソッド。
+    ///
+    /// 経過時間は上限で切り詰められる。
+    /// 可変タイムステップの場合、常に1を返す。
+    pub fn accumulate(&mut self, elapsed: Duration) -> u32 {
+        let Some(step) = self.get_step() else {
+            return 1;
+        };
+        self.accumulator += self.clamp(elapsed);
+        let mut count = 0;
+        while self.accumulator >= step {
+            self.accumulator -= step;
+            count += 1;
+        }
+        count
+    }
+
+    /// 直前のステップから次のステップまでの進行度 [0, 1) を取得するメソッド。
+    ///
+    /// 描画時に直前のステップと現在のステップの状態を補間するために用いる。
+    /// 可変タイムステップの場合、常に1.0を返す。
+    pub fn get_alpha(&self) -> f32 {
+        match self.get_step() {
+            Some(step) => self.accumulator.as_secs_f32() / step.as_secs_f32(),
+            None => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn passes_variable_frames_through() {
+        let mut timestep = Timestep::new(TimestepMode::default(), DEFAULT_MAX_FRAME_DURATION);
+        assert_eq!(timestep.get_step(), None);
+        assert_eq!(timestep.accumulate(Duration::from_millis(3)), 1);
+        assert_eq!(timestep.accumulate(Duration::from_secs(5)), 1);
+        assert_eq!(timestep.get_alpha(), 1.0);
+        assert_eq!(
+            timestep.clamp(Duration::from_secs(5)),
+            DEFAULT_MAX_FRAME_DURATION
+        );
+    }
+
+    #[test]
+    fn accumulates_fixed_steps() {
+        let mut timestep = Timestep::new(TimestepMode::Fixed(STEP), DEFAULT_MAX_FRAME_DURATION);
+        assert_eq!(timestep.get_step(), Some(STEP));
+        assert_eq!(timestep.accumulate(Duration::from_millis(25)), 2);
+        assert!((timestep.get_alpha() - 0.5).abs() < 1e-6);
+        assert_eq!(timestep.accumulate(Duration::from_millis(4)), 0);
+        assert!((timestep.get_alpha() - 0.9).abs() < 1e-6);
+        assert_eq!(timestep.accumulate(Duration::from_millis(1)), 1);
+        assert_eq!(timestep.get_alpha(), 0.0);
+    }
+
+    #[test]
+    fn clamps_frame_spikes() {
+        let max_frame_duration = Duration::from_millis(50);
+        let mut timestep = Timestep::new(TimestepMode::Fixed(STEP), max_frame_duration);
+        assert_eq!(timestep.accumulate(Duration::from_secs(10)), 5);
+        assert_eq!(timestep.get_alpha(), 0.0);
+    }
+
+    #[test]
+    fn treats_zero_step_as_variable() {
+        let timestep = Timestep::new(
+            TimestepMode::Fixed(Duration::ZERO),
+            DEFAULT_MAX_FRAME_DURATION,
+        );
+        assert_eq!(timestep.get_step(), None);
+    }
+}
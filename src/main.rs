@@ -1,17 +1,91 @@
 mod client;
 mod engine;
 
+use engine::{
+    graphic::{backend::GraphicBackend, ScalingMode},
+    input::replay::ReplayMode,
+    timestep::{TimestepMode, DEFAULT_MAX_FRAME_DURATION},
+};
+use std::{num::NonZeroU32, path::PathBuf, time::Duration};
+
 const SCENE_WIDTH: f32 = 1280.0;
 const SCENE_HEIGHT: f32 = 720.0;
+/// 固定タイムステップの周波数 [Hz]。
+///
+/// Noneであれば可変タイムステップ(既定)で動作する。
+/// 固定する場合は`NonZeroU32::new(60)`のように指定する。
+const FIXED_TIMESTEP_RATE: Option<NonZeroU32> = None;
 
 fn main() {
-    engine::run::<client::GameManager>(engine::ApplicationInfo {
+    // `--record <FILE>`で入力を記録し、`--replay <FILE>`で記録された入力を再生する。
+    // `--headless`を付けるとウィンドウを作らずに再生し、`--capture <PNG>`で最後のフレームを保存する。
+    // `--backends <NAME,...>`で描画に用いるバックエンドの候補を順に指定する(例：`gl,software`)。
+    let mut replay_mode = None;
+    let mut is_headless = false;
+    let mut capture_path = None;
+    let mut graphic_backends = GraphicBackend::DEFAULT_ORDER.to_vec();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" if replay_mode.is_none() => {
+                replay_mode = Some(ReplayMode::Record(next_value(&mut args).into()))
+            }
+            "--replay" if replay_mode.is_none() => {
+                replay_mode = Some(ReplayMode::Replay(next_value(&mut args).into()))
+            }
+            "--headless" => is_headless = true,
+            "--capture" => capture_path = Some(PathBuf::from(next_value(&mut args))),
+            "--backends" => {
+                match next_value(&mut args)
+                    .split(',')
+                    .map(GraphicBackend::from_name)
+                    .collect()
+                {
+                    Some(n) => graphic_backends = n,
+                    None => exit_with_usage(),
+                }
+            }
+            _ => exit_with_usage(),
+        }
+    }
+    // NOTE: ヘッドレスでは記録された入力の再生のみ行える。
+    let is_replay = matches!(replay_mode, Some(ReplayMode::Replay(_)));
+    if (is_headless && !is_replay) || (!is_headless && capture_path.is_some()) {
+        exit_with_usage();
+    }
+
+    let info = engine::ApplicationInfo {
         title: "タイトル",
         scene_width: SCENE_WIDTH as u32,
         scene_height: SCENE_HEIGHT as u32,
         window_width: SCENE_WIDTH,
         window_height: SCENE_HEIGHT,
         is_fullscreen: false,
-    })
-    .unwrap();
+        scaling_mode: ScalingMode::Fit,
+        replay_mode,
+        graphic_backends,
+        timestep_mode: match FIXED_TIMESTEP_RATE {
+            Some(n) => TimestepMode::Fixed(Duration::from_secs(1) / n.get()),
+            None => TimestepMode::default(),
+        },
+        max_frame_duration: DEFAULT_MAX_FRAME_DURATION,
+    };
+    if is_headless {
+        engine::run_headless::<client::GameManager>(info, capture_path.as_deref()).unwrap();
+    } else {
+        engine::run::<client::GameManager>(info).unwrap();
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>) -> String {
+    args.next().unwrap_or_else(|| exit_with_usage())
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!(
+        "usage: prototype [--record <FILE> | --replay <FILE> [--headless [--capture <PNG>]]] \
+         [--backends <NAME,...>]\n\
+         backends: native, vulkan, dx12, metal, gl, software"
+    );
+    std::process::exit(1);
 }
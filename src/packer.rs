@@ -0,0 +1,170 @@
+//! resディレクトリ下のリソースを1ファイルのアーカイブにまとめるツール。
+//!
+//! 使い方：
+//! - `packer pack [RES_DIR] [ARCHIVE]` - RES_DIR下のリソースをARCHIVEにまとめる
+//! - `packer list [ARCHIVE]` - ARCHIVEの目次(マニフェスト)を表示する
+//! - `packer extract [ARCHIVE] [OUT_DIR]` - ARCHIVEのすべてのエントリをOUT_DIR下に展開する
+//! - `packer verify [ARCHIVE] [RES_DIR]` - ARCHIVEのチェックサムを検証する
+//!   (RES_DIRを指定した場合、RES_DIR下のリソースと一致するかも検証する)
+//!
+//! ARCHIVEの既定値は`res.dat`、RES_DIRの既定値は`res`である。
+
+#[path = "engine/resource/archive.rs"]
+mod archive;
+
+use std::{env, error::Error, fs, path::Path, process::ExitCode};
+
+type EError = Box<dyn Error>;
+
+const DEFAULT_RESOURCE_DIRECTORY: &str = "res";
+const DEFAULT_ARCHIVE_PATH: &str = "res.dat";
+
+/// アーカイブにまとめるリソースの拡張子。
+const PACKED_EXTENSIONS: &[&str] = &["png", "otf", "ttf", "wav"];
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let arg = |i: usize, default: &'static str| args.get(i).map_or(default, |n| n.as_str());
+
+    let result = match args.first().map(|n| n.as_str()) {
+        Some("pack") => pack(
+            arg(1, DEFAULT_RESOURCE_DIRECTORY),
+            arg(2, DEFAULT_ARCHIVE_PATH),
+        ),
+        Some("list") => list(arg(1, DEFAULT_ARCHIVE_PATH)),
+        Some("extract") => extract(arg(1, DEFAULT_ARCHIVE_PATH), arg(2, "extracted")),
+        Some("verify") => verify(arg(1, DEFAULT_ARCHIVE_PATH), args.get(2)),
+        _ => {
+            eprintln!("usage: packer <pack|list|extract|verify> [args...]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// resディレクトリ下のリソースをアーカイブにまとめる関数。
+fn pack(res_dir: &str, archive_path: &str) -> Result<(), EError> {
+    let mut names = Vec::new();
+    collect_names(Path::new(res_dir), "", &mut names)?;
+    names.sort();
+
+    let mut entries = Vec::with_capacity(names.len());
+    for name in names {
+        let data = fs::read(Path::new(res_dir).join(&name))?;
+        println!("{:>10} {name}", data.len());
+        entries.push((name, data));
+    }
+
+    archive::write(archive_path, &entries)?;
+    println!("packed {} entries into '{archive_path}'.", entries.len());
+    Ok(())
+}
+
+/// アーカイブの目次を表示する関数。
+fn list(archive_path: &str) -> Result<(), EError> {
+    let archive = archive::Archive::open(archive_path)?;
+    println!("{:>10} {:>10} {:>16} name", "offset", "length", "checksum");
+    for name in archive.names() {
+        let entry = archive.get(name).unwrap();
+        println!(
+            "{:>10} {:>10} {:016x} {name}",
+            entry.offset, entry.length, entry.checksum
+        );
+    }
+    Ok(())
+}
+
+/// アーカイブのすべてのエントリを展開する関数。
+fn extract(archive_path: &str, out_dir: &str) -> Result<(), EError> {
+    let archive = archive::Archive::open(archive_path)?;
+    for name in archive.names() {
+        // NOTE: オープン時にも検証されるが、out_dirの外に書き込まないよう念のため確かめる。
+        if !archive::is_valid_name(name) {
+            return Err(format!("entry '{name}' has an invalid name.").into());
+        }
+        let path = Path::new(out_dir).join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, archive.read(name)?)?;
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// アーカイブを検証する関数。
+///
+/// res_dirを指定した場合、res_dir下のリソースとの過不足・差異も検証する。
+fn verify(archive_path: &str, res_dir: Option<&String>) -> Result<(), EError> {
+    let archive = archive::Archive::open(archive_path)?;
+    let mut errors = Vec::new();
+
+    // NOTE: 読み出し時にチェックサムが検証される。
+    for name in archive.names() {
+        if let Err(e) = archive.read(name) {
+            errors.push(format!("unreadable: {name} ({e})"));
+        }
+    }
+
+    if let Some(res_dir) = res_dir {
+        let mut names = Vec::new();
+        collect_names(Path::new(res_dir), "", &mut names)?;
+        for name in &names {
+            if !archive.contains(name) {
+                errors.push(format!("missing in archive: {name}"));
+            } else if archive
+                .read(name)
+                .is_ok_and(|n| fs::read(Path::new(res_dir).join(name)).ok() != Some(n))
+            {
+                errors.push(format!("outdated in archive: {name}"));
+            }
+        }
+        for name in archive.names() {
+            if !names.iter().any(|n| n == name) {
+                errors.push(format!("missing in '{res_dir}': {name}"));
+            }
+        }
+    }
+
+    for n in &errors {
+        println!("{n}");
+    }
+    if errors.is_empty() {
+        println!("'{archive_path}' is valid.");
+        Ok(())
+    } else {
+        Err(format!("'{archive_path}' has {} problems.", errors.len()).into())
+    }
+}
+
+/// dir下のリソースの相対パスを再帰的に集める関数。
+///
+/// パス区切り文字はOSに依らず'/'とする。
+fn collect_names(dir: &Path, prefix: &str, names: &mut Vec<String>) -> Result<(), EError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry
+            .file_name()
+            .into_string()
+            .map_err(|n| format!("non UTF-8 file name: {}", n.to_string_lossy()))?;
+        let name = format!("{prefix}{file_name}");
+        if entry.file_type()?.is_dir() {
+            collect_names(&entry.path(), &format!("{name}/"), names)?;
+        } else if entry
+            .path()
+            .extension()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| PACKED_EXTENSIONS.contains(&n.to_ascii_lowercase().as_str()))
+        {
+            names.push(name);
+        }
+    }
+    Ok(())
+}